@@ -0,0 +1,54 @@
+use anyhow::{anyhow, Error};
+use duct::cmd;
+use git2::Repository;
+
+use crate::confirm::Confirmation;
+use crate::git;
+use crate::DEVELOP_BRANCH;
+
+/// The git-flow arguments deleting the branch of a release
+fn get_delete_release_args(version: &str) -> Vec<&str> {
+    vec!["release", "delete", "-f", version]
+}
+
+/// Delete the release branch left by a failed run, and go back to develop
+pub fn abort(repository: &Repository, confirmation: &Confirmation) -> Result<(), Error> {
+    let version = git::get_release_in_progress(repository)?
+        .ok_or_else(|| anyhow!("No release is in progress, nothing to abort."))?;
+
+    info!("[Abort] Release {} is in progress.", version);
+    confirmation.ask(&format!("Do you want to delete the {} release?", version))?;
+
+    delete_release(&version)?;
+    info!("[Abort] Release {} was aborted.", version);
+
+    Ok(())
+}
+
+/// Delete the branch of a release, and go back to develop
+pub fn delete_release(version: &str) -> Result<(), Error> {
+    git::git_flow(&get_delete_release_args(version))
+        .stdout_capture()
+        .stderr_capture()
+        .read()?;
+
+    cmd!("git", "checkout", DEVELOP_BRANCH.to_string())
+        .stdout_capture()
+        .stderr_capture()
+        .read()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::abort::get_delete_release_args;
+
+    #[test]
+    fn assembles_the_release_delete_command() {
+        assert_eq!(
+            vec!["release", "delete", "-f", "1.2.0"],
+            get_delete_release_args("1.2.0")
+        );
+    }
+}
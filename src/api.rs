@@ -0,0 +1,225 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Error;
+use gitlab::api::{ApiError, Endpoint};
+use reqwest::Url;
+
+use crate::error::WrError;
+
+/// Whether to log each call to the GitLab API
+static DUMP_API_CALLS: AtomicBool = AtomicBool::new(false);
+
+/// Log each call to the GitLab API, and its outcome
+pub fn set_dump_api_calls(dump: bool) {
+    DUMP_API_CALLS.store(dump, Ordering::Relaxed);
+}
+
+/// Describe the call to an endpoint: its method, its path holding the
+/// project and the ids of the resources, and its query parameters (e.g. the
+/// ref of the pipelines). The body is left out, it may hold secrets.
+pub fn describe_endpoint(endpoint: &impl Endpoint) -> String {
+    let description = format!("{} {}", endpoint.method(), endpoint.endpoint());
+
+    let mut url = Url::parse("https://gitlab.invalid/").unwrap();
+    endpoint.parameters().add_to_url(&mut url);
+
+    match url.query() {
+        Some(query) if !query.is_empty() => format!("{}?{}", description, query),
+        _ => description,
+    }
+}
+
+/// Describe the outcome of a call to the GitLab API
+pub fn describe_outcome<T, E>(result: &Result<T, ApiError<E>>) -> String
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    match result {
+        Ok(_) => format!("returned a {}", std::any::type_name::<T>()),
+        Err(error) => match get_api_error_status(error) {
+            Some(status) => format!("failed with HTTP {} ({})", status, error),
+            None => format!("failed ({})", error),
+        },
+    }
+}
+
+/// Query an endpoint, logging the call and its outcome with the
+/// dump-api-calls flag
+pub fn dump_query<P, T, E>(
+    endpoint: P,
+    query: impl FnOnce(P) -> Result<T, ApiError<E>>,
+) -> Result<T, ApiError<E>>
+where
+    P: Endpoint,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let dump = DUMP_API_CALLS.load(Ordering::Relaxed);
+    let description = describe_endpoint(&endpoint);
+
+    if dump {
+        debug!("[API] {}", description);
+    }
+
+    let result = query(endpoint);
+
+    if dump {
+        debug!("[API] {} {}", description, describe_outcome(&result));
+    }
+
+    result
+}
+
+/// Get the HTTP status returned by the GitLab API, if any
+pub fn get_api_error_status<E>(error: &ApiError<E>) -> Option<u16>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    match error {
+        ApiError::GitlabService { status, .. } => Some(status.as_u16()),
+        ApiError::GitlabWithStatus { status, .. } => Some(status.as_u16()),
+        _ => None,
+    }
+}
+
+/// Whether the GitLab API could not find the requested resource
+pub fn is_not_found<E>(error: &ApiError<E>) -> bool
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    get_api_error_status(error) == Some(404)
+}
+
+/// Map a GitLab API error, detecting when the token is rejected
+pub fn map_token_error<E>(error: ApiError<E>) -> Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    match get_api_error_status(&error) {
+        Some(status @ (401 | 403)) => WrError::GitlabTokenInvalid { status }.into(),
+        _ => error.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use gitlab::api::{ApiError, Endpoint, QueryParams};
+    use http::{Method, StatusCode};
+
+    use crate::api::{describe_endpoint, describe_outcome, dump_query, map_token_error};
+    use crate::error::WrError;
+    use crate::releases::{CreateRelease, ProjectReleases};
+    use crate::trigger::{Trigger, TriggerPipeline};
+
+    fn service_error(status: StatusCode) -> ApiError<std::io::Error> {
+        ApiError::GitlabService {
+            status,
+            data: vec![],
+        }
+    }
+
+    #[test]
+    fn detects_rejected_tokens() {
+        for status in [StatusCode::UNAUTHORIZED, StatusCode::FORBIDDEN] {
+            let error = map_token_error(service_error(status));
+
+            assert!(matches!(
+                error.downcast_ref::<WrError>(),
+                Some(WrError::GitlabTokenInvalid { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn keeps_other_api_errors() {
+        let error = map_token_error(service_error(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(error.downcast_ref::<WrError>().is_none());
+
+        let error = map_token_error(ApiError::<std::io::Error>::Gitlab {
+            msg: "404 Not Found".to_string(),
+        });
+        assert!(error.downcast_ref::<WrError>().is_none());
+    }
+
+    #[test]
+    fn describes_the_endpoints() {
+        let trigger = Trigger {
+            token: "glptt-0123".to_string(),
+            r#ref: "master".to_string(),
+            variables: Default::default(),
+        };
+
+        assert_eq!(
+            "GET projects/aeyoll%2Fwr/releases",
+            describe_endpoint(&ProjectReleases {
+                project: "aeyoll/wr"
+            })
+        );
+        assert_eq!(
+            "POST projects/aeyoll%2Fwr/releases",
+            describe_endpoint(&CreateRelease {
+                project: "aeyoll/wr",
+                tag_name: "1.2.0",
+                name: "1.2.0",
+                description: "",
+                assets: &[],
+            })
+        );
+        assert_eq!(
+            "POST projects/aeyoll%2Fwr/trigger/pipeline",
+            describe_endpoint(&TriggerPipeline {
+                project: "aeyoll/wr",
+                trigger: &trigger,
+            })
+        );
+    }
+
+    #[test]
+    fn describes_the_parameters_of_the_endpoints() {
+        struct Pipelines;
+
+        impl Endpoint for Pipelines {
+            fn method(&self) -> Method {
+                Method::GET
+            }
+
+            fn endpoint(&self) -> Cow<'static, str> {
+                "projects/aeyoll%2Fwr/pipelines".into()
+            }
+
+            fn parameters(&self) -> QueryParams<'_> {
+                let mut params = QueryParams::default();
+                params.push("ref", "release/1.2.0").push("order_by", "id");
+                params
+            }
+        }
+
+        assert_eq!(
+            "GET projects/aeyoll%2Fwr/pipelines?ref=release%2F1.2.0&order_by=id",
+            describe_endpoint(&Pipelines)
+        );
+    }
+
+    #[test]
+    fn describes_the_outcome_of_a_call() {
+        let success: Result<Vec<u64>, ApiError<std::io::Error>> = Ok(vec![1, 2]);
+        assert!(describe_outcome(&success).starts_with("returned"));
+
+        let failure: Result<Vec<u64>, _> = Err(service_error(StatusCode::NOT_FOUND));
+        assert!(describe_outcome(&failure).starts_with("failed with HTTP 404"));
+    }
+
+    #[test]
+    fn passes_the_result_of_the_query_through() {
+        let endpoint = ProjectReleases {
+            project: "aeyoll/wr",
+        };
+
+        let result = dump_query(endpoint, |endpoint| {
+            Ok::<_, ApiError<std::io::Error>>(endpoint.project.to_string())
+        });
+
+        assert_eq!("aeyoll/wr", result.unwrap());
+    }
+}
@@ -0,0 +1,668 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use chrono::{DateTime, FixedOffset, Utc};
+use git2::{Commit, Oid, Repository};
+use semver::Version;
+use serde::Serialize;
+
+use crate::merge_request::MergeRequest;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum, Default)]
+pub enum ChangelogSource {
+    #[default]
+    Commits,
+    Mrs,
+    /// The "Release notes" sections of the merge request descriptions
+    MrDescriptions,
+}
+
+impl FromStr for ChangelogSource {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "commits" => Ok(ChangelogSource::Commits),
+            "mrs" => Ok(ChangelogSource::Mrs),
+            "mr-descriptions" => Ok(ChangelogSource::MrDescriptions),
+            _ => Err("Unknown changelog source"),
+        }
+    }
+}
+
+impl fmt::Display for ChangelogSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// The formats the commit range can be emitted in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum CommitsFormat {
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommitInfo {
+    /// The id of the commit.
+    pub sha: String,
+    /// The name of the commit's author.
+    pub author: String,
+    /// The date of the commit, in RFC 3339 format.
+    pub date: String,
+    /// The first line of the commit message.
+    pub subject: String,
+    /// The rest of the commit message.
+    pub body: String,
+}
+
+impl From<&Commit<'_>> for CommitInfo {
+    fn from(commit: &Commit) -> Self {
+        CommitInfo {
+            sha: commit.id().to_string(),
+            author: commit.author().name().unwrap_or_default().to_string(),
+            date: format_time(&commit.time()),
+            subject: commit.summary().unwrap_or_default().to_string(),
+            body: commit.body().unwrap_or_default().to_string(),
+        }
+    }
+}
+
+/// Format a git time in RFC 3339 format, keeping its offset
+fn format_time(time: &git2::Time) -> String {
+    FixedOffset::east_opt(time.offset_minutes() * 60)
+        .and_then(|offset| {
+            DateTime::from_timestamp(time.seconds(), 0).map(|date| date.with_timezone(&offset))
+        })
+        .map(|date| date.to_rfc3339())
+        .unwrap_or_default()
+}
+
+/// List the commits reachable from any of the `tips`, but not from `from`
+fn walk_commits(
+    repository: &Repository,
+    from: Option<Oid>,
+    tips: &[Oid],
+) -> Result<Vec<CommitInfo>, Error> {
+    let mut revwalk = repository.revwalk()?;
+    for tip in tips {
+        revwalk.push(*tip)?;
+    }
+
+    if let Some(from) = from {
+        revwalk.hide(from)?;
+    }
+
+    revwalk
+        .map(|oid| {
+            let commit = repository.find_commit(oid?)?;
+            Ok(CommitInfo::from(&commit))
+        })
+        .collect()
+}
+
+/// Get the commit the changelog starts from: the given revision, or else the
+/// latest tag
+pub fn get_range_start<'r>(
+    repository: &'r Repository,
+    since: Option<&str>,
+    last_tag: Option<&Version>,
+) -> Result<Option<Commit<'r>>, Error> {
+    let revision = match (since, last_tag) {
+        (Some(since), _) => since.to_string(),
+        (None, Some(last_tag)) => last_tag.to_string(),
+        (None, None) => return Ok(None),
+    };
+
+    let commit = repository.revparse_single(&revision)?.peel_to_commit()?;
+
+    Ok(Some(commit))
+}
+
+/// Get the commits the range ends at: HEAD, along with the base branch when
+/// previewing the release of a branch as if it was merged into it
+pub fn get_range_ends(
+    repository: &Repository,
+    base_branch: Option<&str>,
+) -> Result<Vec<Oid>, Error> {
+    let mut ends = vec![repository.head()?.peel_to_commit()?.id()];
+
+    if let Some(base_branch) = base_branch {
+        let base = repository
+            .revparse_single(base_branch)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| anyhow!("The \"{}\" base branch was not found ({}).", base_branch, e))?;
+        ends.push(base.id());
+    }
+
+    Ok(ends)
+}
+
+/// A range of commits, resolved and walked once to be shared by the
+/// changelog, the stats and the semver type inference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitRange {
+    /// The commit the range starts after, none when there is no previous tag.
+    pub from: Option<Oid>,
+    /// The commits the range ends at, HEAD first.
+    pub to: Vec<Oid>,
+    /// The commits of the range, newest first.
+    pub commits: Vec<CommitInfo>,
+}
+
+impl CommitRange {
+    /// Resolve the range between a commit and HEAD, merged into the base
+    /// branch if any
+    pub fn resolve(
+        repository: &Repository,
+        start: Option<&Commit>,
+        base_branch: Option<&str>,
+    ) -> Result<CommitRange, Error> {
+        let from = start.map(Commit::id);
+        let to = get_range_ends(repository, base_branch)?;
+        let commits = walk_commits(repository, from, &to)?;
+
+        Ok(CommitRange { from, to, commits })
+    }
+
+    /// Resolve the range between a commit and a past commit, to backfill the
+    /// release of the latter
+    pub fn until(
+        repository: &Repository,
+        start: Option<&Commit>,
+        end: &Commit,
+    ) -> Result<CommitRange, Error> {
+        let from = start.map(Commit::id);
+        let to = vec![end.id()];
+        let commits = walk_commits(repository, from, &to)?;
+
+        Ok(CommitRange { from, to, commits })
+    }
+}
+
+/// Get the dates a range of commits spans: after its start commit, up to its
+/// latest end commit
+pub fn get_range_dates(
+    repository: &Repository,
+    range: &CommitRange,
+) -> Result<(Option<DateTime<Utc>>, DateTime<Utc>), Error> {
+    let get_date = |oid: Oid| -> Result<DateTime<Utc>, Error> {
+        let seconds = repository.find_commit(oid)?.time().seconds();
+
+        DateTime::from_timestamp(seconds, 0)
+            .ok_or_else(|| anyhow!("Commit {:.7} has an invalid date.", oid))
+    };
+
+    let since = range.from.map(get_date).transpose()?;
+    let until = range
+        .to
+        .iter()
+        .map(|oid| get_date(*oid))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .max()
+        .unwrap_or_else(Utc::now);
+
+    Ok((since, until))
+}
+
+/// Keep the merge requests merged into one of the branches in the given date
+/// range
+pub fn filter_merge_requests(
+    merge_requests: Vec<MergeRequest>,
+    branches: &[&str],
+    since: Option<DateTime<Utc>>,
+    until: DateTime<Utc>,
+) -> Vec<MergeRequest> {
+    merge_requests
+        .into_iter()
+        .filter(|merge_request| branches.contains(&merge_request.target_branch.as_str()))
+        .filter(|merge_request| match merge_request.merged_at {
+            Some(merged_at) => since.is_none_or(|since| merged_at > since) && merged_at <= until,
+            None => false,
+        })
+        .collect()
+}
+
+/// Format a list of commits into release notes
+pub fn format_commits(commits: &[CommitInfo]) -> String {
+    commits
+        .iter()
+        .map(|commit| format!("* {} ({:.7})", commit.subject, commit.sha))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Emit a list of commits in a format external tools can read
+pub fn emit_commits(commits: &[CommitInfo], format: CommitsFormat) -> Result<String, Error> {
+    match format {
+        CommitsFormat::Json => Ok(serde_json::to_string_pretty(commits)?),
+    }
+}
+
+/// List the distinct authors of some commits, by name
+pub fn get_contributors(commits: &[CommitInfo]) -> Vec<String> {
+    let contributors: BTreeSet<&str> = commits
+        .iter()
+        .map(|commit| commit.author.as_str())
+        .filter(|author| !author.is_empty())
+        .collect();
+
+    contributors.into_iter().map(str::to_string).collect()
+}
+
+/// Append the contributors of some commits to release notes
+pub fn append_contributors(notes: &str, commits: &[CommitInfo]) -> String {
+    let contributors = get_contributors(commits);
+
+    if contributors.is_empty() {
+        return notes.to_string();
+    }
+
+    let section = contributors
+        .iter()
+        .map(|contributor| format!("* {}", contributor))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    format!("{}\n\n## Contributors\n{}", notes, section)
+}
+
+/// Format a list of merge requests into GitHub-style release notes
+pub fn format_merge_requests(merge_requests: &[MergeRequest]) -> String {
+    let mut notes = vec!["## What's Changed".to_string()];
+
+    notes.extend(merge_requests.iter().map(|merge_request| {
+        format!(
+            "* {} by @{} in !{}",
+            merge_request.title, merge_request.author.username, merge_request.iid
+        )
+    }));
+
+    notes.join("\n")
+}
+
+/// The heading of the section of a merge request description holding its
+/// release notes
+const RELEASE_NOTES_HEADING: &str = "release notes";
+
+/// Parse a markdown heading into its level and its title
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let line = line.trim();
+    let level = line.chars().take_while(|c| *c == '#').count();
+
+    match level {
+        1..=6 => Some((level, line[level..].trim())),
+        _ => None,
+    }
+}
+
+/// Extract the "Release notes" section of a merge request description: the
+/// lines following its heading, up to the next heading of the same level or
+/// higher
+pub fn extract_release_notes(description: &str) -> Option<String> {
+    let mut lines = description.lines();
+    let level = lines.by_ref().find_map(|line| {
+        parse_heading(line)
+            .filter(|(_, title)| title.eq_ignore_ascii_case(RELEASE_NOTES_HEADING))
+            .map(|(level, _)| level)
+    })?;
+
+    let notes = lines
+        .take_while(|line| !matches!(parse_heading(line), Some((other, _)) if other <= level))
+        .collect::<Vec<&str>>()
+        .join("\n");
+    let notes = notes.trim();
+
+    match notes.is_empty() {
+        true => None,
+        false => Some(notes.to_string()),
+    }
+}
+
+/// Format the release notes of some merge requests, from the "Release notes"
+/// section of their descriptions, skipping the ones without any
+pub fn format_merge_request_descriptions(merge_requests: &[MergeRequest]) -> String {
+    let mut notes = vec!["## What's Changed".to_string()];
+
+    notes.extend(merge_requests.iter().filter_map(|merge_request| {
+        let release_notes = extract_release_notes(merge_request.description.as_deref()?)?;
+
+        Some(format!(
+            "\n### {} (!{})\n{}",
+            merge_request.title, merge_request.iid, release_notes
+        ))
+    }));
+
+    notes.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use semver::Version;
+
+    use crate::changelog::{
+        append_contributors, emit_commits, extract_release_notes, filter_merge_requests,
+        format_commits, format_merge_request_descriptions, format_merge_requests, format_time,
+        get_contributors, get_range_dates, get_range_ends, get_range_start, CommitInfo,
+        CommitRange, CommitsFormat,
+    };
+    use crate::merge_request::{Author, MergeRequest};
+    use crate::test_utils::{commit, commit_file, init_repository};
+
+    fn merge_request(iid: u64, title: &str, username: &str, day: u32) -> MergeRequest {
+        MergeRequest {
+            iid,
+            title: title.to_string(),
+            author: Author {
+                username: username.to_string(),
+            },
+            description: None,
+            target_branch: "develop".to_string(),
+            merged_at: Utc.with_ymd_and_hms(2024, 5, day, 12, 0, 0).single(),
+            web_url: format!("https://gitlab.com/aeyoll/wr/-/merge_requests/{}", iid),
+        }
+    }
+
+    #[test]
+    fn lists_the_commits_since_a_commit() {
+        let (_dir, repository) = init_repository("main");
+        let from = repository.head().unwrap().target().unwrap();
+        commit(&repository, "feat: add a feature");
+        commit(&repository, "fix: fix a bug");
+        let start = repository.find_commit(from).unwrap();
+
+        let subjects: Vec<String> = CommitRange::resolve(&repository, Some(&start), None)
+            .unwrap()
+            .commits
+            .into_iter()
+            .map(|commit| commit.subject)
+            .collect();
+
+        assert_eq!(vec!["fix: fix a bug", "feat: add a feature"], subjects);
+    }
+
+    #[test]
+    fn lists_the_commits_until_a_past_commit() {
+        let (_dir, repository) = init_repository("main");
+        let from = repository.head().unwrap().target().unwrap();
+        let past = commit(&repository, "feat: add a feature");
+        commit(&repository, "fix: fix a bug");
+        let start = repository.find_commit(from).unwrap();
+        let end = repository.find_commit(past).unwrap();
+
+        let range = CommitRange::until(&repository, Some(&start), &end).unwrap();
+
+        assert_eq!(vec![past], range.to);
+        assert_eq!(
+            vec!["feat: add a feature"],
+            range
+                .commits
+                .into_iter()
+                .map(|commit| commit.subject)
+                .collect::<Vec<String>>()
+        );
+    }
+
+    #[test]
+    fn spans_the_dates_of_the_range() {
+        let (_dir, repository) = init_repository("main");
+        let from = repository.head().unwrap().target().unwrap();
+        let past = commit(&repository, "feat: add a feature");
+        let date = |oid| {
+            DateTime::from_timestamp(repository.find_commit(oid).unwrap().time().seconds(), 0)
+                .unwrap()
+        };
+        let range = CommitRange {
+            from: Some(from),
+            to: vec![past],
+            commits: vec![],
+        };
+
+        assert_eq!(
+            (Some(date(from)), date(past)),
+            get_range_dates(&repository, &range).unwrap()
+        );
+    }
+
+    #[test]
+    fn lists_the_commits_since_the_latest_tag_or_a_revision() {
+        let (_dir, repository) = init_repository("main");
+        let tagged = commit(&repository, "feat: add a feature");
+        let tagged = repository.find_object(tagged, None).unwrap();
+        repository.tag_lightweight("1.0.0", &tagged, false).unwrap();
+        commit(&repository, "fix: fix a bug");
+        commit(&repository, "docs: document the fix");
+
+        let notes = |since: Option<&str>| {
+            let start = get_range_start(&repository, since, Some(&Version::new(1, 0, 0))).unwrap();
+            let commits = CommitRange::resolve(&repository, start.as_ref(), None)
+                .unwrap()
+                .commits;
+
+            commits
+                .into_iter()
+                .map(|commit| commit.subject)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            vec!["docs: document the fix", "fix: fix a bug"],
+            notes(None)
+        );
+        assert_eq!(vec!["docs: document the fix"], notes(Some("HEAD~1")));
+        assert!(get_range_start(&repository, None, None).unwrap().is_none());
+        assert!(get_range_start(&repository, Some("unknown"), None).is_err());
+    }
+
+    #[test]
+    fn lists_the_commits_as_if_merged_into_the_base_branch() {
+        let (_dir, repository) = init_repository("develop");
+        let tagged = commit(&repository, "feat: add a feature");
+        let tagged = repository.find_object(tagged, None).unwrap();
+        repository.tag_lightweight("1.0.0", &tagged, false).unwrap();
+        commit(&repository, "fix: fix a bug on develop");
+
+        let develop = repository.head().unwrap().peel_to_commit().unwrap();
+        repository
+            .branch("feature", &tagged.peel_to_commit().unwrap(), false)
+            .unwrap();
+        repository.set_head("refs/heads/feature").unwrap();
+        commit(&repository, "feat: add another feature");
+
+        let subjects = |base_branch: Option<&str>| {
+            let start = get_range_start(&repository, None, Some(&Version::new(1, 0, 0))).unwrap();
+            let mut subjects: Vec<String> =
+                CommitRange::resolve(&repository, start.as_ref(), base_branch)
+                    .unwrap()
+                    .commits
+                    .into_iter()
+                    .map(|commit| commit.subject)
+                    .collect();
+            subjects.sort();
+            subjects
+        };
+
+        assert_eq!(vec!["feat: add another feature"], subjects(None));
+        assert_eq!(
+            vec!["feat: add another feature", "fix: fix a bug on develop"],
+            subjects(Some("develop"))
+        );
+        assert_eq!(
+            vec![repository.head().unwrap().target().unwrap(), develop.id()],
+            get_range_ends(&repository, Some("develop")).unwrap()
+        );
+        assert!(get_range_ends(&repository, Some("unknown")).is_err());
+    }
+
+    #[test]
+    fn collects_the_distinct_contributors_of_the_range() {
+        let (_dir, repository) = init_repository("main");
+        let from = commit_file(&repository, "carol", "a.txt", "feat: add a feature");
+        commit_file(&repository, "bob", "b.txt", "fix: fix a bug");
+        commit_file(&repository, "alice", "c.txt", "docs: document the fix");
+        commit_file(&repository, "bob", "d.txt", "fix: fix another bug");
+        let start = repository.find_commit(from).unwrap();
+
+        let commits = CommitRange::resolve(&repository, Some(&start), None)
+            .unwrap()
+            .commits;
+
+        assert_eq!(vec!["alice", "bob"], get_contributors(&commits));
+        assert_eq!(
+            "* fix: fix another bug\n\n## Contributors\n* alice\n* bob",
+            append_contributors("* fix: fix another bug", &commits)
+        );
+        assert_eq!("No changes", append_contributors("No changes", &[]));
+    }
+
+    #[test]
+    fn formats_commits() {
+        let commits = vec![CommitInfo {
+            sha: "0123456789abcdef".to_string(),
+            author: "wr".to_string(),
+            date: "2024-06-01T12:00:00+02:00".to_string(),
+            subject: "fix: fix a bug".to_string(),
+            body: "".to_string(),
+        }];
+
+        assert_eq!("* fix: fix a bug (0123456)", format_commits(&commits));
+    }
+
+    #[test]
+    fn emits_the_commits_as_json() {
+        let commits = vec![
+            CommitInfo {
+                sha: "0123456789abcdef".to_string(),
+                author: "alice".to_string(),
+                date: "2024-06-01T12:00:00+02:00".to_string(),
+                subject: "feat: add a feature".to_string(),
+                body: "Closes #12".to_string(),
+            },
+            CommitInfo {
+                sha: "fedcba9876543210".to_string(),
+                author: "bob".to_string(),
+                date: "2024-05-31T09:30:00+00:00".to_string(),
+                subject: "fix: fix a bug".to_string(),
+                body: "".to_string(),
+            },
+        ];
+
+        let json: serde_json::Value =
+            serde_json::from_str(&emit_commits(&commits, CommitsFormat::Json).unwrap()).unwrap();
+
+        assert_eq!(
+            serde_json::json!([
+                {
+                    "sha": "0123456789abcdef",
+                    "author": "alice",
+                    "date": "2024-06-01T12:00:00+02:00",
+                    "subject": "feat: add a feature",
+                    "body": "Closes #12"
+                },
+                {
+                    "sha": "fedcba9876543210",
+                    "author": "bob",
+                    "date": "2024-05-31T09:30:00+00:00",
+                    "subject": "fix: fix a bug",
+                    "body": ""
+                }
+            ]),
+            json
+        );
+    }
+
+    #[test]
+    fn formats_the_commit_time_with_its_offset() {
+        let time = git2::Time::new(1717243200, 120);
+
+        assert_eq!("2024-06-01T14:00:00+02:00", format_time(&time));
+    }
+
+    #[test]
+    fn formats_merge_requests_into_grouped_notes() {
+        let merge_requests = vec![
+            merge_request(12, "Add a feature", "alice", 2),
+            merge_request(13, "Fix a bug", "bob", 3),
+        ];
+
+        assert_eq!(
+            "## What's Changed\n* Add a feature by @alice in !12\n* Fix a bug by @bob in !13",
+            format_merge_requests(&merge_requests)
+        );
+    }
+
+    #[test]
+    fn extracts_the_release_notes_section() {
+        let description = "## Summary\nRefactor the parser.\n\n## Release notes\n\n* Faster parsing\n* Better errors\n\n### Details\nMore.\n\n## Checklist\n- [x] Tests";
+
+        assert_eq!(
+            Some("* Faster parsing\n* Better errors\n\n### Details\nMore.".to_string()),
+            extract_release_notes(description)
+        );
+        assert_eq!(
+            Some("Breaking: drop Node 16.".to_string()),
+            extract_release_notes("# RELEASE NOTES\nBreaking: drop Node 16.")
+        );
+    }
+
+    #[test]
+    fn ignores_descriptions_without_release_notes() {
+        assert_eq!(
+            None,
+            extract_release_notes("## Summary\nRelease notes below.")
+        );
+        assert_eq!(
+            None,
+            extract_release_notes("## Release notes\n\n## Checklist\n- [x] Tests")
+        );
+        assert_eq!(None, extract_release_notes(""));
+    }
+
+    #[test]
+    fn formats_the_release_notes_of_the_merge_requests() {
+        let mut with_notes = merge_request(12, "Add a feature", "alice", 2);
+        with_notes.description = Some("## Release notes\nA new feature.".to_string());
+        let mut without_notes = merge_request(13, "Fix a bug", "bob", 3);
+        without_notes.description = Some("Fixes #42".to_string());
+        let without_description = merge_request(14, "Chore", "carol", 4);
+
+        assert_eq!(
+            "## What's Changed\n\n### Add a feature (!12)\nA new feature.",
+            format_merge_request_descriptions(&[with_notes, without_notes, without_description])
+        );
+    }
+
+    #[test]
+    fn keeps_merge_requests_merged_in_the_range() {
+        let mut unmerged = merge_request(10, "Draft", "alice", 1);
+        unmerged.merged_at = None;
+        let mut hotfix = merge_request(14, "Hotfix", "dave", 4);
+        hotfix.target_branch = "master".to_string();
+        let mut feature = merge_request(15, "Feature", "erin", 4);
+        feature.target_branch = "feature/x".to_string();
+        let merge_requests = vec![
+            unmerged,
+            merge_request(11, "Before", "alice", 1),
+            merge_request(12, "During", "bob", 3),
+            merge_request(13, "After", "carol", 9),
+            hotfix,
+            feature,
+        ];
+
+        let iids: Vec<u64> = filter_merge_requests(
+            merge_requests,
+            &["master", "develop"],
+            Utc.with_ymd_and_hms(2024, 5, 2, 0, 0, 0).single(),
+            Utc.with_ymd_and_hms(2024, 5, 5, 0, 0, 0).unwrap(),
+        )
+        .into_iter()
+        .map(|merge_request| merge_request.iid)
+        .collect();
+
+        assert_eq!(vec![12, 14], iids);
+    }
+}
@@ -0,0 +1,103 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Error;
+
+/// The dotenv file written in GitLab CI, to declare as a dotenv report
+/// artifact of the job
+pub const GITLAB_DOTENV_FILE: &str = "wr.env";
+
+/// The CI system running wr, and the file its job outputs go to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CiSystem {
+    /// GitHub Actions, appending to the file of `GITHUB_OUTPUT`.
+    GithubActions(PathBuf),
+    /// GitLab CI, writing a dotenv artifact.
+    Gitlab(PathBuf),
+}
+
+/// Detect the CI system from its environment variables
+fn detect<F>(var: F) -> Option<CiSystem>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if let Some(output) = var("GITHUB_OUTPUT") {
+        return Some(CiSystem::GithubActions(PathBuf::from(output)));
+    }
+
+    var("GITLAB_CI").map(|_| CiSystem::Gitlab(PathBuf::from(GITLAB_DOTENV_FILE)))
+}
+
+/// Format the outputs as "key=value" lines
+fn format_outputs(outputs: &[(&str, String)]) -> String {
+    outputs
+        .iter()
+        .map(|(key, value)| format!("{}={}\n", key, value))
+        .collect()
+}
+
+/// Write the outputs of the release for the next steps of the CI job
+pub fn write(outputs: &[(&str, String)]) -> Result<(), Error> {
+    let content = format_outputs(outputs);
+
+    match detect(|name| std::env::var(name).ok()) {
+        Some(CiSystem::GithubActions(path)) => {
+            debug!("[CI] Appending the outputs to {}.", path.display());
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            file.write_all(content.as_bytes())?;
+        }
+        Some(CiSystem::Gitlab(path)) => {
+            debug!("[CI] Writing the outputs to {}.", path.display());
+            fs::write(path, content)?;
+        }
+        None => warn!("[CI] No CI system was detected, the outputs are not written."),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use crate::ci_output::{detect, format_outputs, CiSystem};
+
+    fn detect_from(vars: &[(&str, &str)]) -> Option<CiSystem> {
+        let vars: HashMap<&str, &str> = vars.iter().copied().collect();
+
+        detect(|name| vars.get(name).map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn formats_the_outputs() {
+        let outputs = vec![
+            ("version", "1.2.0".to_string()),
+            ("environment", "Production".to_string()),
+            (
+                "pipeline_url",
+                "https://gitlab.com/aeyoll/wr/-/pipelines/42".to_string(),
+            ),
+        ];
+
+        assert_eq!(
+            "version=1.2.0\nenvironment=Production\npipeline_url=https://gitlab.com/aeyoll/wr/-/pipelines/42\n",
+            format_outputs(&outputs)
+        );
+        assert_eq!("", format_outputs(&[]));
+    }
+
+    #[test]
+    fn selects_the_output_file_of_the_ci_system() {
+        assert_eq!(
+            Some(CiSystem::GithubActions(PathBuf::from("/tmp/output"))),
+            detect_from(&[("GITHUB_OUTPUT", "/tmp/output"), ("GITLAB_CI", "true")])
+        );
+        assert_eq!(
+            Some(CiSystem::Gitlab(PathBuf::from("wr.env"))),
+            detect_from(&[("GITLAB_CI", "true")])
+        );
+        assert_eq!(None, detect_from(&[]));
+    }
+}
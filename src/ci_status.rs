@@ -0,0 +1,68 @@
+use anyhow::Error;
+use gitlab::Gitlab;
+use serde_json::Value;
+
+use crate::error::WrError;
+use crate::jobs::get_latest_pipeline;
+use crate::pipeline::StatusState;
+use crate::DEVELOP_BRANCH;
+
+/// Parse the status of a pipeline, as returned by the API
+fn parse_status(status: &str) -> Option<StatusState> {
+    serde_json::from_value(Value::String(status.to_string())).ok()
+}
+
+/// Check that a pipeline succeeded
+fn check_ci_green(status: &str, url: &str) -> Result<(), WrError> {
+    match parse_status(status) {
+        Some(StatusState::Success) => Ok(()),
+        _ => Err(WrError::DevelopCiNotGreen {
+            status: status.to_string(),
+            url: url.to_string(),
+        }),
+    }
+}
+
+/// Require the latest pipeline of the develop branch to be green, unless
+/// forced
+pub fn require_ci_green(gitlab: &Gitlab, force: bool) -> Result<(), Error> {
+    let pipeline = get_latest_pipeline(gitlab, &DEVELOP_BRANCH)?;
+
+    match check_ci_green(&pipeline.status, &pipeline.web_url) {
+        Ok(()) => {
+            info!("[Setup] The latest pipeline of develop is green.");
+            Ok(())
+        }
+        Err(error) if force => {
+            warn!("[Setup] {} But force flag has been passed.", error);
+            Ok(())
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ci_status::check_ci_green;
+    use crate::error::WrError;
+
+    const URL: &str = "https://gitlab.com/aeyoll/wr/-/pipelines/42";
+
+    #[test]
+    fn accepts_a_green_pipeline() {
+        assert!(check_ci_green("success", URL).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_pipeline_not_green() {
+        for status in [
+            "failed", "running", "pending", "canceled", "manual", "unknown",
+        ] {
+            assert!(matches!(
+                check_ci_green(status, URL),
+                Err(WrError::DevelopCiNotGreen { status: actual, url })
+                    if actual == status && url == URL
+            ));
+        }
+    }
+}
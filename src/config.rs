@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{env, fmt, fs};
+
+use anyhow::{anyhow, Error};
+use clap::ValueSource;
+use git2::Repository;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::environment::Environment;
+use crate::steps::Step;
+
+/// The name of the configuration file, at the root of the repository
+pub const CONFIG_FILE: &str = "wr.toml";
+
+/// Get the path of the configuration file, at the root of the repository the
+/// directory is in. Outside of a repository, the directory itself is used.
+fn get_config_path(dir: PathBuf) -> PathBuf {
+    let root = Repository::discover(&dir)
+        .ok()
+        .and_then(|repository| repository.workdir().map(Path::to_path_buf))
+        .unwrap_or(dir);
+
+    root.join(CONFIG_FILE)
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Config {
+    /// The configuration of each environment, by lowercase name.
+    pub environments: HashMap<String, EnvironmentConfig>,
+    /// The custom steps to run during the release.
+    pub steps: Vec<Step>,
+    /// The file holding the version number, bumped along with the tag.
+    pub version_file: Option<String>,
+    /// The short names of the environments, e.g. `prod = "production"`.
+    pub aliases: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct EnvironmentConfig {
+    /// The names of the jobs to play when deploying.
+    pub deploy_jobs: Vec<String>,
+    /// The prerelease channel of the releases (e.g. "beta" for
+    /// "1.3.0-beta.1"), stable if not set.
+    pub channel: Option<String>,
+    /// The dotenv file of the variables passed to the pipeline trigger and to
+    /// the steps, e.g. ".env.prod".
+    pub env_file: Option<String>,
+}
+
+impl Config {
+    /// Parse a configuration
+    pub fn parse(content: &str) -> Result<Config, Error> {
+        toml::from_str(content).map_err(|e| anyhow!("Invalid {} file ({}).", CONFIG_FILE, e))
+    }
+
+    /// Load the configuration file from the root of the repository, if any
+    pub fn load() -> Result<Config, Error> {
+        let path = get_config_path(env::current_dir()?);
+
+        if !path.exists() {
+            debug!(
+                "No {} file found, using the default configuration.",
+                CONFIG_FILE
+            );
+            return Ok(Config::default());
+        }
+
+        debug!("Loading the configuration from {}.", path.display());
+        Config::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Get the configuration of an environment
+    pub fn get_environment(&self, environment: &Environment) -> Option<&EnvironmentConfig> {
+        self.environments
+            .get(&environment.to_string().to_lowercase())
+    }
+
+    /// Get the prerelease channel of an environment, if any
+    pub fn get_channel(&self, environment: &Environment) -> Option<&str> {
+        self.get_environment(environment)?.channel.as_deref()
+    }
+
+    /// Get the variables file of an environment, if any
+    pub fn get_env_file(&self, environment: &Environment) -> Option<&str> {
+        self.get_environment(environment)?.env_file.as_deref()
+    }
+}
+
+/// Where a setting value comes from
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Source {
+    Flag,
+    Env,
+    Config,
+    Glab,
+    Default,
+}
+
+impl From<ValueSource> for Source {
+    fn from(value_source: ValueSource) -> Self {
+        match value_source {
+            ValueSource::CommandLine => Source::Flag,
+            ValueSource::EnvVariable => Source::Env,
+            _ => Source::Default,
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+/// A resolved setting, with its provenance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    pub name: &'static str,
+    pub value: String,
+    pub source: Source,
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<16} {} ({})", self.name, self.value, self.source)
+    }
+}
+
+/// Resolve a value from an environment variable, with a default value
+pub fn resolve_env(name: &str, default: &str) -> (String, Source) {
+    match env::var(name) {
+        Ok(value) => (value, Source::Env),
+        Err(_) => (default.to_string(), Source::Default),
+    }
+}
+
+/// Mask a secret value, to be able to display it
+pub fn mask(secret: &str) -> String {
+    match secret.chars().count() {
+        0 => "(empty)".to_string(),
+        1..=8 => "****".to_string(),
+        _ => format!("{}****", secret.chars().take(4).collect::<String>()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::config::{get_config_path, mask, resolve_env, Config, Source, CONFIG_FILE};
+    use crate::environment::Environment;
+    use crate::test_utils::{init_repository, with_env};
+
+    #[test]
+    fn reads_the_configuration_at_the_root_of_the_repository() {
+        let (dir, _repository) = init_repository("main");
+        let subdirectory = dir.path().join("src");
+        fs::create_dir(&subdirectory).unwrap();
+
+        let path = get_config_path(subdirectory);
+
+        assert_eq!(
+            dir.path().canonicalize().unwrap(),
+            path.parent().unwrap().canonicalize().unwrap()
+        );
+        assert!(path.ends_with(CONFIG_FILE));
+
+        let outside = tempfile::TempDir::new().unwrap();
+        assert_eq!(
+            outside.path().join(CONFIG_FILE),
+            get_config_path(outside.path().to_path_buf())
+        );
+    }
+
+    #[test]
+    fn parses_the_deploy_jobs_of_an_environment() {
+        let config = Config::parse(
+            r#"
+[environments.production]
+deploy_jobs = ["deploy_eu", "deploy_us"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["deploy_eu", "deploy_us"],
+            Environment::Production
+                .get_deploy_job_names(&config)
+                .unwrap()
+        );
+        assert_eq!(
+            vec!["deploy_staging"],
+            Environment::Staging.get_deploy_job_names(&config).unwrap()
+        );
+    }
+
+    #[test]
+    fn parses_the_variables_file_of_an_environment() {
+        let config = Config::parse(
+            r#"
+[environments.production]
+env_file = ".env.prod"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(".env.prod"),
+            config.get_env_file(&Environment::Production)
+        );
+        assert_eq!(None, config.get_env_file(&Environment::Staging));
+    }
+
+    #[test]
+    fn resolves_a_value_from_the_environment() {
+        with_env(&[("WR_TEST_RESOLVE_ENV", "gitlab.example.com")], || {
+            assert_eq!(
+                ("gitlab.example.com".to_string(), Source::Env),
+                resolve_env("WR_TEST_RESOLVE_ENV", "gitlab.com")
+            );
+        });
+        assert_eq!(
+            ("gitlab.com".to_string(), Source::Default),
+            resolve_env("WR_TEST_UNDEFINED_ENV", "gitlab.com")
+        );
+    }
+
+    #[test]
+    fn masks_secrets() {
+        assert_eq!("(empty)", mask(""));
+        assert_eq!("****", mask("short"));
+        assert_eq!("glpa****", mask("glpat-012345678012345678"));
+        assert_eq!("été-****", mask("été-012345678"));
+    }
+}
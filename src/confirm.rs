@@ -0,0 +1,190 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::os::fd::{AsFd, AsRawFd};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Error};
+use dialoguer::{theme::ColorfulTheme, Confirm};
+
+use crate::environment::Environment;
+
+/// When to ask for a confirmation before releasing
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum, Default)]
+pub enum ConfirmPolicy {
+    /// Always ask
+    Always,
+    /// Only ask for production releases
+    #[default]
+    Prod,
+    /// Never ask
+    Never,
+}
+
+impl ConfirmPolicy {
+    /// Whether a release to an environment must be confirmed
+    pub fn requires_confirmation(&self, environment: Environment) -> bool {
+        match self {
+            ConfirmPolicy::Always => true,
+            ConfirmPolicy::Prod => environment == Environment::Production,
+            ConfirmPolicy::Never => false,
+        }
+    }
+}
+
+impl fmt::Display for ConfirmPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+pub struct Confirmation {
+    /// Whether to ask at all, from the confirmation policy.
+    pub required: bool,
+    /// The answer selected when the user just presses enter, or doesn't answer
+    /// in time.
+    pub default: bool,
+    /// How long to wait for an answer.
+    pub timeout: Option<Duration>,
+}
+
+impl Confirmation {
+    /// Ask for a confirmation, failing if the user refuses
+    pub fn ask(&self, prompt: &str) -> Result<(), Error> {
+        if !self.required {
+            debug!("No confirmation required, continuing.");
+            return Ok(());
+        }
+
+        let answer = match self.timeout {
+            Some(timeout) => ask_with_timeout(prompt, self.default, timeout)?,
+            None => Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(prompt)
+                .default(self.default)
+                .interact_opt()
+                .unwrap(),
+        };
+
+        match answer {
+            Some(true) => Ok(()),
+            Some(false) => Err(anyhow!("Cancelling.")),
+            None => Err(anyhow!("Aborting.")),
+        }
+    }
+}
+
+/// Ask a yes/no question, falling back to the default answer once the
+/// timeout is elapsed. Stdin is polled instead of being read from another
+/// thread, which would keep reading after the timeout and steal the answers
+/// to the next prompts.
+fn ask_with_timeout(prompt: &str, default: bool, timeout: Duration) -> Result<Option<bool>, Error> {
+    let deadline = Instant::now() + timeout;
+    let choices = if default { "[Y/n]" } else { "[y/N]" };
+
+    loop {
+        eprint!("{} {} ", prompt, choices);
+        io::stderr().flush()?;
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if !wait_for_input(&io::stdin(), remaining)? {
+            eprintln!();
+            warn!(
+                "No answer after {} seconds, defaulting to \"{}\".",
+                timeout.as_secs(),
+                if default { "yes" } else { "no" }
+            );
+            return Ok(Some(default));
+        }
+
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        if let Some(answer) = parse_answer(&line, default) {
+            return Ok(Some(answer));
+        }
+    }
+}
+
+/// Wait for an input to be readable, without reading it
+fn wait_for_input(input: &impl AsFd, timeout: Duration) -> Result<bool, Error> {
+    let mut poll_fd = libc::pollfd {
+        fd: input.as_fd().as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+
+    // SAFETY: the single descriptor polled is borrowed for the whole call
+    let ready = unsafe { libc::poll(&mut poll_fd, 1, timeout) };
+    if ready < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    Ok(ready > 0)
+}
+
+/// Read the answer to a yes/no question, the default one when empty
+fn parse_answer(line: &str, default: bool) -> Option<bool> {
+    match line.trim().to_lowercase().as_str() {
+        "" => Some(default),
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::time::Duration;
+
+    use crate::confirm::{parse_answer, wait_for_input, ConfirmPolicy, Confirmation};
+    use crate::environment::Environment;
+
+    #[test]
+    fn resolves_the_confirmation_policy_per_environment() {
+        assert!(ConfirmPolicy::Always.requires_confirmation(Environment::Production));
+        assert!(ConfirmPolicy::Always.requires_confirmation(Environment::Staging));
+        assert!(ConfirmPolicy::Prod.requires_confirmation(Environment::Production));
+        assert!(!ConfirmPolicy::Prod.requires_confirmation(Environment::Staging));
+        assert!(!ConfirmPolicy::Never.requires_confirmation(Environment::Production));
+        assert!(!ConfirmPolicy::Never.requires_confirmation(Environment::Staging));
+    }
+
+    #[test]
+    fn does_not_prompt_when_no_confirmation_is_required() {
+        let confirmation = Confirmation {
+            required: false,
+            default: false,
+            timeout: None,
+        };
+
+        assert!(confirmation.ask("Do you want to continue?").is_ok());
+    }
+
+    #[test]
+    fn gives_up_waiting_for_an_answer_after_the_timeout() {
+        let (reader, _writer) = io::pipe().unwrap();
+
+        assert!(!wait_for_input(&reader, Duration::from_millis(10)).unwrap());
+    }
+
+    #[test]
+    fn detects_an_answer_given_in_time() {
+        let (reader, mut writer) = io::pipe().unwrap();
+        writer.write_all(b"n\n").unwrap();
+
+        assert!(wait_for_input(&reader, Duration::from_secs(1)).unwrap());
+    }
+
+    #[test]
+    fn parses_the_answers() {
+        assert_eq!(Some(true), parse_answer("y\n", false));
+        assert_eq!(Some(true), parse_answer("Yes", false));
+        assert_eq!(Some(false), parse_answer("n", true));
+        assert_eq!(Some(true), parse_answer("\n", true));
+        assert_eq!(Some(false), parse_answer("", false));
+        assert_eq!(None, parse_answer("maybe", true));
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::{self, IsTerminal, Write};
+use std::thread::sleep;
+use std::time::Duration;
+
+use anyhow::{anyhow, Error};
+use gitlab::{
+    api::{projects, Query},
+    Gitlab,
+};
+
+use crate::api::dump_query;
+use crate::job::Job;
+use crate::jobs::{format_jobs_with, get_jobs, get_latest_pipeline};
+use crate::pipeline::StatusState;
+use crate::PROJECT_NAME;
+
+/// Color a job status, with the ANSI escape codes of the terminal
+fn paint_status(status: StatusState) -> String {
+    let color = match status {
+        StatusState::Success => "32",
+        StatusState::Failed => "31",
+        StatusState::Running | StatusState::Pending | StatusState::Preparing => "33",
+        StatusState::Manual | StatusState::Scheduled => "36",
+        _ => "90",
+    };
+
+    format!("\x1b[{}m{:?}\x1b[0m", color, status)
+}
+
+/// Whether a pipeline won't change its status anymore
+fn is_pipeline_over(status: &str) -> bool {
+    matches!(status, "success" | "failed" | "canceled" | "skipped")
+}
+
+/// Render a frame of the dashboard: the pipeline, followed by the table of
+/// its jobs
+fn render_frame(pipeline_id: u64, pipeline_status: &str, jobs: &[Job]) -> Vec<String> {
+    let mut lines = vec![format!("Pipeline {} ({})", pipeline_id, pipeline_status)];
+    lines.extend(format_jobs_with(jobs, paint_status));
+
+    lines
+}
+
+/// Show the jobs of the latest pipeline of a ref, redrawn on an interval
+/// until the pipeline is over
+pub fn show(gitlab: &Gitlab, pipeline_ref: &str, interval: Duration) -> Result<(), Error> {
+    if !io::stdout().is_terminal() {
+        return Err(anyhow!("[Dashboard] The dashboard needs a terminal."));
+    }
+
+    let mut pipeline = get_latest_pipeline(gitlab, pipeline_ref)?;
+    let mut stdout = io::stdout();
+    let mut drawn_lines = 0;
+
+    loop {
+        let jobs = get_jobs(gitlab, pipeline.id)?;
+        let frame = render_frame(pipeline.id, &pipeline.status, &jobs);
+
+        // Move back to the top of the previous frame, and clear it
+        if drawn_lines > 0 {
+            write!(stdout, "\x1b[{}A\x1b[J", drawn_lines)?;
+        }
+        for line in &frame {
+            writeln!(stdout, "{}", line)?;
+        }
+        stdout.flush()?;
+        drawn_lines = frame.len();
+
+        if is_pipeline_over(&pipeline.status) {
+            return Ok(());
+        }
+
+        sleep(interval);
+
+        let pipeline_endpoint = projects::pipelines::Pipeline::builder()
+            .project(PROJECT_NAME.to_string())
+            .pipeline(pipeline.id)
+            .build()
+            .unwrap();
+        pipeline = dump_query(pipeline_endpoint, |endpoint| endpoint.query(gitlab))?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dashboard::{is_pipeline_over, paint_status, render_frame};
+    use crate::job::Job;
+    use crate::pipeline::StatusState;
+
+    fn job(id: u64, name: &str, status: StatusState) -> Job {
+        Job {
+            id,
+            status,
+            name: name.to_string(),
+            stage: "deploy".to_string(),
+        }
+    }
+
+    #[test]
+    fn paints_the_statuses() {
+        assert_eq!("\x1b[32mSuccess\x1b[0m", paint_status(StatusState::Success));
+        assert_eq!("\x1b[31mFailed\x1b[0m", paint_status(StatusState::Failed));
+        assert_eq!("\x1b[33mRunning\x1b[0m", paint_status(StatusState::Running));
+        assert_eq!("\x1b[90mCreated\x1b[0m", paint_status(StatusState::Created));
+    }
+
+    #[test]
+    fn renders_a_frame_of_the_pipeline_jobs() {
+        let jobs = vec![
+            job(31, "deploy_eu", StatusState::Success),
+            job(32, "deploy_us", StatusState::Running),
+        ];
+
+        assert_eq!(
+            vec![
+                "Pipeline 12 (running)",
+                "ID         NAME      STAGE  STATUS",
+                "31         deploy_eu deploy \x1b[32mSuccess\x1b[0m",
+                "32         deploy_us deploy \x1b[33mRunning\x1b[0m",
+            ],
+            render_frame(12, "running", &jobs)
+        );
+    }
+
+    #[test]
+    fn stops_once_the_pipeline_is_over() {
+        assert!(is_pipeline_over("success"));
+        assert!(is_pipeline_over("canceled"));
+        assert!(!is_pipeline_over("running"));
+        assert!(!is_pipeline_over("manual"));
+    }
+}
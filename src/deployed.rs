@@ -0,0 +1,208 @@
+use anyhow::Error;
+use git2::Repository;
+use gitlab::{
+    api::{projects, Query},
+    Gitlab,
+};
+use semver::Version;
+use serde::Deserialize;
+
+use crate::api::dump_query;
+use crate::environment::Environment;
+use crate::PROJECT_NAME;
+
+#[derive(Debug, Deserialize)]
+struct Deployment {
+    /// The branch or tag which was deployed.
+    r#ref: String,
+    /// The commit which was deployed.
+    sha: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitlabEnvironment {
+    /// The ID of the environment.
+    id: u64,
+    /// The last successful deployment to the environment.
+    #[serde(default)]
+    last_deployment: Option<Deployment>,
+    /// The URL the environment is served at.
+    #[serde(default)]
+    external_url: Option<String>,
+}
+
+/// List the versions tagged in the repository, along with their commit
+fn get_tagged_versions(repository: &Repository) -> Result<Vec<(Version, String)>, Error> {
+    let tags = repository.tag_names(None)?;
+
+    let versions = tags
+        .iter()
+        .flatten()
+        .filter_map(|tag| {
+            let version = Version::parse(tag).ok()?;
+            let commit = repository
+                .revparse_single(tag)
+                .ok()?
+                .peel_to_commit()
+                .ok()?;
+
+            Some((version, commit.id().to_string()))
+        })
+        .collect();
+
+    Ok(versions)
+}
+
+/// Get the version of a deployment, from its ref or from the tags of its commit
+fn get_deployed_version(
+    deployment: &Deployment,
+    versions: &[(Version, String)],
+) -> Option<Version> {
+    Version::parse(&deployment.r#ref).ok().or_else(|| {
+        versions
+            .iter()
+            .filter(|(_, sha)| *sha == deployment.sha)
+            .map(|(version, _)| version.clone())
+            .max()
+    })
+}
+
+/// Count the versions released after the deployed one
+fn get_versions_behind(deployed: &Version, versions: &[Version]) -> usize {
+    versions
+        .iter()
+        .filter(|version| *version > deployed)
+        .count()
+}
+
+/// Find the GitLab environment of an environment, by name
+fn find_environment(
+    gitlab: &Gitlab,
+    environment: &Environment,
+) -> Result<Option<GitlabEnvironment>, Error> {
+    let environments_endpoint = projects::environments::Environments::builder()
+        .project(PROJECT_NAME.to_string())
+        .name(environment.to_string().to_lowercase())
+        .build()
+        .unwrap();
+    let environments: Vec<GitlabEnvironment> =
+        dump_query(environments_endpoint, |endpoint| endpoint.query(gitlab))?;
+
+    Ok(environments.into_iter().next())
+}
+
+/// Get the URL an environment is served at, if known
+pub fn get_external_url(
+    gitlab: &Gitlab,
+    environment: &Environment,
+) -> Result<Option<String>, Error> {
+    let gitlab_environment = find_environment(gitlab, environment)?;
+
+    Ok(gitlab_environment.and_then(|environment| environment.external_url))
+}
+
+/// Fetch the last successful deployment of an environment
+fn get_last_deployment(
+    gitlab: &Gitlab,
+    environment: &Environment,
+) -> Result<Option<Deployment>, Error> {
+    let Some(gitlab_environment) = find_environment(gitlab, environment)? else {
+        return Ok(None);
+    };
+
+    let environment_endpoint = projects::environments::Environment::builder()
+        .project(PROJECT_NAME.to_string())
+        .environment(gitlab_environment.id)
+        .build()
+        .unwrap();
+    let gitlab_environment: GitlabEnvironment =
+        dump_query(environment_endpoint, |endpoint| endpoint.query(gitlab))?;
+
+    Ok(gitlab_environment.last_deployment)
+}
+
+/// Report the version deployed to each environment, and how many versions
+/// are waiting to be deployed
+pub fn report(
+    gitlab: &Gitlab,
+    repository: &Repository,
+    environments: &[Environment],
+) -> Result<(), Error> {
+    let tagged_versions = get_tagged_versions(repository)?;
+    let versions: Vec<Version> = tagged_versions
+        .iter()
+        .map(|(version, _)| version.clone())
+        .collect();
+    let latest = versions.iter().max();
+
+    for environment in environments {
+        let deployment = get_last_deployment(gitlab, environment)?;
+
+        match deployment {
+            None => info!("[Deployed] {}: nothing was deployed.", environment),
+            Some(deployment) => match get_deployed_version(&deployment, &tagged_versions) {
+                Some(deployed) => info!(
+                    "[Deployed] {}: {} is deployed, {} versions behind {}.",
+                    environment,
+                    deployed,
+                    get_versions_behind(&deployed, &versions),
+                    latest.map_or("the latest tag".to_string(), Version::to_string)
+                ),
+                None => info!(
+                    "[Deployed] {}: \"{}\" ({:.7}) is deployed, which is not a tagged version.",
+                    environment, deployment.r#ref, deployment.sha
+                ),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::Version;
+
+    use crate::deployed::{get_deployed_version, get_versions_behind, Deployment};
+
+    fn versions(versions: &[&str]) -> Vec<Version> {
+        versions
+            .iter()
+            .map(|version| Version::parse(version).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn counts_the_versions_behind_the_deployed_one() {
+        let versions = versions(&["1.0.0", "1.1.0", "1.2.0", "1.10.0", "0.9.0"]);
+
+        assert_eq!(3, get_versions_behind(&Version::new(1, 0, 0), &versions));
+        assert_eq!(0, get_versions_behind(&Version::new(1, 10, 0), &versions));
+        assert_eq!(5, get_versions_behind(&Version::new(0, 1, 0), &versions));
+    }
+
+    #[test]
+    fn gets_the_deployed_version_from_the_ref_or_the_commit() {
+        let tagged_versions = vec![
+            (Version::new(1, 0, 0), "aaa".to_string()),
+            (Version::new(1, 1, 0), "bbb".to_string()),
+        ];
+        let deployment = |r#ref: &str, sha: &str| Deployment {
+            r#ref: r#ref.to_string(),
+            sha: sha.to_string(),
+        };
+
+        assert_eq!(
+            Some(Version::new(1, 2, 0)),
+            get_deployed_version(&deployment("1.2.0", "ccc"), &tagged_versions)
+        );
+        assert_eq!(
+            Some(Version::new(1, 1, 0)),
+            get_deployed_version(&deployment("master", "bbb"), &tagged_versions)
+        );
+        assert_eq!(
+            None,
+            get_deployed_version(&deployment("master", "ddd"), &tagged_versions)
+        );
+    }
+}
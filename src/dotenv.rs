@@ -0,0 +1,174 @@
+use std::fs;
+
+use anyhow::{anyhow, Error};
+
+/// Whether a name is a valid variable name
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse the value of a variable, unquoting it
+fn parse_value(value: &str) -> Option<String> {
+    if let Some(quoted) = value.strip_prefix('"') {
+        let (content, rest) = quoted.rsplit_once('"')?;
+        if !is_comment_or_empty(rest) {
+            return None;
+        }
+
+        let mut unescaped = String::new();
+        let mut chars = content.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                unescaped.push(c);
+                continue;
+            }
+
+            match chars.next()? {
+                'n' => unescaped.push('\n'),
+                't' => unescaped.push('\t'),
+                escaped => unescaped.push(escaped),
+            }
+        }
+
+        return Some(unescaped);
+    }
+
+    if let Some(quoted) = value.strip_prefix('\'') {
+        let (content, rest) = quoted.rsplit_once('\'')?;
+
+        return is_comment_or_empty(rest).then(|| content.to_string());
+    }
+
+    // An unquoted value ends at the first comment
+    let value = match value.find(" #") {
+        Some(index) => &value[..index],
+        None => value,
+    };
+
+    Some(value.trim().to_string())
+}
+
+/// Whether what follows a quoted value can be ignored
+fn is_comment_or_empty(rest: &str) -> bool {
+    let rest = rest.trim();
+
+    rest.is_empty() || rest.starts_with('#')
+}
+
+/// Parse the "KEY=VALUE" lines of a dotenv file, skipping the blank lines
+/// and the comments
+pub fn parse(content: &str) -> Result<Vec<(String, String)>, Error> {
+    let mut variables = vec![];
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let variable = line.split_once('=').and_then(|(key, value)| {
+            let key = key.trim();
+            let value = parse_value(value.trim())?;
+
+            is_valid_key(key).then(|| (key.to_string(), value))
+        });
+
+        match variable {
+            Some(variable) => variables.push(variable),
+            None => {
+                return Err(anyhow!(
+                    "Line {} is not a valid KEY=VALUE variable.",
+                    index + 1
+                ))
+            }
+        }
+    }
+
+    Ok(variables)
+}
+
+/// Load the variables of a dotenv file
+pub fn load(path: &str) -> Result<Vec<(String, String)>, Error> {
+    debug!("Loading the variables from {}.", path);
+    let content = fs::read_to_string(path).map_err(|e| anyhow!("Cannot read {} ({}).", path, e))?;
+
+    parse(&content).map_err(|e| anyhow!("Invalid {} file ({})", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dotenv::parse;
+
+    fn variable(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn parses_the_variables() {
+        let content = "API_URL=https://api.example.com\nexport REGION = eu-west-1\nEMPTY=\n";
+
+        assert_eq!(
+            vec![
+                variable("API_URL", "https://api.example.com"),
+                variable("REGION", "eu-west-1"),
+                variable("EMPTY", ""),
+            ],
+            parse(content).unwrap()
+        );
+    }
+
+    #[test]
+    fn skips_the_comments_and_blank_lines() {
+        let content =
+            "# The API\n\n   \nAPI_URL=https://api.example.com # trailing comment\nHASH=abc#def\n";
+
+        assert_eq!(
+            vec![
+                variable("API_URL", "https://api.example.com"),
+                variable("HASH", "abc#def"),
+            ],
+            parse(content).unwrap()
+        );
+    }
+
+    #[test]
+    fn unquotes_the_values() {
+        let content = r#"
+GREETING="Hello \"world\"\nBye" # comment
+LITERAL='Hello \n # not a comment'
+EQUALS="a=b"
+"#;
+
+        assert_eq!(
+            vec![
+                variable("GREETING", "Hello \"world\"\nBye"),
+                variable("LITERAL", "Hello \\n # not a comment"),
+                variable("EQUALS", "a=b"),
+            ],
+            parse(content).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_the_malformed_lines() {
+        for content in [
+            "API_URL",
+            "=value",
+            "1KEY=value",
+            "MY KEY=value",
+            "QUOTED=\"unterminated",
+            "QUOTED='a' b",
+        ] {
+            assert!(parse(content).is_err(), "{} should be rejected", content);
+        }
+
+        assert_eq!(
+            "Line 2 is not a valid KEY=VALUE variable.",
+            parse("KEY=value\nbroken\n").unwrap_err().to_string()
+        );
+    }
+}
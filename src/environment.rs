@@ -1,5 +1,10 @@
+use crate::config::Config;
 use crate::{DEVELOP_BRANCH, MASTER_BRANCH};
 use anyhow::Error;
+use clap::builder::{StringValueParser, TypedValueParser};
+use clap::{ErrorKind, ValueEnum};
+use std::collections::HashMap;
+use std::ffi::OsStr;
 use std::fmt;
 use std::str::FromStr;
 
@@ -21,6 +26,17 @@ impl Environment {
         Ok(job_name)
     }
 
+    /// Get the names of the jobs to play when deploying, from the configuration
+    /// if any
+    pub fn get_deploy_job_names(&self, config: &Config) -> Result<Vec<String>, Error> {
+        match config.get_environment(self) {
+            Some(environment) if !environment.deploy_jobs.is_empty() => {
+                Ok(environment.deploy_jobs.clone())
+            }
+            _ => Ok(vec![self.get_deploy_job_name()?]),
+        }
+    }
+
     ///
     pub fn get_pipeline_ref(&self) -> Result<String, Error> {
         let pipeline_ref = match self {
@@ -30,6 +46,58 @@ impl Environment {
 
         Ok(pipeline_ref)
     }
+
+    /// Resolve an alias of the configuration to the name of its environment,
+    /// a name being kept as is
+    pub fn resolve_alias<'a>(
+        value: &'a str,
+        aliases: &'a HashMap<String, String>,
+    ) -> Result<&'a str, String> {
+        if <Environment as ValueEnum>::from_str(value, true).is_ok() {
+            return Ok(value);
+        }
+
+        match aliases.get(value) {
+            Some(name) if <Environment as ValueEnum>::from_str(name, true).is_err() => {
+                Err(format!(
+                    "the \"{}\" alias points to the unknown \"{}\" environment",
+                    value, name
+                ))
+            }
+            Some(name) => Ok(name),
+            None => Ok(value),
+        }
+    }
+}
+
+/// Resolve the aliases of the environments to their names, which `value_enum`
+/// parses next
+#[derive(Clone)]
+pub struct AliasParser {
+    aliases: HashMap<String, String>,
+}
+
+impl AliasParser {
+    pub fn new(aliases: HashMap<String, String>) -> Self {
+        AliasParser { aliases }
+    }
+}
+
+impl TypedValueParser for AliasParser {
+    type Value = String;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> Result<String, clap::Error> {
+        let value = StringValueParser::new().parse_ref(cmd, arg, value)?;
+
+        Environment::resolve_alias(&value, &self.aliases)
+            .map(str::to_string)
+            .map_err(|e| cmd.clone().error(ErrorKind::ValueValidation, e))
+    }
 }
 
 impl FromStr for Environment {
@@ -49,3 +117,42 @@ impl fmt::Display for Environment {
         write!(f, "{:?}", self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::environment::Environment;
+
+    fn aliases() -> HashMap<String, String> {
+        HashMap::from([
+            ("prod".to_string(), "production".to_string()),
+            ("stg".to_string(), "staging".to_string()),
+            ("qa".to_string(), "testing".to_string()),
+        ])
+    }
+
+    #[test]
+    fn resolves_the_environment_aliases() {
+        let aliases = aliases();
+
+        assert_eq!(
+            Ok("production"),
+            Environment::resolve_alias("prod", &aliases)
+        );
+        assert_eq!(Ok("staging"), Environment::resolve_alias("stg", &aliases));
+        assert_eq!(
+            Ok("Staging"),
+            Environment::resolve_alias("Staging", &aliases)
+        );
+        assert_eq!(Ok("dev"), Environment::resolve_alias("dev", &aliases));
+    }
+
+    #[test]
+    fn rejects_an_alias_of_an_unknown_environment() {
+        assert_eq!(
+            Err("the \"qa\" alias points to the unknown \"testing\" environment".to_string()),
+            Environment::resolve_alias("qa", &aliases())
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::borrow::Cow;
+
+use anyhow::Error;
+use gitlab::{
+    api::{Endpoint, Query},
+    Gitlab,
+};
+use http::Method;
+
+use crate::api::{dump_query, is_not_found};
+use crate::environment::Environment;
+use crate::error::WrError;
+
+/// The endpoint getting a protected branch of a project
+pub struct ProtectedBranch<'a> {
+    pub project: &'a str,
+    pub branch: &'a str,
+}
+
+impl Endpoint for ProtectedBranch<'_> {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/protected_branches/{}",
+            self.project.replace('/', "%2F"),
+            self.branch.replace('/', "%2F")
+        )
+        .into()
+    }
+}
+
+/// Whether a branch of the project is protected
+fn is_protected(gitlab: &Gitlab, project: &str, branch: &str) -> Result<bool, Error> {
+    let endpoint = ProtectedBranch { project, branch };
+
+    match dump_query(endpoint, |endpoint| {
+        gitlab::api::ignore(endpoint).query(gitlab)
+    }) {
+        Ok(()) => Ok(true),
+        Err(e) if is_not_found(&e) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Check that an environment deploys from its expected ref, and that this
+/// ref is protected
+fn check_environment_ref(
+    environment: Environment,
+    expected: &str,
+    actual: &str,
+    protected: bool,
+) -> Result<(), WrError> {
+    if expected != actual {
+        return Err(WrError::EnvironmentRefMismatch {
+            environment: environment.to_string(),
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        });
+    }
+
+    if !protected {
+        return Err(WrError::EnvironmentRefUnprotected {
+            environment: environment.to_string(),
+            branch: actual.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Verify that the deploy of an environment runs on its expected ref, and
+/// that the ref is protected on GitLab
+pub fn verify(
+    gitlab: &Gitlab,
+    project: &str,
+    environment: Environment,
+    deploy_ref: &str,
+) -> Result<(), Error> {
+    let expected = environment.get_pipeline_ref()?;
+    let protected = is_protected(gitlab, project, deploy_ref)?;
+
+    check_environment_ref(environment, &expected, deploy_ref, protected)?;
+    info!(
+        "[Deploy] {} deploys from the protected \"{}\" ref.",
+        environment, deploy_ref
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use gitlab::api::Endpoint;
+
+    use crate::environment::Environment;
+    use crate::environment_ref::{check_environment_ref, ProtectedBranch};
+    use crate::error::WrError;
+
+    #[test]
+    fn accepts_the_expected_protected_ref() {
+        assert!(check_environment_ref(Environment::Production, "master", "master", true).is_ok());
+    }
+
+    #[test]
+    fn detects_a_ref_mismatch() {
+        let result = check_environment_ref(Environment::Production, "master", "feature/x", true);
+
+        assert!(matches!(
+            result,
+            Err(WrError::EnvironmentRefMismatch { expected, actual, .. })
+                if expected == "master" && actual == "feature/x"
+        ));
+    }
+
+    #[test]
+    fn detects_an_unprotected_ref() {
+        let result = check_environment_ref(Environment::Staging, "develop", "develop", false);
+
+        assert!(matches!(
+            result,
+            Err(WrError::EnvironmentRefUnprotected { branch, .. }) if branch == "develop"
+        ));
+    }
+
+    #[test]
+    fn assembles_the_protected_branch_request() {
+        let endpoint = ProtectedBranch {
+            project: "aeyoll/wr",
+            branch: "release/1.0",
+        };
+
+        assert_eq!(
+            "projects/aeyoll%2Fwr/protected_branches/release%2F1.0",
+            endpoint.endpoint()
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum WrError {
+    /// Neither the git-flow configuration nor the repository could tell
+    /// which branches to use
+    #[error("Unable to detect the default branch of the repository. Please run 'git flow init' or checkout a branch.")]
+    DefaultBranchNotFound,
+
+    /// The ssh-agent is running, but has no key to authenticate with
+    #[error("The ssh-agent could not authenticate with the remote. Please make sure it holds a key by running 'ssh-add'.")]
+    SshAgentNoKeys,
+
+    /// A production release would be made from the master branch itself
+    #[error("The develop branch is the master branch (\"{branch}\"), a production release would merge it into itself. Please configure a distinct develop branch with 'git flow init', or pass the force flag.")]
+    ReleaseFromMasterBranch { branch: String },
+
+    /// The GitLab API rejected the token
+    #[error("The GitLab token was rejected (HTTP {status}). It may have expired, or lack the \"api\" and \"write_repository\" scopes. Please check the GITLAB_TOKEN variable.")]
+    GitlabTokenInvalid { status: u16 },
+
+    /// A deploy job vanished from the pipeline, and no replacement was found
+    #[error("The \"{name}\" deploy job could not be found anymore in the pipeline, aborting.")]
+    DeployJobNotFound { name: String },
+
+    /// The whole run took longer than the max-runtime budget
+    #[error("The run exceeded the maximum runtime of {seconds} seconds, aborting.")]
+    RunTimeout { seconds: u64 },
+
+    /// The repository has no working tree, which a git-flow release needs
+    #[error("The repository is {kind}, which is only supported with the tag-only flag. Please run wr from a working tree.")]
+    UnsupportedRepositoryKind { kind: String },
+
+    /// Some commits of the release are not signed, or badly signed
+    #[error("{count} commits of the release are not validly signed. Please sign them, or pass the force flag.")]
+    UnsignedCommits { count: usize },
+
+    /// The latest pipeline of develop did not succeed
+    #[error("The latest pipeline of develop is not green ({status}, {url}). Please fix it, or pass the force flag.")]
+    DevelopCiNotGreen { status: String, url: String },
+
+    /// The release tag does not follow the naming policy
+    #[error("The \"{tag}\" tag does not match the tag pattern \"{pattern}\", aborting.")]
+    TagPolicyViolation { tag: String, pattern: String },
+
+    /// The new version is not above the latest released one
+    #[error("The {version} version is not greater than the latest {origin} ({latest}), aborting.")]
+    VersionDowngrade {
+        version: String,
+        latest: String,
+        origin: String,
+    },
+
+    /// The tag of the new version already exists
+    #[error("The \"{tag}\" tag already exists. Please delete it, or pass the force-tag flag to recreate it.")]
+    TagAlreadyExists { tag: String },
+
+    /// The commit to tag was not merged into the branch
+    #[error(
+        "The \"{commit}\" commit is not on the {branch} branch, it cannot be tagged as a release."
+    )]
+    CommitNotOnBranch { commit: String, branch: String },
+
+    /// The remote reported the tag as pushed, but does not have it
+    #[error("The \"{tag}\" tag is missing from the \"{remote}\" remote after the push. A server-side hook may have rejected it.")]
+    RemoteTagMissing { tag: String, remote: String },
+
+    /// Some tags look like versions, but are not semver ones
+    #[error("Some tags are not valid semver versions: {report}. Please fix them, or drop the strict-semver flag.")]
+    NonSemverTags { report: String },
+
+    /// The environment would be deployed from another ref than its own
+    #[error("{environment} deploys from \"{actual}\", but is expected to deploy from \"{expected}\", aborting.")]
+    EnvironmentRefMismatch {
+        environment: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// The ref the environment deploys from is not protected
+    #[error("{environment} deploys from \"{branch}\", which is not a protected branch on GitLab, aborting.")]
+    EnvironmentRefUnprotected { environment: String, branch: String },
+
+    /// The release was made, but the deploy failed
+    #[error("The {succeeded} phases succeeded, but the {failed} failed.")]
+    PartialSuccess {
+        succeeded: String,
+        failed: String,
+        #[source]
+        reason: anyhow::Error,
+    },
+
+    /// A git operation failed
+    #[error(transparent)]
+    Git(#[from] git2::Error),
+
+    /// Any other failure
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl WrError {
+    /// Get back the error of wr an error holds, wrapping any other one
+    pub fn from_error(error: anyhow::Error) -> WrError {
+        error.downcast().unwrap_or_else(WrError::Other)
+    }
+
+    /// Turn back into an error, unwrapping any other one
+    pub fn into_error(self) -> anyhow::Error {
+        match self {
+            WrError::Other(error) => error,
+            error => error.into(),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::stats::ReleaseStats;
+
+/// An event of the release, printed as a JSON line with the json flag
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// A release was confirmed, and is being created
+    ReleaseStarted {
+        environment: String,
+        version: String,
+    },
+    /// A new release has been created
+    Created {
+        environment: String,
+        version: Option<String>,
+    },
+    /// The release has been pushed to the remote repository
+    Pushed {
+        environment: String,
+        version: Option<String>,
+    },
+    /// The deploy jobs of the release have succeeded
+    Deployed {
+        environment: String,
+        version: Option<String>,
+    },
+    /// A deploy job of the release has failed
+    DeployFailed {
+        environment: String,
+        version: Option<String>,
+        job: String,
+        job_id: u64,
+        pipeline_url: String,
+        /// The last lines of the log of the job.
+        trace: Vec<String>,
+    },
+    /// The summary of the changes of the release
+    Summary {
+        environment: String,
+        version: Option<String>,
+        stats: ReleaseStats,
+    },
+}
+
+/// Print the events of the release on stdout, one JSON object per line
+pub struct EventEmitter {
+    pub json: bool,
+}
+
+impl EventEmitter {
+    pub fn emit(&self, event: Event) {
+        if self.json {
+            println!("{}", serde_json::to_string(&event).unwrap());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::Event;
+
+    #[test]
+    fn serializes_the_release_started_event() {
+        let event = Event::ReleaseStarted {
+            environment: "Production".to_string(),
+            version: "1.2.0".to_string(),
+        };
+
+        assert_eq!(
+            r#"{"event":"release_started","environment":"Production","version":"1.2.0"}"#,
+            serde_json::to_string(&event).unwrap()
+        );
+    }
+}
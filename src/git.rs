@@ -1,10 +1,26 @@
 use regex::Regex;
-use std::{env, path::Path};
+use std::{cell::RefCell, env, sync::OnceLock};
 
 use anyhow::{anyhow, Error};
-use git2::{Config, Cred, Remote, RemoteCallbacks, Repository};
+use chrono::DateTime;
+use duct::{cmd, Expression};
+use git2::{
+    BranchType, Commit, Config, Cred, CredentialType, Direction, ErrorClass, ErrorCode,
+    FetchOptions, ObjectType, Oid, PushOptions, Remote, RemoteCallbacks, Repository, Signature,
+    Time,
+};
 
-use crate::{DEVELOP_BRANCH, MASTER_BRANCH};
+use crate::error::WrError;
+use crate::{DEVELOP, MASTER};
+
+/// The remote used when none is given
+const DEFAULT_REMOTE: &str = "origin";
+
+static REMOTE_NAME: OnceLock<String> = OnceLock::new();
+
+static GIT_FLOW_BIN: OnceLock<String> = OnceLock::new();
+
+static BRANCHES: OnceLock<(String, String)> = OnceLock::new();
 
 /// Format a git branch ref
 pub fn ref_by_branch(branch: &str) -> String {
@@ -16,47 +32,210 @@ pub fn ref_by_tag(tag: &str) -> String {
     format!("refs/tags/{}:refs/tags/{}", tag, tag)
 }
 
+/// Maximum number of times the credentials callback may be called, git2 calling
+/// it again and again while the authentication fails
+const MAX_CREDENTIALS_ATTEMPTS: usize = 2;
+
+/// Wrap a credentials callback, failing fast once it has been called more than
+/// `max_attempts` times
+fn limit_credentials_attempts<F>(
+    max_attempts: usize,
+    mut callback: F,
+) -> impl FnMut(&str, Option<&str>, CredentialType) -> Result<Cred, git2::Error>
+where
+    F: FnMut(&str, Option<&str>, CredentialType) -> Result<Cred, git2::Error>,
+{
+    let mut attempts = 0;
+
+    move |url, username_from_url, allowed_types| {
+        attempts += 1;
+
+        if attempts > max_attempts {
+            return Err(git2::Error::new(
+                ErrorCode::Auth,
+                ErrorClass::Callback,
+                WrError::SshAgentNoKeys.to_string(),
+            ));
+        }
+
+        callback(url, username_from_url, allowed_types)
+    }
+}
+
 /// Fetch credentials from the ssh-agent
 pub fn create_remote_callback() -> Result<RemoteCallbacks<'static>, Error> {
     let mut callback = RemoteCallbacks::new();
-    callback.credentials(|_url, username_from_url, _allowed_types| {
-        Cred::ssh_key_from_agent(username_from_url.unwrap())
-    });
+    callback.credentials(limit_credentials_attempts(
+        MAX_CREDENTIALS_ATTEMPTS,
+        |_url, username_from_url, _allowed_types| {
+            Cred::ssh_key_from_agent(username_from_url.unwrap())
+        },
+    ));
 
     Ok(callback)
 }
 
-/// Get the current git repository's configuration
-pub fn get_config() -> Config {
-    let current_dir = env::current_dir().unwrap();
-    let path = format!("{}/.git/config", current_dir.display());
-    let config = Config::open(Path::new(&path)).unwrap();
-    config
+/// Map an error from a remote operation, detecting when the ssh-agent could not
+/// provide any usable key
+pub fn map_remote_error(error: git2::Error) -> Error {
+    if error.code() == ErrorCode::Auth && error.class() == ErrorClass::Callback {
+        WrError::SshAgentNoKeys.into()
+    } else {
+        error.into()
+    }
 }
 
-///
-pub fn get_gitflow_branch_name(branch: &str) -> String {
-    let config = get_config();
+/// Get the signing key of the user, required to sign commits
+pub fn get_signing_key(repository: &Repository) -> Result<String, Error> {
+    repository
+        .config()?
+        .get_string("user.signingkey")
+        .map_err(|_| {
+            anyhow!("No signing key is configured. Please run 'git config user.signingkey <KEY>'.")
+        })
+}
+
+/// Get the environment variables overriding some git configuration values,
+/// for the git subprocesses
+pub fn get_config_env(overrides: &[(&str, &str)]) -> Vec<(String, String)> {
+    let mut env = vec![("GIT_CONFIG_COUNT".to_string(), overrides.len().to_string())];
+
+    for (index, (key, value)) in overrides.iter().enumerate() {
+        env.push((format!("GIT_CONFIG_KEY_{}", index), key.to_string()));
+        env.push((format!("GIT_CONFIG_VALUE_{}", index), value.to_string()));
+    }
+
+    env
+}
+
+/// Get a branch name from the git-flow configuration
+fn get_gitflow_branch_name(config: &Config, branch: &str) -> Option<String> {
     let config_path = format!("gitflow.branch.{}", &branch);
-    config.get_string(&config_path).unwrap()
+    config.get_string(&config_path).ok()
 }
 
-/// Get a Gitlab project name from the remote url set in the config
-fn extract_project_name_from_remote_url(remote_url: &str) -> String {
-    lazy_static! {
-        static ref PROJECT_NAME_REGEX: Regex = Regex::new(
-            r"(?x)
+/// The prefix of the release branches, when git-flow does not configure one
+const DEFAULT_RELEASE_PREFIX: &str = "release/";
+
+/// Get the prefix of the git-flow release branches
+pub fn get_release_prefix(repository: &Repository) -> Result<String, Error> {
+    let prefix = repository
+        .config()?
+        .get_string("gitflow.prefix.release")
+        .unwrap_or_else(|_| DEFAULT_RELEASE_PREFIX.to_string());
+
+    Ok(prefix)
+}
+
+/// List the names of the branches of a type
+pub fn get_branch_names(
+    repository: &Repository,
+    branch_type: BranchType,
+) -> Result<Vec<String>, Error> {
+    let mut names = vec![];
+
+    for branch in repository.branches(Some(branch_type))? {
+        let (branch, _) = branch?;
+
+        if let Some(name) = branch.name()? {
+            names.push(name.to_string());
+        }
+    }
+
+    Ok(names)
+}
+
+/// Get the version of the release in progress, from its git-flow release
+/// branch, if any
+pub fn get_release_in_progress(repository: &Repository) -> Result<Option<String>, Error> {
+    let prefix = get_release_prefix(repository)?;
+
+    let version = get_branch_names(repository, BranchType::Local)?
+        .into_iter()
+        .find_map(|name| name.strip_prefix(&prefix).map(str::to_string));
+
+    Ok(version)
+}
+
+/// Get the default branch of the remote, from "<remote>/HEAD"
+fn get_remote_default_branch(repository: &Repository) -> Option<String> {
+    let remote_prefix = format!("refs/remotes/{}/", get_remote_name());
+    let reference = repository
+        .find_reference(&format!("{}HEAD", remote_prefix))
+        .ok()?;
+    let target = reference.symbolic_target()?;
+
+    target
+        .strip_prefix(&remote_prefix)
+        .map(|branch| branch.to_string())
+}
+
+/// Get the name of the currently checked out branch
+pub fn get_current_branch(repository: &Repository) -> Option<String> {
+    let head = repository.head().ok()?;
+
+    if !head.is_branch() {
+        return None;
+    }
+
+    head.shorthand().map(|branch| branch.to_string())
+}
+
+/// Resolve the master and develop branch names
+///
+/// The git-flow configuration is used when available. Otherwise, the remote's
+/// default branch (or the current branch) is treated as master, and the current
+/// branch as develop.
+pub fn resolve_branches(repository: &Repository) -> Result<(String, String), WrError> {
+    let config = repository.config()?;
+
+    if let (Some(master), Some(develop)) = (
+        get_gitflow_branch_name(&config, MASTER),
+        get_gitflow_branch_name(&config, DEVELOP),
+    ) {
+        return Ok((master, develop));
+    }
+
+    debug!("git-flow is not initialized, falling back to the default branch.");
+    let develop = get_current_branch(repository).ok_or(WrError::DefaultBranchNotFound)?;
+    let master = get_remote_default_branch(repository).unwrap_or_else(|| develop.clone());
+
+    Ok((master, develop))
+}
+
+/// Resolve the master and develop branch names of the repository, once for
+/// the whole run
+pub fn init_branches(repository: &Repository) -> Result<(), WrError> {
+    if BRANCHES.get().is_none() {
+        BRANCHES.set(resolve_branches(repository)?).ok();
+    }
+
+    Ok(())
+}
+
+/// Get the master and develop branch names, resolved at startup
+pub fn get_branches() -> &'static (String, String) {
+    BRANCHES
+        .get()
+        .expect("The branches are resolved before being used.")
+}
+
+lazy_static! {
+    static ref REMOTE_URL_REGEX: Regex = Regex::new(
+        r"(?x)
 (?P<user>[^@\s]+)
 @
 (?P<host>[^@\s]+)
 :
 (?P<project_name>[^@\s]+)
 .git"
-        )
-        .unwrap();
-    }
+    )
+    .unwrap();
+}
 
-    let project_name = PROJECT_NAME_REGEX
+/// Get a Gitlab project name from the remote url set in the config
+fn extract_project_name_from_remote_url(remote_url: &str) -> String {
+    let project_name = REMOTE_URL_REGEX
         .captures(remote_url)
         .and_then(|cap| cap.name("project_name").map(|login| login.as_str()))
         .unwrap();
@@ -64,15 +243,80 @@ fn extract_project_name_from_remote_url(remote_url: &str) -> String {
     project_name.to_string()
 }
 
+/// Get a Gitlab host from the remote url set in the config
+fn extract_host_from_remote_url(remote_url: &str) -> Option<String> {
+    REMOTE_URL_REGEX
+        .captures(remote_url)
+        .and_then(|cap| cap.name("host"))
+        .map(|host| host.as_str().to_string())
+}
+
+/// Set the name of the remote to push to, and to detect the GitLab project from
+pub fn set_remote_name(remote_name: &str) {
+    REMOTE_NAME.set(remote_name.to_string()).ok();
+}
+
+/// Get the name of the remote to push to, and to detect the GitLab project from
+pub fn get_remote_name() -> &'static str {
+    REMOTE_NAME
+        .get()
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_REMOTE)
+}
+
+/// Set the git-flow binary to run, instead of the `git flow` subcommand
+pub fn set_git_flow_bin(git_flow_bin: &str) {
+    GIT_FLOW_BIN.set(git_flow_bin.to_string()).ok();
+}
+
+/// Assemble a git-flow command line, with the given binary or `git flow`
+fn get_git_flow_command(git_flow_bin: Option<&str>, args: &[&str]) -> Vec<String> {
+    let program = match git_flow_bin {
+        Some(git_flow_bin) => vec![git_flow_bin],
+        None => vec!["git", "flow"],
+    };
+
+    program
+        .into_iter()
+        .chain(args.iter().copied())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Build a git-flow command, with the configured binary
+pub fn git_flow(args: &[&str]) -> Expression {
+    let command = get_git_flow_command(GIT_FLOW_BIN.get().map(String::as_str), args);
+
+    cmd(&command[0], &command[1..])
+}
+
+/// Get the url of a remote from the repository config
+fn get_remote_url(repository: &Repository, remote_name: &str) -> Result<String, Error> {
+    let config_path = format!("remote.{}.url", remote_name);
+    let remote_url = repository
+        .config()?
+        .get_string(&config_path)
+        .map_err(|_| anyhow!("The \"{}\" remote has no url.", remote_name))?;
+
+    Ok(remote_url)
+}
+
 /// Get the project name from the git remote url
 pub fn get_project_name() -> String {
-    let config = get_config();
-    let config_path = "remote.origin.url";
-    let remote_url = config.get_string(config_path).unwrap();
+    let repository = get_repository().unwrap();
+    let remote_url = get_remote_url(&repository, get_remote_name()).unwrap();
 
     extract_project_name_from_remote_url(&remote_url)
 }
 
+/// Get the GitLab host from the git remote url, if it can be detected
+pub fn get_gitlab_host() -> Option<String> {
+    let repository = get_repository().ok()?;
+    let remote_url = get_remote_url(&repository, get_remote_name()).ok()?;
+
+    extract_host_from_remote_url(&remote_url)
+}
+
 /// Get an instance of the git repository in the current directory
 pub fn get_repository() -> Result<Repository, Error> {
     debug!("Try to load the current repository.");
@@ -89,28 +333,434 @@ pub fn get_repository() -> Result<Repository, Error> {
 /// Get a Remote instance from the current repository
 pub fn get_remote(repository: &Repository) -> Result<Remote, Error> {
     debug!("Try to find the remote for current repository.");
-    let remote = repository.find_remote("origin")?;
+    let remote = repository.find_remote(get_remote_name())?;
     debug!("Found git repository's remote.");
 
     Ok(remote)
 }
 
-///
-pub fn get_gitflow_branches_refs() -> Vec<String> {
-    let branches = [MASTER_BRANCH.to_string(), DEVELOP_BRANCH.to_string()];
-    let branches_refs: Vec<String> = branches.iter().map(|a| ref_by_branch(a)).collect();
-    branches_refs
+/// Get the branches pushed by a production release: master, and develop
+/// unless it is managed separately
+pub fn get_production_branches<'a>(
+    master: &'a str,
+    develop: &'a str,
+    push_develop: bool,
+) -> Vec<&'a str> {
+    match push_develop {
+        true => vec![master, develop],
+        false => vec![master],
+    }
+}
+
+/// Create an annotated tag on the current commit
+pub fn create_tag(repository: &Repository, tag: &str, message: &str) -> Result<Oid, Error> {
+    let head = repository.head()?.peel_to_commit()?;
+
+    create_tag_at(repository, tag, &head, message)
+}
+
+/// Create an annotated tag on the given commit
+pub fn create_tag_at(
+    repository: &Repository,
+    tag: &str,
+    commit: &Commit,
+    message: &str,
+) -> Result<Oid, Error> {
+    let signature = repository.signature()?;
+    let oid = repository.tag(tag, commit.as_object(), &signature, message, false)?;
+
+    Ok(oid)
+}
+
+/// Whether the tip of a branch is reachable from another branch
+pub fn is_branch_merged(repository: &Repository, branch: &str, into: &str) -> Result<bool, Error> {
+    let tip = |name: &str| -> Result<Oid, Error> {
+        Ok(repository
+            .find_branch(name, BranchType::Local)?
+            .get()
+            .peel_to_commit()?
+            .id())
+    };
+    let (branch_tip, into_tip) = (tip(branch)?, tip(into)?);
+
+    Ok(branch_tip == into_tip || repository.graph_descendant_of(into_tip, branch_tip)?)
+}
+
+/// Resolve a revision to a commit, checking that the commit was merged into
+/// the given branch, local or remote-tracking (e.g. "origin/master")
+pub fn resolve_branch_commit<'r>(
+    repository: &'r Repository,
+    revision: &str,
+    branch: &str,
+) -> Result<Commit<'r>, Error> {
+    let commit = repository.revparse_single(revision)?.peel_to_commit()?;
+    let head = repository.revparse_single(branch)?.peel_to_commit()?;
+
+    if head.id() != commit.id() && !repository.graph_descendant_of(head.id(), commit.id())? {
+        return Err(WrError::CommitNotOnBranch {
+            commit: revision.to_string(),
+            branch: branch.to_string(),
+        }
+        .into());
+    }
+
+    Ok(commit)
+}
+
+/// Replace the message of an existing tag, keeping its target and tagger. A
+/// lightweight tag becomes an annotated one, tagged by the current user.
+pub fn annotate_tag(repository: &Repository, tag: &str, message: &str) -> Result<Oid, Error> {
+    let reference = repository.find_reference(&format!("refs/tags/{}", tag))?;
+    let target = reference.peel(ObjectType::Commit)?;
+
+    let tagger = match reference.peel_to_tag() {
+        Ok(existing_tag) => existing_tag.tagger().map(|tagger| tagger.to_owned()),
+        Err(_) => None,
+    };
+    let tagger = match tagger {
+        Some(tagger) => tagger,
+        None => repository.signature()?,
+    };
+
+    let oid = repository.tag(tag, &target, &tagger, message, true)?;
+
+    Ok(oid)
+}
+
+/// Read the message of an annotated tag
+pub fn read_tag_message(repository: &Repository, tag: &str) -> Result<String, Error> {
+    let reference = repository.find_reference(&format!("refs/tags/{}", tag))?;
+    let tag_object = reference.peel_to_tag().map_err(|_| {
+        anyhow!(
+            "The {} tag is a lightweight tag, which has no message. Please annotate it.",
+            tag
+        )
+    })?;
+
+    Ok(tag_object
+        .message()
+        .unwrap_or_default()
+        .trim_end()
+        .to_string())
+}
+
+/// The date given to the tagger of a release tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagDate {
+    /// The date of the tagged commit
+    Commit,
+    /// A fixed date
+    At(Time),
+}
+
+/// Parse a tag date, "commit" or an RFC 3339 date
+pub fn parse_tag_date(value: &str) -> Result<TagDate, String> {
+    if value == "commit" {
+        return Ok(TagDate::Commit);
+    }
+
+    let date = DateTime::parse_from_rfc3339(value).map_err(|e| {
+        format!(
+            "\"{}\" is neither \"commit\" nor an RFC 3339 date ({})",
+            value, e
+        )
+    })?;
+
+    Ok(TagDate::At(Time::new(
+        date.timestamp(),
+        date.offset().local_minus_utc() / 60,
+    )))
+}
+
+/// Copy a signature, dated at the given time
+pub fn get_signature_at(signature: &Signature, time: &Time) -> Result<Signature<'static>, Error> {
+    let signature = Signature::new(
+        signature.name().unwrap_or_default(),
+        signature.email().unwrap_or_default(),
+        time,
+    )?;
+
+    Ok(signature)
+}
+
+/// Re-create a tag with its tagger dated at the given date, keeping its target
+/// and message
+pub fn redate_tag(repository: &Repository, tag: &str, date: TagDate) -> Result<Oid, Error> {
+    let reference = repository.find_reference(&format!("refs/tags/{}", tag))?;
+    let target = reference.peel(ObjectType::Commit)?;
+
+    let (tagger, message) = match reference.peel_to_tag() {
+        Ok(existing_tag) => (
+            existing_tag.tagger().map(|tagger| tagger.to_owned()),
+            existing_tag.message().unwrap_or(tag).to_string(),
+        ),
+        Err(_) => (None, tag.to_string()),
+    };
+    let tagger = match tagger {
+        Some(tagger) => tagger,
+        None => repository.signature()?,
+    };
+
+    let time = match date {
+        TagDate::Commit => target.peel_to_commit()?.time(),
+        TagDate::At(time) => time,
+    };
+
+    let oid = repository.tag(
+        tag,
+        &target,
+        &get_signature_at(&tagger, &time)?,
+        &message,
+        true,
+    )?;
+
+    Ok(oid)
+}
+
+/// Push a single tag to a remote
+pub fn push_tag(
+    remote: &mut Remote,
+    tag: &str,
+    push_options: &mut PushOptions,
+) -> Result<(), Error> {
+    remote
+        .push(&[ref_by_tag(tag)], Some(push_options))
+        .map_err(map_remote_error)?;
+
+    Ok(())
+}
+
+/// Fetch the tags of a remote, to know about the tags pushed by others
+pub fn fetch_tags(remote: &mut Remote) -> Result<(), Error> {
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(create_remote_callback()?);
+
+    remote
+        .fetch(&["refs/tags/*:refs/tags/*"], Some(&mut fetch_options), None)
+        .map_err(map_remote_error)?;
+
+    Ok(())
+}
+
+/// Get the refspec fetching a branch of the remote into its remote-tracking
+/// branch
+pub fn get_tracking_refspec(branch: &str) -> String {
+    format!(
+        "refs/heads/{}:refs/remotes/{}/{}",
+        branch,
+        get_remote_name(),
+        branch
+    )
+}
+
+/// Fetch a branch of the remote into its remote-tracking branch
+pub fn fetch_branch(remote: &mut Remote, branch: &str) -> Result<(), Error> {
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(create_remote_callback()?);
+
+    remote
+        .fetch(
+            &[get_tracking_refspec(branch)],
+            Some(&mut fetch_options),
+            None,
+        )
+        .map_err(map_remote_error)?;
+
+    Ok(())
+}
+
+/// List the refs advertised by a remote
+pub fn list_remote_refs(remote: &mut Remote) -> Result<Vec<String>, Error> {
+    let connection = remote
+        .connect_auth(Direction::Fetch, Some(create_remote_callback()?), None)
+        .map_err(map_remote_error)?;
+
+    let refs = connection
+        .list()?
+        .iter()
+        .map(|head| head.name().to_string())
+        .collect();
+
+    Ok(refs)
+}
+
+/// Whether a list of refs holds a tag, peeled or not
+fn has_tag_ref(refs: &[String], tag: &str) -> bool {
+    let tag_ref = format!("refs/tags/{}", tag);
+
+    refs.iter()
+        .any(|r| r.strip_suffix("^{}").unwrap_or(r) == tag_ref)
+}
+
+/// Check that a tag landed on the remote, as a server-side hook may reject
+/// it after a successful push
+pub fn verify_remote_tag(remote: &mut Remote, tag: &str) -> Result<(), Error> {
+    let refs = list_remote_refs(remote)?;
+
+    match has_tag_ref(&refs, tag) {
+        true => Ok(()),
+        false => Err(WrError::RemoteTagMissing {
+            tag: tag.to_string(),
+            remote: remote.name().unwrap_or_default().to_string(),
+        }
+        .into()),
+    }
+}
+
+/// Push refs to a remote, returning the refs rejected by the remote with the
+/// reason of their rejection, along with the messages of the remote
+pub fn push_refs(remote: &mut Remote, refs: &[String]) -> Result<Vec<(String, String)>, Error> {
+    let rejected = RefCell::new(Vec::new());
+    let messages = RefCell::new(Vec::new());
+
+    let mut callback = create_remote_callback()?;
+    callback.push_update_reference(|refname, status| {
+        if let Some(status) = status {
+            rejected
+                .borrow_mut()
+                .push((refname.to_string(), status.to_string()));
+        }
+        Ok(())
+    });
+    // GitLab explains a rejection in the messages of the remote only, the
+    // status being "pre-receive hook declined"
+    callback.sideband_progress(|data| {
+        messages.borrow_mut().extend_from_slice(data);
+        true
+    });
+
+    let mut push_options = PushOptions::new();
+    push_options.remote_callbacks(callback);
+    remote
+        .push(refs, Some(&mut push_options))
+        .map_err(map_remote_error)?;
+    drop(push_options);
+
+    let messages = String::from_utf8_lossy(&messages.into_inner()).into_owned();
+
+    Ok(rejected
+        .into_inner()
+        .into_iter()
+        .map(|(refname, status)| (refname, describe_rejection(&status, &messages)))
+        .collect())
+}
+
+/// Describe the rejection of a ref, with the messages of the remote if any
+fn describe_rejection(status: &str, messages: &str) -> String {
+    let messages: Vec<&str> = messages
+        .split(['\r', '\n'])
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    match messages.is_empty() {
+        true => status.to_string(),
+        false => format!("{}: {}", status, messages.join(" ")),
+    }
+}
+
+/// Whether a push was rejected by GitLab because the branch is protected. A
+/// declined pre-receive hook alone may come from any server-side hook.
+pub fn is_protected_branch_rejection(status: &str) -> bool {
+    status.to_lowercase().contains("protected branch")
+}
+
+/// Whether a push was rejected by GitLab because the tag is protected. A
+/// declined pre-receive hook alone may come from any server-side hook.
+pub fn is_protected_tag_rejection(status: &str) -> bool {
+    let status = status.to_lowercase();
+
+    status.contains("protected tag") || status.contains("as it is protected")
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::git::{extract_project_name_from_remote_url, ref_by_branch, ref_by_tag};
+    use crate::error::WrError;
+    use crate::git::{
+        annotate_tag, create_tag, create_tag_at, describe_rejection, extract_host_from_remote_url,
+        extract_project_name_from_remote_url, get_config_env, get_git_flow_command,
+        get_production_branches, get_release_in_progress, get_remote_url, get_signature_at,
+        get_signing_key, has_tag_ref, is_branch_merged, is_protected_branch_rejection,
+        is_protected_tag_rejection, limit_credentials_attempts, map_remote_error, parse_tag_date,
+        push_refs, push_tag, read_tag_message, redate_tag, ref_by_branch, ref_by_tag,
+        resolve_branch_commit, resolve_branches, verify_remote_tag, TagDate,
+    };
+    use crate::test_utils::{commit, init_bare_remote, init_repository};
+    use git2::{Cred, CredentialType, PushOptions, Signature, Time};
+
+    #[test]
+    fn assembles_the_git_flow_command() {
+        assert_eq!(
+            vec!["git", "flow", "release", "start", "1.2.0"],
+            get_git_flow_command(None, &["release", "start", "1.2.0"])
+        );
+        assert_eq!(
+            vec!["/opt/git-flow/bin/git-flow", "version"],
+            get_git_flow_command(Some("/opt/git-flow/bin/git-flow"), &["version"])
+        );
+    }
 
     #[test]
     fn format_a_branch_ref() {
         assert_eq!("refs/heads/main:refs/heads/main", ref_by_branch("main"));
     }
 
+    #[test]
+    fn lists_the_production_branches() {
+        assert_eq!(
+            vec!["main", "develop"],
+            get_production_branches("main", "develop", true)
+        );
+        assert_eq!(
+            vec!["main"],
+            get_production_branches("main", "develop", false)
+        );
+    }
+
+    #[test]
+    fn finds_a_tag_in_the_remote_refs() {
+        let refs: Vec<String> = [
+            "HEAD",
+            "refs/heads/main",
+            "refs/tags/1.0.0",
+            "refs/tags/1.1.0",
+            "refs/tags/1.1.0^{}",
+            "refs/tags/1.2.0^{}",
+        ]
+        .iter()
+        .map(|r| r.to_string())
+        .collect();
+
+        assert!(has_tag_ref(&refs, "1.0.0"));
+        assert!(has_tag_ref(&refs, "1.1.0"));
+        assert!(has_tag_ref(&refs, "1.2.0"));
+        assert!(!has_tag_ref(&refs, "1.3.0"));
+        assert!(!has_tag_ref(&refs, "main"));
+    }
+
+    #[test]
+    fn verifies_that_the_tag_landed_on_the_remote() {
+        let (_dir, repository) = init_repository("main");
+        let (_remote_dir, _remote_repository) = init_bare_remote(&repository);
+        let head = commit(&repository, "feat: add a feature");
+        let object = repository.find_object(head, None).unwrap();
+        repository.tag_lightweight("1.0.0", &object, false).unwrap();
+        repository.tag_lightweight("1.1.0", &object, false).unwrap();
+
+        let mut remote = repository.find_remote("origin").unwrap();
+        remote
+            .push(&[ref_by_branch("main"), ref_by_tag("1.0.0")], None)
+            .unwrap();
+
+        assert!(verify_remote_tag(&mut remote, "1.0.0").is_ok());
+
+        let error = verify_remote_tag(&mut remote, "1.1.0").unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<WrError>(),
+            Some(WrError::RemoteTagMissing { tag, remote }) if tag == "1.1.0" && remote == "origin"
+        ));
+    }
+
     #[test]
     fn format_a_tag_ref() {
         assert_eq!("refs/tags/1.0.0:refs/tags/1.0.0", ref_by_tag("1.0.0"));
@@ -123,4 +773,434 @@ mod tests {
             extract_project_name_from_remote_url("git@github.com:aeyoll/wr.git")
         )
     }
+
+    #[test]
+    fn detects_the_host_and_project_from_a_non_origin_remote() {
+        let (_dir, repository) = init_repository("main");
+        repository
+            .remote("origin", "git@github.com:aeyoll/wr.git")
+            .unwrap();
+        repository
+            .remote("gitlab", "git@gitlab.example.com:tools/wr.git")
+            .unwrap();
+
+        let remote_url = get_remote_url(&repository, "gitlab").unwrap();
+
+        assert_eq!(
+            Some("gitlab.example.com".to_string()),
+            extract_host_from_remote_url(&remote_url)
+        );
+        assert_eq!(
+            "tools/wr",
+            extract_project_name_from_remote_url(&remote_url)
+        );
+        assert!(get_remote_url(&repository, "upstream").is_err());
+    }
+
+    #[test]
+    fn overrides_the_git_config_of_subprocesses() {
+        let env = get_config_env(&[("commit.gpgsign", "true"), ("user.signingkey", "ABCD1234")]);
+
+        assert_eq!(
+            vec![
+                ("GIT_CONFIG_COUNT".to_string(), "2".to_string()),
+                ("GIT_CONFIG_KEY_0".to_string(), "commit.gpgsign".to_string()),
+                ("GIT_CONFIG_VALUE_0".to_string(), "true".to_string()),
+                (
+                    "GIT_CONFIG_KEY_1".to_string(),
+                    "user.signingkey".to_string()
+                ),
+                ("GIT_CONFIG_VALUE_1".to_string(), "ABCD1234".to_string()),
+            ],
+            env
+        );
+    }
+
+    #[test]
+    fn requires_a_signing_key() {
+        let (_dir, repository) = init_repository("main");
+        assert!(get_signing_key(&repository).is_err());
+
+        repository
+            .config()
+            .unwrap()
+            .set_str("user.signingkey", "ABCD1234")
+            .unwrap();
+        assert_eq!("ABCD1234", get_signing_key(&repository).unwrap());
+    }
+
+    #[test]
+    fn reads_the_message_of_an_annotated_tag() {
+        let (_dir, repository) = init_repository("main");
+        create_tag(&repository, "1.0.0", "1.0.0\n\nHand-crafted notes\n").unwrap();
+        let head = repository.head().unwrap().peel_to_commit().unwrap();
+        repository
+            .tag_lightweight("1.0.1", head.as_object(), false)
+            .unwrap();
+
+        assert_eq!(
+            "1.0.0\n\nHand-crafted notes",
+            read_tag_message(&repository, "1.0.0").unwrap()
+        );
+        assert!(read_tag_message(&repository, "1.0.1")
+            .unwrap_err()
+            .to_string()
+            .contains("lightweight"));
+        assert!(read_tag_message(&repository, "2.0.0").is_err());
+    }
+
+    #[test]
+    fn parses_a_tag_date() {
+        assert_eq!(Ok(TagDate::Commit), parse_tag_date("commit"));
+        assert_eq!(
+            Ok(TagDate::At(Time::new(1717243200, 120))),
+            parse_tag_date("2024-06-01T14:00:00+02:00")
+        );
+        assert!(parse_tag_date("yesterday").is_err());
+    }
+
+    #[test]
+    fn dates_a_signature() {
+        let signature = Signature::now("wr", "wr@example.com").unwrap();
+        let time = Time::new(1717243200, 120);
+
+        let dated = get_signature_at(&signature, &time).unwrap();
+
+        assert_eq!(Some("wr"), dated.name());
+        assert_eq!(Some("wr@example.com"), dated.email());
+        assert_eq!(time, dated.when());
+    }
+
+    #[test]
+    fn redates_a_tag_to_its_commit_date() {
+        let (_dir, repository) = init_repository("main");
+        let head = commit(&repository, "feat: add a feature");
+        create_tag(&repository, "1.0.0", "Release notes").unwrap();
+
+        redate_tag(&repository, "1.0.0", TagDate::Commit).unwrap();
+
+        let tag = repository
+            .find_reference("refs/tags/1.0.0")
+            .unwrap()
+            .peel_to_tag()
+            .unwrap();
+        let commit = repository.find_commit(head).unwrap();
+        assert_eq!(head, tag.target_id());
+        assert_eq!(Some("Release notes"), tag.message());
+        assert_eq!(commit.time(), tag.tagger().unwrap().when());
+    }
+
+    #[test]
+    fn detects_a_release_in_progress() {
+        let (_dir, repository) = init_repository("develop");
+        let head = repository
+            .find_commit(repository.head().unwrap().target().unwrap())
+            .unwrap();
+        assert_eq!(None, get_release_in_progress(&repository).unwrap());
+
+        repository.branch("release/1.2.0", &head, false).unwrap();
+        assert_eq!(
+            Some("1.2.0".to_string()),
+            get_release_in_progress(&repository).unwrap()
+        );
+
+        repository
+            .config()
+            .unwrap()
+            .set_str("gitflow.prefix.release", "rel-")
+            .unwrap();
+        assert_eq!(None, get_release_in_progress(&repository).unwrap());
+    }
+
+    #[test]
+    fn resolves_branches_from_the_gitflow_config() {
+        let (_dir, repository) = init_repository("develop");
+        let mut config = repository.config().unwrap();
+        config
+            .set_str("gitflow.branch.master", "production")
+            .unwrap();
+        config.set_str("gitflow.branch.develop", "develop").unwrap();
+
+        assert_eq!(
+            ("production".to_string(), "develop".to_string()),
+            resolve_branches(&repository).unwrap()
+        );
+    }
+
+    #[test]
+    fn resolves_branches_from_the_remote_default_branch() {
+        let (_dir, repository) = init_repository("develop");
+        let head = repository.head().unwrap().target().unwrap();
+        repository
+            .reference("refs/remotes/origin/main", head, false, "")
+            .unwrap();
+        repository
+            .reference_symbolic(
+                "refs/remotes/origin/HEAD",
+                "refs/remotes/origin/main",
+                false,
+                "",
+            )
+            .unwrap();
+
+        assert_eq!(
+            ("main".to_string(), "develop".to_string()),
+            resolve_branches(&repository).unwrap()
+        );
+    }
+
+    #[test]
+    fn resolves_branches_from_the_current_branch() {
+        let (_dir, repository) = init_repository("trunk");
+
+        assert_eq!(
+            ("trunk".to_string(), "trunk".to_string()),
+            resolve_branches(&repository).unwrap()
+        );
+    }
+
+    #[test]
+    fn fails_to_resolve_branches_on_a_detached_head() {
+        let (_dir, repository) = init_repository("main");
+        let oid = commit(&repository, "Second commit");
+        repository.set_head_detached(oid).unwrap();
+
+        assert!(resolve_branches(&repository).is_err());
+    }
+
+    #[test]
+    fn creates_an_annotated_tag_on_head() {
+        let (_dir, repository) = init_repository("main");
+        let head = commit(&repository, "Second commit");
+
+        let oid = create_tag(&repository, "1.0.0", "1.0.0").unwrap();
+        let tag = repository.find_tag(oid).unwrap();
+
+        assert_eq!(Some("1.0.0"), tag.name());
+        assert_eq!(Some("1.0.0"), tag.message());
+        assert_eq!(head, tag.target_id());
+    }
+
+    #[test]
+    fn annotates_an_existing_tag_with_a_message() {
+        let (_dir, repository) = init_repository("main");
+        let head = commit(&repository, "Second commit");
+        let original = create_tag(&repository, "1.0.0", "1.0.0").unwrap();
+        let tagger = repository
+            .find_tag(original)
+            .unwrap()
+            .tagger()
+            .unwrap()
+            .to_owned();
+        commit(&repository, "Third commit");
+        repository
+            .config()
+            .unwrap()
+            .set_str("user.name", "someone else")
+            .unwrap();
+
+        let oid = annotate_tag(&repository, "1.0.0", "1.0.0\n\n* Second commit").unwrap();
+        let tag = repository.find_tag(oid).unwrap();
+
+        assert_ne!(original, oid);
+        assert_eq!(Some("1.0.0\n\n* Second commit"), tag.message());
+        assert_eq!(head, tag.target_id());
+        assert_eq!(tagger.name(), tag.tagger().unwrap().name());
+        assert_eq!(
+            oid,
+            repository
+                .find_reference("refs/tags/1.0.0")
+                .unwrap()
+                .target()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn annotates_a_lightweight_tag() {
+        let (_dir, repository) = init_repository("main");
+        let head = commit(&repository, "Second commit");
+        repository
+            .tag_lightweight("1.0.0", &repository.find_object(head, None).unwrap(), false)
+            .unwrap();
+
+        let oid = annotate_tag(&repository, "1.0.0", "Notes").unwrap();
+        let tag = repository.find_tag(oid).unwrap();
+
+        assert_eq!(Some("Notes"), tag.message());
+        assert_eq!(head, tag.target_id());
+        assert_eq!(Some("wr"), tag.tagger().unwrap().name());
+    }
+
+    #[test]
+    fn pushes_a_single_tag() {
+        let (_dir, repository) = init_repository("main");
+        let (_remote_dir, remote_repository) = init_bare_remote(&repository);
+        create_tag(&repository, "1.0.0", "1.0.0").unwrap();
+        create_tag(&repository, "0.9.0", "0.9.0").unwrap();
+
+        let mut remote = repository.find_remote("origin").unwrap();
+        push_tag(&mut remote, "1.0.0", &mut PushOptions::new()).unwrap();
+
+        let tags = remote_repository.tag_names(None).unwrap();
+        assert_eq!(vec![Some("1.0.0")], tags.iter().collect::<Vec<_>>());
+        assert!(remote_repository
+            .find_branch("main", git2::BranchType::Local)
+            .is_err());
+    }
+
+    #[test]
+    fn fails_fast_after_too_many_credentials_attempts() {
+        let mut calls = 0;
+        let mut callback = limit_credentials_attempts(2, |_, _, _| {
+            calls += 1;
+            Cred::username("git")
+        });
+
+        assert!(callback("url", Some("git"), CredentialType::SSH_KEY).is_ok());
+        assert!(callback("url", Some("git"), CredentialType::SSH_KEY).is_ok());
+
+        let error = callback("url", Some("git"), CredentialType::SSH_KEY)
+            .err()
+            .unwrap();
+        drop(callback);
+
+        assert_eq!(2, calls);
+        assert!(matches!(
+            map_remote_error(error).downcast_ref::<WrError>(),
+            Some(WrError::SshAgentNoKeys)
+        ));
+    }
+
+    #[test]
+    fn keeps_other_remote_errors() {
+        let error = map_remote_error(git2::Error::from_str("Connection refused"));
+
+        assert!(error.downcast_ref::<WrError>().is_none());
+    }
+
+    #[test]
+    fn pushes_refs_without_rejection() {
+        let (_dir, repository) = init_repository("main");
+        let (_remote_dir, remote_repository) = init_bare_remote(&repository);
+
+        let mut remote = repository.find_remote("origin").unwrap();
+        let rejected = push_refs(&mut remote, &[ref_by_branch("main")]).unwrap();
+
+        assert!(rejected.is_empty());
+        assert!(remote_repository
+            .find_branch("main", git2::BranchType::Local)
+            .is_ok());
+    }
+
+    #[test]
+    fn resolves_a_commit_merged_into_the_branch() {
+        let (_dir, repository) = init_repository("master");
+        let past = commit(&repository, "feat: add a feature");
+        commit(&repository, "fix: fix a bug");
+
+        assert_eq!(
+            past,
+            resolve_branch_commit(&repository, &past.to_string(), "master")
+                .unwrap()
+                .id()
+        );
+        assert!(resolve_branch_commit(&repository, "HEAD", "master").is_ok());
+
+        let head = repository.head().unwrap().peel_to_commit().unwrap();
+        repository.branch("feature", &head, false).unwrap();
+        repository.set_head("refs/heads/feature").unwrap();
+        let unmerged = commit(&repository, "feat: add an unmerged feature");
+
+        let error =
+            resolve_branch_commit(&repository, &unmerged.to_string(), "master").unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<WrError>(),
+            Some(WrError::CommitNotOnBranch { branch, .. }) if branch == "master"
+        ));
+        assert!(resolve_branch_commit(&repository, "unknown", "master").is_err());
+
+        // A stale local branch is not trusted over the remote-tracking one
+        repository
+            .reference("refs/remotes/origin/master", unmerged, true, "Fetch")
+            .unwrap();
+        assert!(resolve_branch_commit(&repository, &unmerged.to_string(), "origin/master").is_ok());
+    }
+
+    #[test]
+    fn checks_that_master_was_merged_into_develop() {
+        let (_dir, repository) = init_repository("master");
+        let head = repository.head().unwrap().peel_to_commit().unwrap();
+        repository.branch("develop", &head, false).unwrap();
+
+        assert!(is_branch_merged(&repository, "master", "develop").unwrap());
+
+        // develop moves on, still holding master
+        repository.set_head("refs/heads/develop").unwrap();
+        commit(&repository, "feat: add a feature");
+        assert!(is_branch_merged(&repository, "master", "develop").unwrap());
+
+        // master receives a release develop does not have
+        repository.set_head("refs/heads/master").unwrap();
+        commit(&repository, "Merge branch 'release/1.0.0'");
+        assert!(!is_branch_merged(&repository, "master", "develop").unwrap());
+        assert!(is_branch_merged(&repository, "master", "unknown").is_err());
+    }
+
+    #[test]
+    fn tags_a_past_commit() {
+        let (_dir, repository) = init_repository("master");
+        let past = commit(&repository, "feat: add a feature");
+        commit(&repository, "fix: fix a bug");
+
+        let past_commit = repository.find_commit(past).unwrap();
+        create_tag_at(&repository, "1.0.0", &past_commit, "1.0.0").unwrap();
+
+        let tagged = repository
+            .revparse_single("1.0.0")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(past, tagged.id());
+        assert_ne!(repository.head().unwrap().target().unwrap(), tagged.id());
+    }
+
+    #[test]
+    fn describes_a_rejection_with_the_remote_messages() {
+        assert_eq!(
+            "pre-receive hook declined",
+            describe_rejection("pre-receive hook declined", "")
+        );
+        assert_eq!(
+            "pre-receive hook declined: GitLab: You are not allowed to push code to protected branches on this project.",
+            describe_rejection(
+                "pre-receive hook declined",
+                "\nGitLab: You are not allowed to push code to protected branches on this project.\r\n\n"
+            )
+        );
+    }
+
+    #[test]
+    fn detects_a_protected_branch_rejection() {
+        assert!(is_protected_branch_rejection(
+            "GitLab: You are not allowed to push code to protected branches on this project."
+        ));
+        assert!(is_protected_branch_rejection(
+            "GitLab: You are not allowed to force push code to a protected branch on this project."
+        ));
+        assert!(!is_protected_branch_rejection("pre-receive hook declined"));
+        assert!(!is_protected_branch_rejection("non-fast-forward"));
+    }
+
+    #[test]
+    fn detects_a_protected_tag_rejection() {
+        assert!(is_protected_tag_rejection(
+            "GitLab: You are not allowed to create this tag as it is protected."
+        ));
+        assert!(is_protected_tag_rejection(
+            "GitLab: Protected tags cannot be updated."
+        ));
+        assert!(!is_protected_tag_rejection("pre-receive hook declined"));
+        assert!(!is_protected_tag_rejection("already exists"));
+    }
 }
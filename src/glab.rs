@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::{env, fs};
+
+use anyhow::{anyhow, Error};
+use serde::Deserialize;
+
+/// The configuration file of the `glab` CLI, holding its credentials
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct GlabConfig {
+    /// The default host.
+    host: Option<String>,
+    /// The settings of each host, by name.
+    hosts: HashMap<String, GlabHost>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct GlabHost {
+    /// The access token of the host.
+    token: Option<String>,
+}
+
+impl GlabConfig {
+    /// Parse a `glab` configuration
+    fn parse(content: &str) -> Result<GlabConfig, Error> {
+        serde_yaml::from_str(content).map_err(|e| anyhow!("Invalid glab configuration ({}).", e))
+    }
+
+    /// Get the host and the token of the given host, or else of the default
+    /// host of the configuration
+    fn get_credentials(&self, host: Option<&str>) -> Option<(String, String)> {
+        let host = host.or(self.host.as_deref()).unwrap_or("gitlab.com");
+        let token = self.hosts.get(host)?.token.clone()?;
+
+        match token.is_empty() {
+            true => None,
+            false => Some((host.to_string(), token)),
+        }
+    }
+}
+
+/// Get the path of the `glab` configuration file
+fn get_config_path() -> Option<PathBuf> {
+    if let Ok(config_dir) = env::var("GLAB_CONFIG_DIR") {
+        return Some(PathBuf::from(config_dir).join("config.yml"));
+    }
+
+    let config_home = env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| env::var("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok()?;
+
+    Some(config_home.join("glab-cli").join("config.yml"))
+}
+
+/// Read the host and the token of a GitLab host from the `glab`
+/// configuration, if it is authenticated there
+pub fn load_credentials(host: Option<&str>) -> Result<Option<(String, String)>, Error> {
+    let Some(path) = get_config_path().filter(|path| path.exists()) else {
+        debug!("No glab configuration was found.");
+        return Ok(None);
+    };
+
+    debug!("Loading the GitLab credentials from {}.", path.display());
+    let config = GlabConfig::parse(&fs::read_to_string(path)?)?;
+
+    Ok(config.get_credentials(host))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::glab::GlabConfig;
+
+    fn config() -> GlabConfig {
+        GlabConfig::parse(
+            r#"
+git_protocol: ssh
+host: gitlab.example.com
+hosts:
+    gitlab.com:
+        token: glpat-public
+        api_protocol: https
+        user: wr
+    gitlab.example.com:
+        token: glpat-private
+        api_host: gitlab.example.com
+    gitlab.empty.com:
+        token: ""
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn reads_the_credentials_of_a_host() {
+        assert_eq!(
+            Some(("gitlab.com".to_string(), "glpat-public".to_string())),
+            config().get_credentials(Some("gitlab.com"))
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_default_host() {
+        assert_eq!(
+            Some((
+                "gitlab.example.com".to_string(),
+                "glpat-private".to_string()
+            )),
+            config().get_credentials(None)
+        );
+        assert_eq!(
+            Some(("gitlab.com".to_string(), "glpat-public".to_string())),
+            GlabConfig::parse("hosts:\n  gitlab.com:\n    token: glpat-public\n")
+                .unwrap()
+                .get_credentials(None)
+        );
+    }
+
+    #[test]
+    fn ignores_unknown_hosts_and_empty_tokens() {
+        assert_eq!(None, config().get_credentials(Some("gitlab.other.com")));
+        assert_eq!(None, config().get_credentials(Some("gitlab.empty.com")));
+        assert!(GlabConfig::parse("hosts: [").is_err());
+    }
+}
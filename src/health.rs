@@ -0,0 +1,131 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Error};
+use reqwest::blocking::Client;
+use reqwest::redirect::Policy;
+
+/// How long a single probe may take
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The outcome of a health check probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// The application answered with a 2xx status code.
+    Healthy,
+    /// The application answered with another status code.
+    Unhealthy(u16),
+    /// The application could not be reached.
+    Unreachable,
+}
+
+/// Classify the status code a health check URL answered with, if any
+pub fn classify_status(status_code: Option<u16>) -> HealthStatus {
+    match status_code {
+        Some(code) if (200..300).contains(&code) => HealthStatus::Healthy,
+        Some(code) => HealthStatus::Unhealthy(code),
+        None => HealthStatus::Unreachable,
+    }
+}
+
+/// Request a URL, returning the status code it answered with
+fn get_status_code(client: &Client, url: &str) -> Option<u16> {
+    client
+        .get(url)
+        .send()
+        .ok()
+        .map(|response| response.status().as_u16())
+}
+
+/// Probe until the application is healthy or the timeout is reached,
+/// returning the last status
+pub fn poll<F>(mut probe: F, timeout: Duration, interval: Duration) -> HealthStatus
+where
+    F: FnMut() -> Option<u16>,
+{
+    let start = Instant::now();
+
+    loop {
+        let status = classify_status(probe());
+
+        if status == HealthStatus::Healthy || start.elapsed() + interval > timeout {
+            return status;
+        }
+
+        sleep(interval);
+    }
+}
+
+/// Check that the deployed application answers its health check URL
+pub fn check(url: &str, timeout: Duration) -> Result<(), Error> {
+    info!("[Health] Probing \"{}\".", url);
+
+    // A redirect is reported as is, it usually means a misconfiguration
+    let client = Client::builder()
+        .timeout(PROBE_TIMEOUT)
+        .redirect(Policy::none())
+        .build()?;
+
+    match poll(
+        || get_status_code(&client, url),
+        timeout,
+        Duration::from_secs(2),
+    ) {
+        HealthStatus::Healthy => {
+            info!("[Health] \"{}\" is healthy.", url);
+            Ok(())
+        }
+        status => {
+            let reason = match status {
+                HealthStatus::Unhealthy(code) => format!("answered with {}", code),
+                _ => "could not be reached".to_string(),
+            };
+
+            Err(anyhow!(
+                "[Health] \"{}\" {} after {}s. Consider rolling back to the previous version.",
+                url,
+                reason,
+                timeout.as_secs()
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    use crate::health::{classify_status, poll, HealthStatus};
+
+    #[test]
+    fn classifies_the_status_codes() {
+        assert_eq!(HealthStatus::Healthy, classify_status(Some(200)));
+        assert_eq!(HealthStatus::Healthy, classify_status(Some(204)));
+        assert_eq!(HealthStatus::Unhealthy(301), classify_status(Some(301)));
+        assert_eq!(HealthStatus::Unhealthy(503), classify_status(Some(503)));
+        assert_eq!(HealthStatus::Unreachable, classify_status(None));
+    }
+
+    #[test]
+    fn polls_until_the_application_is_healthy() {
+        let responses = RefCell::new(vec![Some(200), Some(502), None]);
+        let responder = || responses.borrow_mut().pop().unwrap();
+
+        let status = poll(responder, Duration::from_secs(1), Duration::from_millis(1));
+
+        assert_eq!(HealthStatus::Healthy, status);
+        assert!(responses.borrow().is_empty());
+    }
+
+    #[test]
+    fn gives_up_after_the_timeout() {
+        let status = poll(
+            || Some(503),
+            Duration::from_millis(20),
+            Duration::from_millis(5),
+        );
+
+        assert_eq!(HealthStatus::Unhealthy(503), status);
+    }
+}
@@ -1,8 +1,10 @@
+use std::sync::{Arc, Mutex};
+
 use serde::Deserialize;
 
 use crate::pipeline::StatusState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Job {
     /// The ID of the job.
     pub id: u64,
@@ -10,4 +12,25 @@ pub struct Job {
     pub status: StatusState,
     /// The name of the job.
     pub name: String,
+    /// The stage of the job.
+    #[serde(default)]
+    pub stage: String,
+}
+
+/// The ids of the jobs being played, to cancel them if the run is aborted
+#[derive(Debug, Default, Clone)]
+pub struct RunningJobs(Arc<Mutex<Vec<u64>>>);
+
+impl RunningJobs {
+    pub fn add(&self, job_id: u64) {
+        self.0.lock().unwrap().push(job_id);
+    }
+
+    pub fn remove(&self, job_id: u64) {
+        self.0.lock().unwrap().retain(|id| *id != job_id);
+    }
+
+    pub fn ids(&self) -> Vec<u64> {
+        self.0.lock().unwrap().clone()
+    }
 }
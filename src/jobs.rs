@@ -0,0 +1,146 @@
+use anyhow::{anyhow, Error};
+use gitlab::{
+    api::{
+        common::SortOrder,
+        projects::{self, pipelines::PipelineOrderBy},
+        Query,
+    },
+    Gitlab,
+};
+
+use crate::api::dump_query;
+use crate::job::Job;
+use crate::pipeline::{Pipeline, StatusState};
+use crate::PROJECT_NAME;
+
+/// Get the latest pipeline of a ref
+pub fn get_latest_pipeline(gitlab: &Gitlab, pipeline_ref: &str) -> Result<Pipeline, Error> {
+    let pipelines_endpoint = projects::pipelines::Pipelines::builder()
+        .project(PROJECT_NAME.to_string())
+        .ref_(pipeline_ref)
+        .order_by(PipelineOrderBy::Id)
+        .sort(SortOrder::Descending)
+        .build()
+        .unwrap();
+    let pipelines: Vec<Pipeline> =
+        dump_query(pipelines_endpoint, |endpoint| endpoint.query(gitlab))?;
+
+    pipelines
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("[Jobs] No pipeline was found on \"{}\".", pipeline_ref))
+}
+
+/// Get the jobs of a pipeline
+pub fn get_jobs(gitlab: &Gitlab, pipeline_id: u64) -> Result<Vec<Job>, Error> {
+    let jobs_endpoint = projects::pipelines::PipelineJobs::builder()
+        .project(PROJECT_NAME.to_string())
+        .pipeline(pipeline_id)
+        .build()
+        .unwrap();
+
+    let jobs = dump_query(jobs_endpoint, |endpoint| endpoint.query(gitlab))?;
+
+    Ok(jobs)
+}
+
+/// Format the jobs of a pipeline into the rows of a table
+fn format_jobs(jobs: &[Job]) -> Vec<String> {
+    format_jobs_with(jobs, |status| format!("{:?}", status))
+}
+
+/// Format the jobs of a pipeline into the rows of a table, with the given
+/// formatting of their status
+pub fn format_jobs_with<F>(jobs: &[Job], format_status: F) -> Vec<String>
+where
+    F: Fn(StatusState) -> String,
+{
+    let name_width = jobs
+        .iter()
+        .map(|job| job.name.len())
+        .chain(["NAME".len()])
+        .max()
+        .unwrap_or_default();
+    let stage_width = jobs
+        .iter()
+        .map(|job| job.stage.len())
+        .chain(["STAGE".len()])
+        .max()
+        .unwrap_or_default();
+
+    let row = |id: &str, name: &str, stage: &str, status: &str| {
+        format!(
+            "{:<10} {:<name_width$} {:<stage_width$} {}",
+            id, name, stage, status
+        )
+    };
+
+    let mut rows = vec![row("ID", "NAME", "STAGE", "STATUS")];
+    rows.extend(jobs.iter().map(|job| {
+        row(
+            &job.id.to_string(),
+            &job.name,
+            &job.stage,
+            &format_status(job.status),
+        )
+    }));
+
+    rows
+}
+
+/// List the jobs of a pipeline, or of the latest pipeline of a ref, to know
+/// the names of the deploy jobs
+pub fn list(gitlab: &Gitlab, pipeline_ref: &str, pipeline_id: Option<u64>) -> Result<(), Error> {
+    let pipeline_id = match pipeline_id {
+        Some(pipeline_id) => pipeline_id,
+        None => get_latest_pipeline(gitlab, pipeline_ref)?.id,
+    };
+
+    let jobs = get_jobs(gitlab, pipeline_id)?;
+
+    info!("[Jobs] Pipeline {}:", pipeline_id);
+    for row in format_jobs(&jobs) {
+        println!("{}", row);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::job::Job;
+    use crate::jobs::format_jobs;
+    use crate::pipeline::StatusState;
+
+    #[test]
+    fn formats_the_jobs_into_a_table() {
+        let jobs = vec![
+            Job {
+                id: 1201,
+                status: StatusState::Success,
+                name: "build".to_string(),
+                stage: "build".to_string(),
+            },
+            Job {
+                id: 1202,
+                status: StatusState::Manual,
+                name: "deploy_production".to_string(),
+                stage: "deploy".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            vec![
+                "ID         NAME              STAGE  STATUS",
+                "1201       build             build  Success",
+                "1202       deploy_production deploy Manual",
+            ],
+            format_jobs(&jobs)
+        );
+    }
+
+    #[test]
+    fn formats_an_empty_pipeline() {
+        assert_eq!(vec!["ID         NAME STAGE STATUS"], format_jobs(&[]));
+    }
+}
@@ -1,4 +1,7 @@
-use clap::Parser;
+use clap::builder::{FalseyValueParser, PossibleValue};
+use clap::{
+    Arg, ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+};
 
 use anyhow::{anyhow, Error};
 
@@ -10,42 +13,114 @@ extern crate simplelog;
 extern crate lazy_static;
 
 use indicatif::HumanDuration;
+use miette::{GraphicalReportHandler, GraphicalTheme};
 use simplelog::*;
 
+use std::backtrace::BacktraceStatus;
+use std::collections::HashMap;
 use std::env;
+use std::fmt;
 use std::process;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
-use gitlab::Gitlab;
+use gitlab::{Gitlab, GitlabError};
+use regex::Regex;
 
 mod system;
 use system::System;
 
 mod job;
+mod jobs;
+use job::RunningJobs;
+
+mod phases;
+use phases::{PhaseRunner, Phases};
 
 mod pipeline;
 
 mod environment;
-use environment::Environment;
+mod environment_ref;
+use environment::{AliasParser, Environment};
 
 mod semver_type;
+mod signature;
 use semver_type::SemverType;
 
 mod release;
-use release::Release;
+use release::{Release, VersionScheme};
+
+mod release_model;
+mod releases;
+use release_model::ReleaseModel;
 
 use crate::git::get_project_name;
-use crate::git::{get_gitflow_branch_name, get_repository};
+use crate::git::{
+    get_branches, get_gitlab_host, get_repository, init_branches, parse_tag_date, set_git_flow_bin,
+    set_remote_name, TagDate,
+};
+
+mod abort;
+mod api;
+
+mod changelog;
+mod ci_output;
+mod ci_status;
+use changelog::{ChangelogSource, CommitRange, CommitsFormat};
+
+mod config;
+use config::{mask, resolve_env, Config, Setting, Source};
+
+mod confirm;
+use confirm::{ConfirmPolicy, Confirmation};
 
+mod dashboard;
+mod deployed;
+mod dotenv;
+
+mod error;
+use error::WrError;
+mod event;
+use event::{Event, EventEmitter};
 mod git;
+mod glab;
+mod health;
+mod merge_request;
+mod notify;
+mod plan;
+mod prune;
 mod repository_status;
+mod run_state;
+use plan::{Branches, Plan};
+use run_state::RunState;
+mod schema;
+use schema::SchemaKind;
+mod stats;
+mod steps;
+use steps::{Phase, StepRunner};
+mod strict_semver;
+mod tags;
+mod trigger;
+use trigger::{parse_variable, Trigger};
+mod version_format;
+mod version_sync;
+mod versioning;
+use versioning::Versioning;
+mod watchdog;
+use watchdog::Watchdog;
+#[cfg(test)]
+mod test_utils;
 
 const DEVELOP: &str = "develop";
 const MASTER: &str = "master";
 
+/// Whether to report the whole chain of causes of an error
+static VERBOSE_DIAGNOSTICS: AtomicBool = AtomicBool::new(false);
+
 lazy_static! {
-    static ref DEVELOP_BRANCH: String = get_gitflow_branch_name(DEVELOP);
-    static ref MASTER_BRANCH: String = get_gitflow_branch_name(MASTER);
+    static ref MASTER_BRANCH: String = get_branches().0.clone();
+    static ref DEVELOP_BRANCH: String = get_branches().1.clone();
     static ref PROJECT_NAME: String = get_project_name();
 }
 
@@ -56,32 +131,648 @@ struct Cli {
     #[clap(long, action)]
     deploy: bool,
 
+    /// Only push a release created beforehand, without creating nor deploying it
+    #[clap(long, action)]
+    only_push: bool,
+
+    /// Only launch the deploy job of a release pushed beforehand
+    #[clap(long, action)]
+    only_deploy: bool,
+
+    /// Check that the environment deploys from its own branch, and that the
+    /// branch is protected on GitLab, before deploying
+    #[clap(long, action)]
+    verify_environment_ref: bool,
+
+    /// Play this job to deploy, instead of looking for the deploy jobs in
+    /// the latest pipeline
+    #[clap(long, value_name = "ID")]
+    deploy_job_id: Option<u64>,
+
+    /// Play a created deploy job right away, instead of waiting for the
+    /// previous jobs of its pipeline to be over
+    #[clap(long, action)]
+    no_wait_created: bool,
+
+    /// Resume the previous run from its first incomplete phase
+    #[clap(long = "continue", action)]
+    continue_run: bool,
+
     /// Print additional debug information
     #[clap(short, long, action)]
     debug: bool,
 
-    /// Allow to make a release even if the remote is up to date
+    /// Log each call to the GitLab API and its outcome, implying the debug
+    /// flag
+    #[clap(long, action)]
+    dump_api_calls: bool,
+
+    /// Report the underlying causes of an error, and its backtrace when
+    /// RUST_BACKTRACE is set
+    #[clap(long, action)]
+    verbose_diagnostics: bool,
+
+    /// Read the GitLab host and token from the glab CLI configuration, when
+    /// the GITLAB_TOKEN variable is not set
+    #[clap(long, action)]
+    use_glab_config: bool,
+
+    /// Reach GitLab over plain HTTP instead of HTTPS, for internal instances
+    /// (also set by the GITLAB_INSECURE variable)
+    #[clap(long, action)]
+    insecure: bool,
+
+    /// Allow to make a release even if the remote is up to date, or from the master branch
     #[clap(short, long, action)]
     force: bool,
 
-    /// Define the deploy environment
+    /// Allow to recreate the tag of the new version when it already exists
+    #[clap(long, action)]
+    force_tag: bool,
+
+    /// Define the deploy environment, by name or alias
     #[clap(short, long, value_enum, default_value_t = Environment::Production)]
     environment: Environment,
 
     /// Define how to increment the version number
     #[clap(short, long, value_enum, default_value_t = SemverType::Patch)]
     semver_type: SemverType,
+
+    /// Infer how to increment the version number from the conventional commits
+    /// since the last tag
+    #[clap(long, action, conflicts_with = "semver-type")]
+    auto: bool,
+
+    /// Ignore the prerelease tags (like "2.0.0-rc.1") when looking for the
+    /// latest version
+    #[clap(long, action)]
+    exclude_prerelease: bool,
+
+    /// Define when a release can be made: only when develop is ahead of its
+    /// upstream (git-flow), or whenever it is not behind (tag-develop)
+    #[clap(long, value_enum, default_value_t = ReleaseModel::GitFlow)]
+    release_model: ReleaseModel,
+
+    /// Define how to number the releases: by bumping the semver type, or by
+    /// date as "YYYY.MM.PATCH" (calver), which ignores the prerelease channels
+    #[clap(long, value_enum, default_value_t = Versioning::Semver)]
+    versioning: Versioning,
+
+    /// Keep the lower components of the version on a minor or major bump
+    /// (e.g. "1.3.3" after "1.2.3"), which is not standard semver
+    #[clap(long, action)]
+    no_reset_lower: bool,
+
+    /// Abort when the release tag is not greater than both the latest local
+    /// tag and the latest GitLab release. A tag recreated with --force-tag is
+    /// compared with the other versions, and backfills are not checked.
+    #[clap(long, action)]
+    prevent_downgrade: bool,
+
+    /// Post the failed deploy jobs to this webhook, as JSON "deploy_failed"
+    /// events with the end of their log
+    #[clap(long, value_name = "URL", requires = "deploy")]
+    deploy_on_failure_notify: Option<String>,
+
+    /// Define the title of the GitLab release, with the "{version}" and
+    /// "{date}" placeholders (default: the tag)
+    #[clap(
+        long,
+        value_name = "TEMPLATE",
+        value_parser = version_format::parse_release_name,
+        requires = "gitlab-release"
+    )]
+    release_name: Option<String>,
+
+    /// Define the path of the GitLab CI configuration file, relative to the
+    /// repository root (default: ".gitlab-ci.yml")
+    #[clap(long, value_name = "PATH")]
+    ci_config_path: Option<String>,
+
+    /// Define the remote to push to, and to detect the GitLab host and project from
+    #[clap(long, value_name = "NAME", default_value = "origin")]
+    remote: String,
+
+    /// Only create and push a tag on the current commit, without git-flow
+    #[clap(long, action)]
+    tag_only: bool,
+
+    /// Tag a past commit of the master branch as a release and push the tag,
+    /// without git-flow, to backfill a release. The version follows the latest
+    /// tag preceding the commit.
+    #[clap(long, value_name = "REV")]
+    tag_existing_commit: Option<String>,
+
+    /// Write the version, the environment and the pipeline URL of the release
+    /// as job outputs, for the next steps of a GitHub Actions or GitLab CI job
+    /// (in GitLab CI, declare the "wr.env" file as a dotenv report artifact)
+    #[clap(long, action)]
+    ci_output: bool,
+
+    /// Print a one-line summary of the run on stdout, the logs going to
+    /// stderr
+    #[clap(long, action)]
+    summary: bool,
+
+    /// Print the changelog of the release, and annotate the release tag with it
+    #[clap(long, action)]
+    changelog: bool,
+
+    /// Define how to build the changelog, from the commits, the merge requests,
+    /// or the "Release notes" sections of the merge request descriptions
+    #[clap(long, value_enum, default_value_t = ChangelogSource::Commits)]
+    changelog_source: ChangelogSource,
+
+    /// Print the changelog of the pending changes, and exit without releasing
+    #[clap(long, action)]
+    print_changelog: bool,
+
+    /// Print the next version, and exit without releasing
+    #[clap(long, action)]
+    print_next_version: bool,
+
+    /// Format the printed version with a template, using "{version}",
+    /// "{major}", "{minor}", "{patch}", "{pre}" and "{build}"
+    #[clap(
+        long,
+        value_name = "TEMPLATE",
+        value_parser = version_format::parse_version_format,
+        requires = "print-next-version"
+    )]
+    version_format: Option<String>,
+
+    /// Print the commits since the latest tag for external changelog tools,
+    /// and exit without releasing
+    #[clap(long, value_enum, value_name = "FORMAT")]
+    emit_commits: Option<CommitsFormat>,
+
+    /// Run this git-flow binary, instead of the `git flow` subcommand
+    #[clap(long, value_name = "PATH")]
+    git_flow_bin: Option<String>,
+
+    /// Fetch the tags of the remote before computing the next version
+    #[clap(long, action)]
+    tags_from_remote: bool,
+
+    /// Date the tagger of the release tag: "commit" for the date of the tagged
+    /// commit, or an RFC 3339 date
+    #[clap(long, value_name = "DATE", value_parser = parse_tag_date)]
+    tag_date: Option<TagDate>,
+
+    /// Ask which job to play when several jobs match a deploy job name
+    #[clap(long, action)]
+    interactive: bool,
+
+    /// Verify that the commits of the release are signed, aborting otherwise
+    #[clap(long, action)]
+    verify_signatures: bool,
+
+    /// Refuse to make a production release unless the latest pipeline of
+    /// develop succeeded
+    #[clap(long, action)]
+    require_ci_green: bool,
+
+    /// Abort when the release tag does not match this regex
+    #[clap(long, value_name = "REGEX", value_parser = parse_tag_pattern)]
+    tag_pattern: Option<Regex>,
+
+    /// Delete the release branches of the versions already released, and exit
+    #[clap(long, action)]
+    prune_old_release_branches: bool,
+
+    /// Also delete the stale release branches on the remote
+    #[clap(long, action, requires = "prune-old-release-branches")]
+    prune_remote: bool,
+
+    /// Also consider the releases published on GitLab to compute the next
+    /// version
+    #[clap(long, action)]
+    consider_gitlab_releases: bool,
+
+    /// Publish a GitLab release of the tag, described by the changelog
+    #[clap(long, action)]
+    gitlab_release: bool,
+
+    /// Describe the GitLab release with the message of the annotated tag,
+    /// instead of the changelog
+    #[clap(long, action, requires = "gitlab-release")]
+    notes_from_tag_message: bool,
+
+    /// Link a build artifact from the GitLab release (can be repeated)
+    #[clap(
+        long,
+        value_name = "NAME=URL",
+        value_parser = releases::parse_release_asset,
+        requires = "gitlab-release"
+    )]
+    release_asset: Vec<releases::ReleaseAsset>,
+
+    /// Run a command on the release branch before finishing the release,
+    /// deleting the release branch when it fails
+    #[clap(long, value_name = "CMD")]
+    release_branch_check: Option<String>,
+
+    /// Probe this URL after the deploy, until it answers with a 2xx status
+    #[clap(long, value_name = "URL")]
+    health_url: Option<String>,
+
+    /// Give up on the health check after some seconds
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 60,
+        requires = "health-url"
+    )]
+    health_timeout: u64,
+
+    /// Only push master and the tags on a production release, leaving the
+    /// develop branch alone
+    #[clap(long, action)]
+    no_push_develop: bool,
+
+    /// Check that the tag is on the remote after the push, as a server-side
+    /// hook may reject it silently
+    #[clap(long, action)]
+    verify_remote_tag: bool,
+
+    /// Bump from the latest tag reachable from the master or develop
+    /// branches, ignoring the tags of unrelated commits
+    #[clap(long, action)]
+    ignore_unreachable_tags: bool,
+
+    /// Append the distinct authors of the release commits to the changelog,
+    /// the tag message and the GitLab release
+    #[clap(long, action)]
+    include_contributors: bool,
+
+    /// Fail on the tags which look like versions but are not semver ones
+    /// (e.g. "v1.2.3" or "1.0"), instead of ignoring them
+    #[clap(long, action)]
+    strict_semver: bool,
+
+    /// Start the changelog from this revision, instead of the latest tag
+    #[clap(long, value_name = "REF")]
+    since: Option<String>,
+
+    /// Compute the version and the changelog as if the current branch was
+    /// merged into this branch, to preview a release from a feature branch
+    #[clap(long, value_name = "NAME")]
+    base_branch: Option<String>,
+
+    /// Print the resolved configuration, and where each value comes from
+    #[clap(long, action)]
+    print_config: bool,
+
+    /// Define when to ask for a confirmation before releasing
+    #[clap(long, value_enum, default_value_t = ConfirmPolicy::Prod)]
+    confirm: ConfirmPolicy,
+
+    /// Answer "yes" by default to the confirmation prompt
+    #[clap(long, action)]
+    default_yes: bool,
+
+    /// Select the default answer of the confirmation prompt after some seconds
+    #[clap(long, value_name = "SECONDS")]
+    confirm_timeout: Option<u64>,
+
+    /// GPG-sign the merge commits created by git-flow
+    #[clap(long, action)]
+    sign_commits: bool,
+
+    /// Open a merge request to the master branch when it is protected against
+    /// direct pushes, instead of failing
+    #[clap(long, action)]
+    mr_on_protected: bool,
+
+    /// Create the release tag through the GitLab API when it is protected
+    /// against pushes, instead of failing
+    #[clap(long, action)]
+    protected_tag_via_api: bool,
+
+    /// Deploy by triggering a pipeline with this trigger token, instead of
+    /// playing the deploy jobs
+    #[clap(long, value_name = "TOKEN", requires = "trigger-ref")]
+    trigger_token: Option<String>,
+
+    /// Define the branch or tag to trigger the pipeline on
+    #[clap(long, value_name = "REF", requires = "trigger-token")]
+    trigger_ref: Option<String>,
+
+    /// Pass a variable to the triggered pipeline (can be repeated)
+    #[clap(
+        long = "var",
+        value_name = "KEY=VALUE",
+        value_parser = parse_variable,
+        requires = "trigger-token"
+    )]
+    vars: Vec<(String, String)>,
+
+    /// Abort the whole run when it takes longer than some seconds
+    #[clap(long, value_name = "SECONDS")]
+    max_runtime: Option<u64>,
+
+    /// Cancel the deploy jobs still running when the run is aborted
+    #[clap(long, action, requires = "max-runtime")]
+    cancel_on_timeout: bool,
+
+    /// Print the release events as JSON lines on stdout
+    #[clap(long, action)]
+    json: bool,
+
+    /// Print the JSON schema of the events or of the configuration file
+    #[clap(long, value_enum, value_name = "SCHEMA", hide = true)]
+    json_schema: Option<SchemaKind>,
+
+    /// Compute the next version from this version instead of the latest tag,
+    /// to check how the versioning options behave
+    #[clap(long, value_name = "VERSION", hide = true)]
+    simulate_from: Option<semver::Version>,
+
+    /// Print what would be done, without creating, pushing or deploying anything
+    #[clap(long, action)]
+    dry_run: bool,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Report the version deployed to each environment, and how many versions
+    /// are waiting to be deployed
+    Deployed {
+        /// Only report this environment
+        #[clap(short, long, value_enum)]
+        environment: Option<Environment>,
+    },
+    /// List the jobs of a pipeline, by default the latest one of the
+    /// environment's branch
+    Jobs {
+        /// List the jobs of this pipeline
+        #[clap(long, value_name = "ID")]
+        pipeline: Option<u64>,
+    },
+    /// Follow the jobs of the latest pipeline of the environment's branch,
+    /// until it is over
+    Dashboard {
+        /// Refresh the jobs every given number of seconds
+        #[clap(long, value_name = "SECONDS", default_value_t = 2)]
+        interval: u64,
+    },
+    /// Delete the release branch left by a failed run, and go back to develop
+    Abort {
+        /// Do not ask for a confirmation
+        #[clap(long, action)]
+        yes: bool,
+    },
+}
+
+/// Get the environment variable setting an option, e.g. WR_SEMVER_TYPE for
+/// the semver-type option
+fn get_env_var_name(id: &str) -> String {
+    format!("WR_{}", id.to_uppercase().replace('-', "_"))
+}
+
+/// Whether a flag is turned off by its environment variable, with one of the
+/// values clap reads as false
+fn is_turned_off(name: &str) -> bool {
+    env::var(name).is_ok_and(|value| {
+        matches!(
+            value.to_lowercase().as_str(),
+            "" | "0" | "n" | "no" | "f" | "false" | "off"
+        )
+    })
+}
+
+/// Let each option be set with a WR_ prefixed environment variable, which an
+/// explicit flag overrides
+fn with_env_vars(command: clap::Command<'static>) -> clap::Command<'static> {
+    let ids: Vec<&'static str> = command
+        .get_arguments()
+        .filter(|arg| arg.get_long().is_some() && !matches!(arg.get_id(), "help" | "version"))
+        .map(|arg| arg.get_id())
+        .collect();
+
+    ids.into_iter().fold(command, |command, id| {
+        // The command borrows the variable names for the whole run
+        let name: &'static str = Box::leak(get_env_var_name(id).into_boxed_str());
+
+        command.mut_arg(id, |arg| match arg.get_action() {
+            // A flag turned off by its variable (e.g. WR_AUTO=false) is left
+            // unset, clap would count it as passed for the conflicts and the
+            // requirements
+            ArgAction::SetTrue if is_turned_off(name) => arg,
+            // Read the flags as booleans, e.g. WR_DEPLOY=1 or WR_DEPLOY=false
+            ArgAction::SetTrue => arg.env(name).value_parser(FalseyValueParser::new()),
+            _ => arg.env(name),
+        })
+    })
+}
+
+/// Build the command line parser, resolving the environment aliases and
+/// the environment variables
+fn get_command(aliases: HashMap<String, String>) -> clap::Command<'static> {
+    // The aliases are accepted without being listed in the help, next to the
+    // possible values of the environments
+    let alias_values: Vec<PossibleValue<'static>> = aliases
+        .keys()
+        .map(|alias| PossibleValue::new(Box::leak(alias.clone().into_boxed_str())).hide(true))
+        .collect();
+    let alias_parser = AliasParser::new(aliases);
+    let with_aliases = |arg: Arg<'static>| {
+        arg.value_parser(alias_parser.clone())
+            .possible_values(alias_values.clone())
+    };
+
+    with_env_vars(Cli::command())
+        .mut_arg("environment", with_aliases)
+        .mut_subcommand("deployed", |command| {
+            command.mut_arg("environment", with_aliases)
+        })
+}
+
+/// Compile the tag pattern
+fn parse_tag_pattern(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("\"{}\" is not a valid regex ({})", pattern, e))
+}
+
+/// Get the URL of a GitLab instance, served over plain HTTP in insecure mode
+fn get_gitlab_url(gitlab_host: &str, insecure: bool) -> String {
+    let scheme = match insecure {
+        true => "http",
+        false => "https",
+    };
+
+    format!("{}://{}", scheme, gitlab_host)
+}
+
+/// Whether to reach GitLab over plain HTTP, from the flag or the
+/// GITLAB_INSECURE variable
+fn is_insecure(flag: bool, variable: Option<&str>) -> bool {
+    flag || matches!(variable, Some("1" | "true" | "yes"))
+}
+
+/// Connect to a Gitlab instance
+fn connect(gitlab_host: &str, gitlab_token: &str, insecure: bool) -> Result<Gitlab, Error> {
+    let gitlab = match insecure {
+        true => Gitlab::new_insecure(gitlab_host, gitlab_token),
+        false => Gitlab::new(gitlab_host, gitlab_token),
+    };
+
+    gitlab.map_err(|e| match e {
+        // The client authenticates when it is built
+        GitlabError::Api { source } => api::map_token_error(source),
+        e => anyhow!(
+            "Failed to connect to Gitlab instance \"{}\", with token \"{}\" ({})",
+            get_gitlab_url(gitlab_host, insecure),
+            mask(gitlab_token),
+            e
+        ),
+    })
+}
+
+/// Get the GitLab host detected from the remote, or gitlab.com
+fn get_default_gitlab_host() -> String {
+    get_gitlab_host().unwrap_or_else(|| "gitlab.com".to_string())
+}
+
+/// Resolve the GitLab host and token from the environment variables, or else
+/// from the glab configuration when allowed
+fn resolve_gitlab_credentials(use_glab_config: bool) -> ((String, Source), (String, Source)) {
+    let (gitlab_host, gitlab_host_source) = resolve_env("GITLAB_HOST", &get_default_gitlab_host());
+    let (gitlab_token, gitlab_token_source) = resolve_env("GITLAB_TOKEN", "");
+
+    if use_glab_config && gitlab_token_source == Source::Default {
+        let host = match gitlab_host_source {
+            Source::Default => get_gitlab_host(),
+            _ => Some(gitlab_host.clone()),
+        };
+
+        match glab::load_credentials(host.as_deref()) {
+            Ok(Some((glab_host, glab_token))) => {
+                let glab_host_source = match gitlab_host_source {
+                    Source::Default => Source::Glab,
+                    source => source,
+                };
+
+                return ((glab_host, glab_host_source), (glab_token, Source::Glab));
+            }
+            Ok(None) => {}
+            Err(e) => warn!("{}", e),
+        }
+    }
+
+    (
+        (gitlab_host, gitlab_host_source),
+        (gitlab_token, gitlab_token_source),
+    )
+}
+
+/// Get the resolved settings, with their provenance
+fn get_settings(arg_matches: &ArgMatches, matches: &Cli, config: &Config) -> Vec<Setting> {
+    let flag_source = |id: &str| {
+        arg_matches
+            .value_source(id)
+            .map_or(Source::Default, Source::from)
+    };
+
+    let ((gitlab_host, gitlab_host_source), (gitlab_token, gitlab_token_source)) =
+        resolve_gitlab_credentials(matches.use_glab_config);
+
+    let git_flow_bin = matches
+        .git_flow_bin
+        .clone()
+        .unwrap_or_else(|| "git flow".to_string());
+
+    let deploy_jobs_source = match config.get_environment(&matches.environment) {
+        Some(environment) if !environment.deploy_jobs.is_empty() => Source::Config,
+        _ => Source::Default,
+    };
+    let deploy_jobs = matches
+        .environment
+        .get_deploy_job_names(config)
+        .unwrap_or_default();
+
+    vec![
+        Setting {
+            name: "environment",
+            value: matches.environment.to_string(),
+            source: flag_source("environment"),
+        },
+        Setting {
+            name: "semver_type",
+            value: matches.semver_type.to_string(),
+            source: flag_source("semver-type"),
+        },
+        Setting {
+            name: "release_model",
+            value: matches.release_model.to_string(),
+            source: flag_source("release-model"),
+        },
+        Setting {
+            name: "versioning",
+            value: matches.versioning.to_string(),
+            source: flag_source("versioning"),
+        },
+        Setting {
+            name: "changelog_source",
+            value: matches.changelog_source.to_string(),
+            source: flag_source("changelog-source"),
+        },
+        Setting {
+            name: "confirm",
+            value: matches.confirm.to_string(),
+            source: flag_source("confirm"),
+        },
+        Setting {
+            name: "deploy_jobs",
+            value: deploy_jobs.join(", "),
+            source: deploy_jobs_source,
+        },
+        Setting {
+            name: "git_flow_bin",
+            value: git_flow_bin,
+            source: flag_source("git-flow-bin"),
+        },
+        Setting {
+            name: "gitlab_host",
+            value: gitlab_host,
+            source: gitlab_host_source,
+        },
+        Setting {
+            name: "gitlab_token",
+            value: mask(&gitlab_token),
+            source: gitlab_token_source,
+        },
+    ]
 }
 
 fn app() -> Result<(), Error> {
-    let matches = Cli::parse();
+    let started = Instant::now();
+
+    // The configuration is needed first, for the environment aliases
+    let config = Config::load();
+    let aliases = config
+        .as_ref()
+        .map(|config| config.aliases.clone())
+        .unwrap_or_default();
+
+    let arg_matches = get_command(aliases).get_matches();
+    let matches = Cli::from_arg_matches(&arg_matches)?;
+
+    if let Some(kind) = matches.json_schema {
+        println!("{}", schema::get_json_schema(kind));
+        return Ok(());
+    }
 
     // Get the logger filter level
-    let level = if matches.debug {
+    let level = if matches.debug || matches.dump_api_calls {
         LevelFilter::Debug
     } else {
         LevelFilter::Info
     };
+    api::set_dump_api_calls(matches.dump_api_calls);
+    VERBOSE_DIAGNOSTICS.store(matches.verbose_diagnostics, Ordering::Relaxed);
 
     let force = matches.force;
 
@@ -90,35 +781,310 @@ fn app() -> Result<(), Error> {
         .set_time_offset_to_local()
         .unwrap();
 
+    // Keep stdout for the events when printing them as JSON, or for the
+    // printed changelog, commits, version or summary
+    let terminal_mode = match matches.json
+        || matches.print_changelog
+        || matches.emit_commits.is_some()
+        || matches.print_next_version
+        || matches.summary
+    {
+        true => TerminalMode::Stderr,
+        false => TerminalMode::Mixed,
+    };
+
     // Define the logger
     TermLogger::init(
         level,
         log_stdout_config_builder.build(),
-        TerminalMode::Mixed,
+        terminal_mode,
         ColorChoice::Auto,
     )
     .unwrap();
 
-    // Set some env variables
+    let insecure = is_insecure(
+        matches.insecure,
+        env::var("GITLAB_INSECURE").ok().as_deref(),
+    );
+    if insecure {
+        warn!("[Setup] Insecure mode: GitLab is reached over plain HTTP, exposing the token on the network.");
+    }
+
+    // Set some env variables, before any other thread is started
     env::set_var("LANG", "en_US.UTF-8");
     env::set_var("GIT_MERGE_AUTOEDIT", "no");
 
+    let running_jobs = RunningJobs::default();
+
+    // Abort the whole run when it exceeds its budget
+    let _watchdog = matches.max_runtime.map(|seconds| {
+        let running_jobs = running_jobs.clone();
+        let cancel_on_timeout = matches.cancel_on_timeout;
+        let use_glab_config = matches.use_glab_config;
+
+        Watchdog::start(Duration::from_secs(seconds), move || {
+            error!("{}", WrError::RunTimeout { seconds });
+
+            if cancel_on_timeout {
+                let ((gitlab_host, _), (gitlab_token, _)) =
+                    resolve_gitlab_credentials(use_glab_config);
+
+                if let Ok(gitlab) = connect(&gitlab_host, &gitlab_token, insecure) {
+                    release::cancel_jobs(&gitlab, &running_jobs.ids());
+                }
+            }
+
+            process::exit(1);
+        })
+    });
+
+    let config = config?;
+    set_remote_name(&matches.remote);
+
+    if let Some(git_flow_bin) = &matches.git_flow_bin {
+        set_git_flow_bin(git_flow_bin);
+    }
+
+    if matches.print_config {
+        for setting in get_settings(&arg_matches, &matches, &config) {
+            println!("{}", setting);
+        }
+
+        return Ok(());
+    }
+
     // Init
     info!("Welcome to wr.");
-    let gitlab_host = env::var("GITLAB_HOST").unwrap_or_else(|_| "gitlab.com".to_string());
-    let gitlab_token = env::var("GITLAB_TOKEN").unwrap_or_else(|_| "".to_string());
+    let ((gitlab_host, _), (gitlab_token, _)) = resolve_gitlab_credentials(matches.use_glab_config);
 
     // Get a git2 "Repository" struct
     let repository = get_repository()?;
+    init_branches(&repository)?;
+
+    if let Some(Command::Deployed { environment }) = matches.command {
+        let gitlab = connect(&gitlab_host, &gitlab_token, insecure)?;
+        let environments = match environment {
+            Some(environment) => vec![environment],
+            None => Environment::value_variants().to_vec(),
+        };
+
+        return deployed::report(&gitlab, &repository, &environments);
+    }
+
+    if let Some(Command::Jobs { pipeline }) = matches.command {
+        let gitlab = connect(&gitlab_host, &gitlab_token, insecure)?;
+        let pipeline_ref = matches.environment.get_pipeline_ref()?;
+
+        return jobs::list(&gitlab, &pipeline_ref, pipeline);
+    }
+
+    if let Some(Command::Dashboard { interval }) = matches.command {
+        let gitlab = connect(&gitlab_host, &gitlab_token, insecure)?;
+        let pipeline_ref = matches.environment.get_pipeline_ref()?;
+
+        return dashboard::show(&gitlab, &pipeline_ref, Duration::from_secs(interval));
+    }
+
+    if let Some(Command::Abort { yes }) = matches.command {
+        let confirmation = Confirmation {
+            required: !yes,
+            default: matches.default_yes,
+            timeout: matches.confirm_timeout.map(Duration::from_secs),
+        };
+
+        return abort::abort(&repository, &confirmation);
+    }
+
+    if matches.prune_old_release_branches {
+        let last_tag = release::find_last_tag(&repository, matches.exclude_prerelease);
+        let confirmation = Confirmation {
+            required: matches.confirm != ConfirmPolicy::Never,
+            default: matches.default_yes,
+            timeout: matches.confirm_timeout.map(Duration::from_secs),
+        };
+
+        return prune::prune(
+            &repository,
+            last_tag.as_ref(),
+            matches.prune_remote,
+            &confirmation,
+        );
+    }
+
+    let base_branch = matches.base_branch.as_deref();
+
+    if let Some(format) = matches.emit_commits {
+        let last_tag = release::find_last_tag(&repository, matches.exclude_prerelease);
+        let start =
+            changelog::get_range_start(&repository, matches.since.as_deref(), last_tag.as_ref())?;
+        let range = CommitRange::resolve(&repository, start.as_ref(), base_branch)?;
+
+        println!("{}", changelog::emit_commits(&range.commits, format)?);
+        return Ok(());
+    }
+
+    if matches.print_changelog {
+        let last_tag = release::find_last_tag(&repository, matches.exclude_prerelease);
+        let start =
+            changelog::get_range_start(&repository, matches.since.as_deref(), last_tag.as_ref())?;
+
+        let range = CommitRange::resolve(&repository, start.as_ref(), base_branch)?;
+
+        // The commits changelog does not need GitLab
+        let changelog = match matches.changelog_source {
+            ChangelogSource::Commits => changelog::format_commits(&range.commits),
+            ChangelogSource::Mrs => {
+                let gitlab = connect(&gitlab_host, &gitlab_token, insecure)?;
+                let merge_requests =
+                    release::get_merged_merge_requests(&gitlab, &repository, &range)?;
+                changelog::format_merge_requests(&merge_requests)
+            }
+            ChangelogSource::MrDescriptions => {
+                let gitlab = connect(&gitlab_host, &gitlab_token, insecure)?;
+                let merge_requests =
+                    release::get_merged_merge_requests(&gitlab, &repository, &range)?;
+                changelog::format_merge_request_descriptions(&merge_requests)
+            }
+        };
+        let changelog = match matches.include_contributors {
+            true => changelog::append_contributors(&changelog, &range.commits),
+            false => changelog,
+        };
+
+        println!("{}", changelog);
+        return Ok(());
+    }
+
+    // The preview only reads the local tags and commits, like the changelog
+    if matches.print_next_version {
+        if matches.tags_from_remote {
+            info!("[Setup] Fetching the tags from \"{}\".", matches.remote);
+            git::fetch_tags(&mut git::get_remote(&repository)?)?;
+        }
+
+        let last_tag = release::find_base_tag(
+            &repository,
+            matches.exclude_prerelease,
+            matches.ignore_unreachable_tags,
+        );
+        let semver_type = match matches.auto {
+            true => {
+                let start = changelog::get_range_start(&repository, None, last_tag.as_ref())?;
+                let range = CommitRange::resolve(&repository, start.as_ref(), base_branch)?;
+                release::infer_semver_type(&range.commits)
+            }
+            false => matches.semver_type,
+        };
+        let last_tag = match matches.consider_gitlab_releases {
+            true => {
+                let gitlab = connect(&gitlab_host, &gitlab_token, insecure)?;
+                let release_tags = releases::get_release_tags(&gitlab, &PROJECT_NAME)?;
+                release::get_highest_version(last_tag, &release_tags, matches.exclude_prerelease)
+            }
+            false => last_tag,
+        };
+
+        let next_version = release::compute_next_tag(
+            &repository,
+            last_tag.as_ref(),
+            matches.simulate_from.as_ref(),
+            VersionScheme {
+                semver_type,
+                versioning: matches.versioning,
+                channel: config.get_channel(&matches.environment),
+                reset_lower: !matches.no_reset_lower,
+            },
+        )?;
+        let template = matches.version_format.as_deref().unwrap_or("{version}");
+
+        println!(
+            "{}",
+            version_format::format_version(template, &next_version).map_err(Error::msg)?
+        );
+        return Ok(());
+    }
+
+    let steps = StepRunner {
+        steps: &config.steps,
+        dry_run: matches.dry_run,
+    };
+    // The variables of the environment go to the trigger and to the steps
+    let env_file_vars = match config.get_env_file(&matches.environment) {
+        Some(env_file) => dotenv::load(env_file)?,
+        None => vec![],
+    };
+    let mut step_vars: Vec<(&str, String)> = env_file_vars
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.clone()))
+        .collect();
+    step_vars.push(("WR_ENVIRONMENT", matches.environment.to_string()));
+    steps.run(Phase::PreCheck, &step_vars)?;
+
+    // Backfilling a past commit only tags it, without git-flow
+    let tag_only = matches.tag_only || matches.tag_existing_commit.is_some();
 
     // Run some system checks
     // This will ensure that everything is in place to do the deployment
     let s = System {
         repository: &repository,
         force,
+        tag_only,
+        environment: matches.environment,
+        release_model: matches.release_model,
+        ci_config_path: matches.ci_config_path.clone(),
+        // The tags are only needed when the run computes a version
+        fetch_tags: tag_only
+            || matches.environment == Environment::Production
+            || config.get_channel(&matches.environment).is_some(),
     };
-    info!("[Setup] Performing system checks.");
-    s.system_check()?;
+    let mut phases = Phases::from_flags(matches.deploy, matches.only_push, matches.only_deploy)?;
+
+    let state_path = run_state::get_state_path(&repository);
+    let mut state = RunState {
+        environment: matches.environment.to_string(),
+        ..RunState::default()
+    };
+
+    if matches.continue_run {
+        let previous_state = match &state_path {
+            Some(path) => run_state::load(path)?,
+            None => None,
+        };
+
+        match previous_state {
+            Some(previous) if previous.environment != state.environment => {
+                return Err(anyhow!(
+                    "The previous run released to {}, not to {}.",
+                    previous.environment,
+                    state.environment
+                ));
+            }
+            Some(previous) => {
+                phases = previous.resume(phases);
+                info!(
+                    "[Setup] Continuing the previous run (created: {}, pushed: {}, deployed: {}).",
+                    previous.created, previous.pushed, previous.deployed
+                );
+                state = previous;
+            }
+            None => info!("[Setup] No previous run to continue."),
+        }
+    }
+
+    if phases.create || phases.push {
+        info!("[Setup] Performing system checks.");
+        s.system_check()?;
+    } else {
+        debug!("Only deploying, skipping the system checks.");
+    }
+
+    if phases.deploy && !s.has_gitlab_ci() {
+        warn!(
+            "\"deploy\" flag was found, but the repository has no \"{}\" file, impossible to deploy.",
+            s.get_ci_config_path()
+        );
+        phases.deploy = false;
+    }
 
     // Get environment
     debug!("Getting the environment name from the arguments.");
@@ -131,57 +1097,255 @@ fn app() -> Result<(), Error> {
     // Get semver type
     debug!("Getting the semver type from the arguments.");
     let semver_type: SemverType = matches.semver_type;
-    info!(
-        "[Setup] {} semver type was found from the arguments.",
-        semver_type
-    );
+    if !matches.auto {
+        info!(
+            "[Setup] {} semver type was found from the arguments.",
+            semver_type
+        );
+    }
 
     info!("[Setup] Login into Gitlab instance \"{}\".", gitlab_host);
-    let gitlab = match Gitlab::new(&gitlab_host, &gitlab_token) {
-        Ok(client) => client,
-        Err(e) => {
-            return Err(anyhow!(
-                "Failed to connect to Gitlab instance \"{}\", with token \"{}\" ({:?})",
-                &gitlab_host,
-                &gitlab_token,
-                e
-            ))
-        }
-    };
+    let gitlab = connect(&gitlab_host, &gitlab_token, insecure)?;
+
+    if matches.tags_from_remote {
+        info!("[Setup] Fetching the tags from \"{}\".", matches.remote);
+        git::fetch_tags(&mut git::get_remote(&repository)?)?;
+    }
+
+    if matches.strict_semver {
+        debug!("[Setup] Checking that the version tags are semver ones.");
+        strict_semver::check(&repository)?;
+    }
 
-    let release = Release {
+    let events = EventEmitter { json: matches.json };
+
+    let mut release = Release {
         gitlab,
         repository: &repository,
         environment,
         semver_type,
+        tag_only,
+        changelog_source: matches.changelog_source,
+        config: &config,
+        confirmation: Confirmation {
+            required: matches.confirm.requires_confirmation(environment),
+            default: matches.default_yes,
+            timeout: matches.confirm_timeout.map(Duration::from_secs),
+        },
+        mr_on_protected: matches.mr_on_protected,
+        trigger: matches.trigger_token.clone().map(|token| Trigger {
+            token,
+            r#ref: matches.trigger_ref.clone().unwrap_or_default(),
+            // The --var flags override the variables file
+            variables: env_file_vars
+                .iter()
+                .chain(matches.vars.iter())
+                .cloned()
+                .collect(),
+        }),
+        running_jobs,
+        changelog: None,
+        sign_commits: matches.sign_commits,
+        exclude_prerelease: matches.exclude_prerelease,
+        since: matches.since.clone(),
+        tag_date: matches.tag_date,
+        interactive: matches.interactive,
+        tag_pattern: matches.tag_pattern.clone(),
+        consider_gitlab_releases: matches.consider_gitlab_releases,
+        gitlab_release: matches.gitlab_release,
+        notes_from_tag_message: matches.notes_from_tag_message,
+        health_url: matches.health_url.clone(),
+        health_timeout: Duration::from_secs(matches.health_timeout),
+        ignore_unreachable_tags: matches.ignore_unreachable_tags,
+        push_develop: !matches.no_push_develop,
+        verify_remote_tag: matches.verify_remote_tag,
+        events: &events,
+        include_contributors: matches.include_contributors,
+        deploy_job_id: matches.deploy_job_id,
+        wait_created: !matches.no_wait_created,
+        base_branch: matches.base_branch.clone(),
+        verify_environment_ref: matches.verify_environment_ref,
+        force_tag: matches.force_tag,
+        release_assets: matches.release_asset.clone(),
+        release_branch_check: matches.release_branch_check.clone(),
+        tag_commit: matches.tag_existing_commit.clone(),
+        pipeline_url: OnceLock::new(),
+        pipeline_id: state.pipeline_id.map(OnceLock::from).unwrap_or_default(),
+        simulate_from: matches.simulate_from.clone(),
+        protected_tag_via_api: matches.protected_tag_via_api,
+        commit_range: OnceLock::new(),
+        versioning: matches.versioning,
+        reset_lower: !matches.no_reset_lower,
+        prevent_downgrade: matches.prevent_downgrade,
+        deploy_failure_notify: matches.deploy_on_failure_notify.clone(),
+        version: None,
+        release_name: matches.release_name.clone(),
     };
 
-    debug!("[Release] Creating a new {} release.", environment);
-    release.create()?;
-    info!("[Release] A new {} release has been created.", environment);
+    release.check_last_tag_reachability()?;
 
-    debug!(
-        "[Release] Pushing the {} release to the remote repository.",
-        environment
-    );
-    release.push()?;
-    info!(
-        "[Release] {} release has been pushed to the remote repository.",
-        environment
-    );
+    if matches.verify_signatures && phases.create {
+        info!("[Setup] Verifying the signatures of the commits.");
+        release.verify_signatures(force)?;
+    }
+
+    if matches.require_ci_green && phases.create && environment == Environment::Production {
+        info!("[Setup] Checking the latest pipeline of develop.");
+        ci_status::require_ci_green(&release.gitlab, force)?;
+    }
+
+    if matches.auto {
+        debug!("Inferring the semver type from the commits.");
+        release.semver_type = release.infer_semver_type()?;
+    }
+
+    if matches.changelog {
+        debug!("[Release] Generating the changelog.");
+        let changelog = release.get_changelog()?;
+        info!("[Release] Changelog:\n{}", changelog);
+        release.changelog = Some(changelog);
+    }
+
+    // A resumed run releases the version of the run it resumes
+    let version = match (phases.create, state.get_version()?) {
+        (true, _) => release.get_release_version()?,
+        (false, Some(version)) => Some(version),
+        (false, None) => release.get_last_tag().ok(),
+    };
+    if let Some(version) = &version {
+        step_vars.push(("WR_VERSION", version.to_string()));
+    }
+    release.version = version.clone();
+
+    if matches.dry_run && matches.json {
+        let plan = Plan::build(
+            phases,
+            environment,
+            tag_only,
+            version.as_ref(),
+            &config,
+            release.trigger.as_ref(),
+            Branches {
+                master: MASTER_BRANCH.as_str(),
+                develop: DEVELOP_BRANCH.as_str(),
+                push_develop: !matches.no_push_develop,
+            },
+        )?;
+        println!("{}", serde_json::to_string(&plan)?);
+
+        return Ok(());
+    }
+
+    // Compute the stats of the release before creating it
+    let stats = match phases.create {
+        true => Some(release.get_stats()?),
+        false => None,
+    };
 
-    if matches.deploy {
-        if s.has_gitlab_ci() {
-            debug!("\"deploy\" flag was found, trying to play the \"deploy\" job.");
-            release.deploy()?;
-        } else {
-            warn!("\"deploy\" flag was found, but the repository has no \".gitlab-ci.yml\" file, impossible to deploy.")
+    let report = PhaseRunner {
+        phases,
+        environment,
+        version: version.clone(),
+        dry_run: matches.dry_run,
+        steps: &steps,
+        step_vars: &step_vars,
+        events: &events,
+    }
+    .run(&release);
+
+    if report.error.is_some() {
+        info!("[Release] {}.", report.summary());
+    }
+
+    if let (Some(path), false) = (&state_path, matches.dry_run) {
+        match report.error {
+            None => run_state::clear(path)?,
+            Some(_) => {
+                state.version = version.as_ref().map(ToString::to_string);
+                state.pipeline_id = release.pipeline_id.get().copied();
+                state.record(&report);
+                run_state::save(path, &state)?;
+                info!("[Release] Pass --continue to resume the run.");
+            }
         }
     }
 
+    if matches.summary {
+        println!(
+            "{}",
+            report.summary_line(version.as_ref(), environment, started.elapsed())
+        );
+    }
+
+    report.into_result()?;
+
+    if matches.ci_output {
+        let outputs: Vec<(&str, String)> = [
+            ("version", version.as_ref().map(ToString::to_string)),
+            ("environment", Some(environment.to_string())),
+            ("pipeline_url", release.pipeline_url.get().cloned()),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect();
+
+        ci_output::write(&outputs)?;
+    }
+
+    if let Some(stats) = stats {
+        info!("[Release] {}.", stats);
+        events.emit(Event::Summary {
+            environment: environment.to_string(),
+            version: version.map(|version| version.to_string()),
+            stats,
+        });
+    }
+
     Ok(())
 }
 
+/// An error reported as a diagnostic, along with the chain of its causes
+#[derive(Debug)]
+struct Diagnostic<'a>(&'a Error);
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Diagnostic<'_> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.chain().nth(1)
+    }
+}
+
+impl miette::Diagnostic for Diagnostic<'_> {}
+
+/// Render an error to report. When verbose, the report holds all its causes,
+/// and its backtrace when RUST_BACKTRACE or RUST_LIB_BACKTRACE is set.
+fn render_error(err: &Error, verbose: bool) -> String {
+    if !verbose {
+        return err.to_string();
+    }
+
+    let mut report = String::new();
+    let handler = GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor());
+    if handler
+        .render_report(&mut report, &Diagnostic(err))
+        .is_err()
+    {
+        return format!("{:?}", err);
+    }
+
+    let backtrace = err.backtrace();
+    if backtrace.status() == BacktraceStatus::Captured {
+        report.push_str(&format!("\nBacktrace:\n{}", backtrace));
+    }
+
+    report
+}
+
 fn main() {
     let started = Instant::now();
 
@@ -191,8 +1355,195 @@ fn main() {
             0
         }
         Err(err) => {
-            error!("{}", err.to_string());
-            1
+            error!(
+                "{}",
+                render_error(&err, VERBOSE_DIAGNOSTICS.load(Ordering::Relaxed))
+            );
+
+            // Tell a failed deploy apart from a failed release
+            match err.downcast_ref::<WrError>() {
+                Some(WrError::PartialSuccess { .. }) => 2,
+                _ => 1,
+            }
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::{ArgMatches, CommandFactory, FromArgMatches, ValueSource};
+
+    use crate::config::{Config, Source};
+    use std::collections::HashMap;
+
+    use crate::environment::Environment;
+    use crate::error::WrError;
+    use crate::test_utils::with_env;
+    use crate::{
+        get_command, get_env_var_name, get_gitlab_url, get_settings, is_insecure, render_error, Cli,
+    };
+
+    fn parse_with_env_vars(args: Vec<&str>) -> Result<(ArgMatches, Cli), clap::Error> {
+        let arg_matches = get_command(HashMap::new()).try_get_matches_from(args)?;
+        let matches = Cli::from_arg_matches(&arg_matches)?;
+
+        Ok((arg_matches, matches))
+    }
+
+    fn get_setting_source(args: Vec<&str>, name: &str) -> Source {
+        let arg_matches = Cli::command().try_get_matches_from(args).unwrap();
+        let matches = Cli::from_arg_matches(&arg_matches).unwrap();
+
+        get_settings(&arg_matches, &matches, &Config::default())
+            .into_iter()
+            .find(|setting| setting.name == name)
+            .unwrap()
+            .source
+    }
+
+    #[test]
+    fn reports_settings_passed_as_flags() {
+        let args = vec!["wr", "--environment", "staging"];
+
+        assert_eq!(
+            Source::Flag,
+            get_setting_source(args.clone(), "environment")
+        );
+        assert_eq!(Source::Default, get_setting_source(args, "semver_type"));
+    }
+
+    #[test]
+    fn reports_settings_set_in_the_environment() {
+        with_env(&[("GITLAB_HOST", "gitlab.example.com")], || {
+            assert_eq!(Source::Env, get_setting_source(vec!["wr"], "gitlab_host"));
+        });
+    }
+
+    #[test]
+    fn reads_the_options_from_wr_environment_variables() {
+        let vars = [
+            ("WR_REMOTE", "upstream"),
+            ("WR_DEPLOY", "1"),
+            ("WR_CONFIRM_TIMEOUT", "30"),
+        ];
+
+        with_env(&vars, || {
+            let (arg_matches, matches) = parse_with_env_vars(vec!["wr"]).unwrap();
+            assert_eq!("upstream", matches.remote);
+            assert!(matches.deploy);
+            assert_eq!(Some(30), matches.confirm_timeout);
+            assert_eq!(
+                Some(ValueSource::EnvVariable),
+                arg_matches.value_source("remote")
+            );
+
+            let (_, matches) =
+                parse_with_env_vars(vec!["wr", "--remote", "origin", "--confirm-timeout", "5"])
+                    .unwrap();
+            assert_eq!("origin", matches.remote);
+            assert_eq!(Some(5), matches.confirm_timeout);
+        });
+
+        with_env(&[("WR_DEPLOY", "false")], || {
+            let (_, matches) = parse_with_env_vars(vec!["wr"]).unwrap();
+            assert!(!matches.deploy);
+        });
+
+        assert_eq!("WR_SEMVER_TYPE", get_env_var_name("semver-type"));
+    }
+
+    #[test]
+    fn ignores_the_flags_turned_off_by_their_variable() {
+        with_env(&[("WR_AUTO", "false"), ("WR_PRUNE_REMOTE", "0")], || {
+            let (_, matches) = parse_with_env_vars(vec!["wr", "--semver-type", "minor"]).unwrap();
+            assert!(!matches.auto);
+            assert!(!matches.prune_remote);
+        });
+
+        with_env(&[("WR_AUTO", "1")], || {
+            assert!(parse_with_env_vars(vec!["wr", "--semver-type", "minor"]).is_err());
+        });
+    }
+
+    #[test]
+    fn selects_the_scheme_of_the_gitlab_url() {
+        assert_eq!("https://gitlab.com", get_gitlab_url("gitlab.com", false));
+        assert_eq!(
+            "http://gitlab.internal:8080",
+            get_gitlab_url("gitlab.internal:8080", true)
+        );
+    }
+
+    #[test]
+    fn enables_the_insecure_mode_from_the_flag_or_the_variable() {
+        assert!(!is_insecure(false, None));
+        assert!(!is_insecure(false, Some("0")));
+        assert!(is_insecure(false, Some("true")));
+        assert!(is_insecure(true, None));
+    }
+
+    #[test]
+    fn parses_an_environment_alias() {
+        let aliases = HashMap::from([("stg".to_string(), "staging".to_string())]);
+        let arg_matches = get_command(aliases)
+            .try_get_matches_from(vec!["wr", "-e", "stg"])
+            .unwrap();
+        let matches = Cli::from_arg_matches(&arg_matches).unwrap();
+
+        assert_eq!(Environment::Staging, matches.environment);
+        assert!(get_command(HashMap::new())
+            .try_get_matches_from(vec!["wr", "-e", "stg"])
+            .is_err());
+    }
+
+    #[test]
+    fn lists_the_environments_in_the_help() {
+        let aliases = HashMap::from([("stg".to_string(), "staging".to_string())]);
+        let mut help = vec![];
+        get_command(aliases).write_help(&mut help).unwrap();
+
+        assert!(String::from_utf8(help)
+            .unwrap()
+            .contains("[possible values: production, staging]"));
+    }
+
+    #[test]
+    fn parses_repeated_trigger_variables() {
+        let args = vec![
+            "wr",
+            "--trigger-token",
+            "glptt-0123",
+            "--trigger-ref",
+            "master",
+            "--var",
+            "A=1",
+            "--var",
+            "B=2",
+        ];
+        let matches = Cli::from_arg_matches(&Cli::command().get_matches_from(args)).unwrap();
+
+        assert_eq!(
+            vec![
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "2".to_string())
+            ],
+            matches.vars
+        );
+        assert!(Cli::command()
+            .try_get_matches_from(vec!["wr", "--trigger-token", "glptt-0123"])
+            .is_err());
+    }
+
+    #[test]
+    fn reports_the_causes_of_an_error_when_verbose() {
+        let err = anyhow::Error::new(WrError::Git(git2::Error::from_str("remote rejected")))
+            .context("[Git] Unable to push the tags.");
+
+        assert_eq!("[Git] Unable to push the tags.", render_error(&err, false));
+
+        let report = render_error(&err, true);
+        assert!(report.contains("[Git] Unable to push the tags."));
+        assert!(report.contains("remote rejected"));
+        assert!(report.find("Unable to push") < report.find("remote rejected"));
+    }
+}
@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use semver::Version;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Author {
+    /// The username of the author.
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MergeRequest {
+    /// The project-scoped ID of the merge request.
+    pub iid: u64,
+    /// The title of the merge request.
+    pub title: String,
+    /// The author of the merge request.
+    pub author: Author,
+    /// The description of the merge request, in markdown.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The branch the merge request was merged into.
+    pub target_branch: String,
+    /// When the merge request was merged.
+    pub merged_at: Option<DateTime<Utc>>,
+    /// The URL of the merge request.
+    pub web_url: String,
+}
+
+/// The parameters of a merge request to open
+#[derive(Debug, PartialEq, Eq)]
+pub struct MergeRequestParams {
+    pub source_branch: String,
+    pub target_branch: String,
+    pub title: String,
+}
+
+impl MergeRequestParams {
+    /// The merge request bringing a release into a protected branch
+    pub fn for_release(version: &Version, source_branch: &str, target_branch: &str) -> Self {
+        MergeRequestParams {
+            source_branch: source_branch.to_string(),
+            target_branch: target_branch.to_string(),
+            title: format!("Release {}", version),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::Version;
+
+    use crate::merge_request::MergeRequestParams;
+
+    #[test]
+    fn builds_the_merge_request_of_a_release() {
+        let params = MergeRequestParams::for_release(&Version::new(1, 2, 0), "develop", "master");
+
+        assert_eq!(
+            MergeRequestParams {
+                source_branch: "develop".to_string(),
+                target_branch: "master".to_string(),
+                title: "Release 1.2.0".to_string(),
+            },
+            params
+        );
+    }
+}
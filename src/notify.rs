@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+use std::time::Duration;
+
+use anyhow::Error;
+use gitlab::{
+    api::{self, Endpoint, Query},
+    Gitlab,
+};
+use http::Method;
+use reqwest::blocking::Client;
+
+use crate::api::dump_query;
+use crate::environment::Environment;
+use crate::event::Event;
+use crate::job::Job;
+
+/// The number of trailing lines of the job log sent with a failure
+const TRACE_LINES: usize = 20;
+
+/// How long the webhook may take to answer
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The endpoint getting the log of a job
+pub struct JobTrace<'a> {
+    pub project: &'a str,
+    pub job: u64,
+}
+
+impl Endpoint for JobTrace<'_> {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/jobs/{}/trace",
+            self.project.replace('/', "%2F"),
+            self.job
+        )
+        .into()
+    }
+}
+
+/// Keep the last lines of a job log
+fn get_last_lines(trace: &str, count: usize) -> Vec<String> {
+    let lines: Vec<&str> = trace.lines().collect();
+
+    lines[lines.len().saturating_sub(count)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Get the last lines of the log of a job
+fn get_trace_tail(gitlab: &Gitlab, project: &str, job_id: u64) -> Result<Vec<String>, Error> {
+    let endpoint = JobTrace {
+        project,
+        job: job_id,
+    };
+    let trace = dump_query(endpoint, |endpoint| api::raw(endpoint).query(gitlab))?;
+
+    Ok(get_last_lines(
+        &String::from_utf8_lossy(&trace),
+        TRACE_LINES,
+    ))
+}
+
+/// Assemble the event of a failed deploy job
+fn get_deploy_failed_event(
+    environment: Environment,
+    version: Option<String>,
+    job: &Job,
+    pipeline_url: &str,
+    trace: Vec<String>,
+) -> Event {
+    Event::DeployFailed {
+        environment: environment.to_string(),
+        version,
+        job: job.name.clone(),
+        job_id: job.id,
+        pipeline_url: pipeline_url.to_string(),
+        trace,
+    }
+}
+
+/// Post an event to a webhook, as JSON
+fn post(url: &str, event: &Event) -> Result<(), Error> {
+    Client::builder()
+        .timeout(POST_TIMEOUT)
+        .build()?
+        .post(url)
+        .json(event)
+        .send()?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+/// Describe a failed deploy job, with the end of its log
+pub fn describe_deploy_failure(
+    gitlab: &Gitlab,
+    project: &str,
+    environment: Environment,
+    version: Option<String>,
+    job: &Job,
+    pipeline_url: &str,
+) -> Event {
+    let trace = get_trace_tail(gitlab, project, job.id).unwrap_or_else(|e| {
+        warn!(
+            "[Notify] The log of \"{}\" could not be read ({}).",
+            job.name, e
+        );
+        vec![]
+    });
+
+    get_deploy_failed_event(environment, version, job, pipeline_url, trace)
+}
+
+/// Notify a webhook of an event. A notification which cannot be sent is only
+/// logged, not to hide the failure it reports.
+pub fn send(url: &str, event: &Event) {
+    info!("[Notify] Sending the notification to {}.", url);
+
+    if let Err(e) = post(url, event) {
+        warn!("[Notify] The notification could not be sent ({}).", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gitlab::api::Endpoint;
+
+    use crate::environment::Environment;
+    use crate::event::Event;
+    use crate::job::Job;
+    use crate::notify::{get_deploy_failed_event, get_last_lines, JobTrace};
+    use crate::pipeline::StatusState;
+
+    #[test]
+    fn keeps_the_last_lines_of_the_trace() {
+        let trace = "Pulling image\nRunning deploy.sh\nConnection refused\nERROR: Job failed";
+
+        assert_eq!(
+            vec!["Connection refused", "ERROR: Job failed"],
+            get_last_lines(trace, 2)
+        );
+        assert_eq!(4, get_last_lines(trace, 20).len());
+        assert!(get_last_lines("", 20).is_empty());
+    }
+
+    #[test]
+    fn assembles_the_deploy_failed_event() {
+        let job = Job {
+            id: 31,
+            status: StatusState::Failed,
+            name: "deploy_eu".to_string(),
+            stage: "deploy".to_string(),
+        };
+
+        let event = get_deploy_failed_event(
+            Environment::Production,
+            Some("1.2.0".to_string()),
+            &job,
+            "https://gitlab.com/aeyoll/wr/-/pipelines/42",
+            vec!["ERROR: Job failed".to_string()],
+        );
+
+        assert_eq!(
+            r#"{"event":"deploy_failed","environment":"Production","version":"1.2.0","job":"deploy_eu","job_id":31,"pipeline_url":"https://gitlab.com/aeyoll/wr/-/pipelines/42","trace":["ERROR: Job failed"]}"#,
+            serde_json::to_string(&event).unwrap()
+        );
+        assert!(matches!(event, Event::DeployFailed { job_id: 31, .. }));
+    }
+
+    #[test]
+    fn assembles_the_job_trace_request() {
+        let endpoint = JobTrace {
+            project: "aeyoll/wr",
+            job: 31,
+        };
+
+        assert_eq!("projects/aeyoll%2Fwr/jobs/31/trace", endpoint.endpoint());
+    }
+}
@@ -0,0 +1,484 @@
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::{anyhow, Error};
+use semver::Version;
+
+use crate::environment::Environment;
+use crate::error::WrError;
+use crate::event::{Event, EventEmitter};
+use crate::steps::{Phase, StepRunner};
+
+/// The actions making a release
+pub trait Releaser {
+    /// Create the new release
+    fn create(&self) -> Result<(), Error>;
+
+    /// Push the release
+    fn push(&self) -> Result<(), Error>;
+
+    /// Deploy the release
+    fn deploy(&self) -> Result<(), Error>;
+}
+
+/// The phases of the release to run
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Phases {
+    pub create: bool,
+    pub push: bool,
+    pub deploy: bool,
+}
+
+impl Phases {
+    /// Select the phases to run from the arguments
+    pub fn from_flags(deploy: bool, only_push: bool, only_deploy: bool) -> Result<Phases, Error> {
+        match (only_push, only_deploy) {
+            (true, true) => Err(anyhow!(
+                "\"only-push\" and \"only-deploy\" flags can't be used together."
+            )),
+            (true, false) if deploy => Err(anyhow!(
+                "\"only-push\" and \"deploy\" flags can't be used together."
+            )),
+            (true, false) => Ok(Phases {
+                create: false,
+                push: true,
+                deploy: false,
+            }),
+            (false, true) => Ok(Phases {
+                create: false,
+                push: false,
+                deploy: true,
+            }),
+            (false, false) => Ok(Phases {
+                create: true,
+                push: true,
+                deploy,
+            }),
+        }
+    }
+}
+
+pub struct PhaseRunner<'a> {
+    pub phases: Phases,
+    pub environment: Environment,
+    pub version: Option<Version>,
+    pub dry_run: bool,
+    pub steps: &'a StepRunner<'a>,
+    pub step_vars: &'a [(&'a str, String)],
+    pub events: &'a EventEmitter,
+}
+
+impl PhaseRunner<'_> {
+    fn get_version(&self) -> Option<String> {
+        self.version.as_ref().map(Version::to_string)
+    }
+
+    /// Create the release, along with its custom steps
+    fn create(&self, releaser: &impl Releaser) -> Result<(), Error> {
+        let environment = self.environment;
+
+        self.steps.run(Phase::PreRelease, self.step_vars)?;
+
+        if self.dry_run {
+            info!(
+                "[Release] Dry run, skipping the creation of the {} release.",
+                environment
+            );
+        } else {
+            debug!("[Release] Creating a new {} release.", environment);
+            releaser.create()?;
+            info!("[Release] A new {} release has been created.", environment);
+            self.events.emit(Event::Created {
+                environment: environment.to_string(),
+                version: self.get_version(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Push the release, along with its custom steps
+    fn push(&self, releaser: &impl Releaser) -> Result<(), Error> {
+        let environment = self.environment;
+
+        if self.dry_run {
+            info!(
+                "[Release] Dry run, skipping the push of the {} release.",
+                environment
+            );
+        } else {
+            debug!(
+                "[Release] Pushing the {} release to the remote repository.",
+                environment
+            );
+            releaser.push()?;
+            info!(
+                "[Release] {} release has been pushed to the remote repository.",
+                environment
+            );
+            self.events.emit(Event::Pushed {
+                environment: environment.to_string(),
+                version: self.get_version(),
+            });
+        }
+
+        self.steps.run(Phase::PostRelease, self.step_vars)
+    }
+
+    /// Deploy the release, along with its custom steps
+    fn deploy(&self, releaser: &impl Releaser) -> Result<(), Error> {
+        if self.dry_run {
+            info!("[Deploy] Dry run, skipping the deploy job.");
+        } else {
+            debug!("\"deploy\" flag was found, trying to play the \"deploy\" job.");
+            releaser.deploy()?;
+            self.events.emit(Event::Deployed {
+                environment: self.environment.to_string(),
+                version: self.get_version(),
+            });
+        }
+
+        self.steps.run(Phase::PostDeploy, self.step_vars)
+    }
+
+    /// Run the selected phases in order, skipping the ones following a failed
+    /// phase
+    pub fn run(&self, releaser: &impl Releaser) -> RunReport {
+        let mut report = RunReport::default();
+
+        if self.phases.create {
+            report.run("create", || self.create(releaser));
+        }
+
+        if self.phases.push {
+            report.run("push", || self.push(releaser));
+        }
+
+        if self.phases.deploy {
+            report.run("deploy", || self.deploy(releaser));
+        }
+
+        report
+    }
+}
+
+/// The outcome of a phase
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PhaseStatus {
+    Succeeded,
+    Failed,
+    /// Not run, as a previous phase failed
+    Skipped,
+}
+
+impl fmt::Display for PhaseStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+/// The outcome of each phase of a run, and the error of the failed one
+#[derive(Debug, Default)]
+pub struct RunReport {
+    pub phases: Vec<(&'static str, PhaseStatus)>,
+    pub error: Option<Error>,
+}
+
+impl RunReport {
+    /// Run a phase, unless a previous one failed, and record its outcome
+    fn run(&mut self, phase: &'static str, run: impl FnOnce() -> Result<(), Error>) {
+        if self.error.is_some() {
+            self.phases.push((phase, PhaseStatus::Skipped));
+            return;
+        }
+
+        match run() {
+            Ok(()) => self.phases.push((phase, PhaseStatus::Succeeded)),
+            Err(e) => {
+                self.phases.push((phase, PhaseStatus::Failed));
+                self.error = Some(e);
+            }
+        }
+    }
+
+    /// List the phases with the given outcome
+    fn get_phases(&self, status: PhaseStatus) -> Vec<&'static str> {
+        self.phases
+            .iter()
+            .filter(|(_, phase_status)| *phase_status == status)
+            .map(|(phase, _)| *phase)
+            .collect()
+    }
+
+    /// The exit code of the run: 0 on success, 2 when only the deploy failed,
+    /// and 1 when the release itself failed
+    pub fn exit_code(&self) -> i32 {
+        match self.get_phases(PhaseStatus::Failed).first() {
+            None => 0,
+            Some(&"deploy") if !self.get_phases(PhaseStatus::Succeeded).is_empty() => 2,
+            Some(_) => 1,
+        }
+    }
+
+    /// Describe the outcome of each phase
+    pub fn summary(&self) -> String {
+        self.phases
+            .iter()
+            .map(|(phase, status)| format!("{}: {}", phase, status))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Get the outcome of a phase, if it was selected
+    fn get_status(&self, phase: &str) -> Option<PhaseStatus> {
+        self.phases
+            .iter()
+            .find(|(name, _)| *name == phase)
+            .map(|(_, status)| *status)
+    }
+
+    /// Describe the run on a single line, easy to grep in the logs
+    pub fn summary_line(
+        &self,
+        version: Option<&Version>,
+        environment: Environment,
+        duration: Duration,
+    ) -> String {
+        let deployed = match self.get_status("deploy") {
+            Some(PhaseStatus::Succeeded) => "success".to_string(),
+            Some(status) => status.to_string(),
+            None => "none".to_string(),
+        };
+
+        format!(
+            "wr: version={} environment={} pushed={} deployed={} duration={}s",
+            version.map_or("none".to_string(), ToString::to_string),
+            environment,
+            self.get_status("push") == Some(PhaseStatus::Succeeded),
+            deployed,
+            duration.as_secs()
+        )
+    }
+
+    /// Fail with the error of the failed phase, as a partial success when
+    /// only the deploy failed
+    pub fn into_result(self) -> Result<(), Error> {
+        let exit_code = self.exit_code();
+        let succeeded = self.get_phases(PhaseStatus::Succeeded).join(" and ");
+
+        match (exit_code, self.error) {
+            (2, Some(error)) => Err(WrError::PartialSuccess {
+                succeeded,
+                failed: "deploy".to_string(),
+                reason: error,
+            }
+            .into()),
+            (_, Some(error)) => Err(error),
+            (_, None) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use std::time::Duration;
+
+    use anyhow::{anyhow, Error};
+    use semver::Version;
+
+    use crate::environment::Environment;
+    use crate::error::WrError;
+    use crate::event::EventEmitter;
+    use crate::phases::{PhaseRunner, PhaseStatus, Phases, Releaser, RunReport};
+    use crate::steps::StepRunner;
+
+    #[derive(Default)]
+    struct RecordingReleaser {
+        calls: RefCell<Vec<&'static str>>,
+    }
+
+    impl Releaser for RecordingReleaser {
+        fn create(&self) -> Result<(), Error> {
+            self.calls.borrow_mut().push("create");
+            Ok(())
+        }
+
+        fn push(&self) -> Result<(), Error> {
+            self.calls.borrow_mut().push("push");
+            Ok(())
+        }
+
+        fn deploy(&self) -> Result<(), Error> {
+            self.calls.borrow_mut().push("deploy");
+            Ok(())
+        }
+    }
+
+    fn run(deploy: bool, only_push: bool, only_deploy: bool) -> Vec<&'static str> {
+        let steps = StepRunner {
+            steps: &[],
+            dry_run: false,
+        };
+        let runner = PhaseRunner {
+            phases: Phases::from_flags(deploy, only_push, only_deploy).unwrap(),
+            environment: Environment::Production,
+            version: None,
+            dry_run: false,
+            steps: &steps,
+            step_vars: &[],
+            events: &EventEmitter { json: false },
+        };
+        let releaser = RecordingReleaser::default();
+
+        runner.run(&releaser).into_result().unwrap();
+
+        releaser.calls.into_inner()
+    }
+
+    #[test]
+    fn runs_every_phase_by_default() {
+        assert_eq!(vec!["create", "push"], run(false, false, false));
+        assert_eq!(vec!["create", "push", "deploy"], run(true, false, false));
+    }
+
+    #[test]
+    fn runs_only_the_push() {
+        assert_eq!(vec!["push"], run(false, true, false));
+    }
+
+    #[test]
+    fn runs_only_the_deploy() {
+        assert_eq!(vec!["deploy"], run(false, false, true));
+        assert_eq!(vec!["deploy"], run(true, false, true));
+    }
+
+    fn report(outcomes: &[(&'static str, Result<(), &str>)]) -> RunReport {
+        let mut report = RunReport::default();
+
+        for (phase, outcome) in outcomes {
+            report.run(phase, || outcome.map_err(|e| anyhow!("{}", e)));
+        }
+
+        report
+    }
+
+    #[test]
+    fn succeeds_when_every_phase_succeeds() {
+        let report = report(&[("create", Ok(())), ("push", Ok(())), ("deploy", Ok(()))]);
+
+        assert_eq!(0, report.exit_code());
+        assert_eq!(
+            "create: succeeded, push: succeeded, deploy: succeeded",
+            report.summary()
+        );
+        assert!(report.into_result().is_ok());
+    }
+
+    #[test]
+    fn reports_a_failed_deploy_as_a_partial_success() {
+        let report = report(&[
+            ("create", Ok(())),
+            ("push", Ok(())),
+            ("deploy", Err("job failed")),
+        ]);
+
+        assert_eq!(2, report.exit_code());
+        let error = report.into_result().unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<WrError>(),
+            Some(WrError::PartialSuccess { succeeded, reason, .. })
+                if succeeded == "create and push" && reason.to_string() == "job failed"
+        ));
+        // The error of the deploy is kept as the cause
+        assert_eq!(
+            vec![
+                "The create and push phases succeeded, but the deploy failed.",
+                "job failed"
+            ],
+            error.chain().map(ToString::to_string).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn skips_the_phases_following_a_failed_release() {
+        let report = report(&[
+            ("create", Err("git-flow failed")),
+            ("push", Ok(())),
+            ("deploy", Ok(())),
+        ]);
+
+        assert_eq!(1, report.exit_code());
+        assert_eq!(
+            vec![
+                ("create", PhaseStatus::Failed),
+                ("push", PhaseStatus::Skipped),
+                ("deploy", PhaseStatus::Skipped),
+            ],
+            report.phases
+        );
+        assert_eq!(
+            "git-flow failed",
+            report.into_result().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn fails_a_deploy_only_run_as_a_whole() {
+        let report = report(&[("deploy", Err("job failed"))]);
+
+        assert_eq!(1, report.exit_code());
+        assert_eq!("job failed", report.into_result().unwrap_err().to_string());
+    }
+
+    #[test]
+    fn summarizes_a_successful_run_on_a_line() {
+        let report = report(&[("create", Ok(())), ("push", Ok(())), ("deploy", Ok(()))]);
+
+        assert_eq!(
+            "wr: version=2.0.1 environment=Production pushed=true deployed=success duration=47s",
+            report.summary_line(
+                Some(&Version::new(2, 0, 1)),
+                Environment::Production,
+                Duration::from_millis(47_300)
+            )
+        );
+    }
+
+    #[test]
+    fn summarizes_a_partially_failed_run_on_a_line() {
+        let failed_deploy = report(&[
+            ("create", Ok(())),
+            ("push", Ok(())),
+            ("deploy", Err("job failed")),
+        ]);
+        let failed_push = report(&[
+            ("create", Ok(())),
+            ("push", Err("rejected")),
+            ("deploy", Ok(())),
+        ]);
+
+        assert_eq!(
+            "wr: version=2.0.1 environment=Production pushed=true deployed=failed duration=12s",
+            failed_deploy.summary_line(
+                Some(&Version::new(2, 0, 1)),
+                Environment::Production,
+                Duration::from_secs(12)
+            )
+        );
+        assert_eq!(
+            "wr: version=none environment=Staging pushed=false deployed=skipped duration=3s",
+            failed_push.summary_line(None, Environment::Staging, Duration::from_secs(3))
+        );
+        assert!(report(&[("create", Ok(()))])
+            .summary_line(None, Environment::Staging, Duration::ZERO)
+            .contains("pushed=false deployed=none"));
+    }
+
+    #[test]
+    fn refuses_illegal_combinations() {
+        assert!(Phases::from_flags(false, true, true).is_err());
+        assert!(Phases::from_flags(true, true, false).is_err());
+    }
+}
@@ -7,12 +7,12 @@ pub struct Pipeline {
     pub status: String,
     r#ref: String,
     sha: String,
-    web_url: String,
+    pub web_url: String,
     created_at: DateTime<Local>,
     updated_at: DateTime<Local>,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Deserialize, PartialEq, Eq)]
 pub enum StatusState {
     /// The check was created.
     #[serde(rename = "created")]
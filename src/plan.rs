@@ -0,0 +1,259 @@
+use anyhow::Error;
+use semver::Version;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::environment::Environment;
+use crate::git::get_production_branches;
+use crate::phases::Phases;
+use crate::trigger::Trigger;
+
+/// The actions a release would take, printed by a dry run with the json flag
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Plan {
+    /// The deploy environment.
+    pub environment: String,
+    /// The version of the release.
+    pub version: Option<String>,
+    /// The tag that would be created.
+    pub create_tag: Option<String>,
+    /// The refs that would be pushed to the remote repository.
+    pub push_refs: Vec<String>,
+    /// The jobs that would be played to deploy.
+    pub deploy_jobs: Vec<String>,
+    /// The ref of the pipeline that would be watched while deploying.
+    pub pipeline_ref: Option<String>,
+}
+
+/// The git-flow branches of a release
+#[derive(Debug, Clone, Copy)]
+pub struct Branches<'a> {
+    pub master: &'a str,
+    pub develop: &'a str,
+    /// Whether a production release pushes develop along with master.
+    pub push_develop: bool,
+}
+
+impl Plan {
+    /// Describe the actions of the selected phases, given the master and
+    /// develop branches
+    pub fn build(
+        phases: Phases,
+        environment: Environment,
+        tag_only: bool,
+        version: Option<&Version>,
+        config: &Config,
+        trigger: Option<&Trigger>,
+        branches: Branches,
+    ) -> Result<Plan, Error> {
+        let Branches {
+            master, develop, ..
+        } = branches;
+        let tag = version.map(Version::to_string);
+        let tag_ref = tag.as_ref().map(|tag| format!("refs/tags/{}", tag));
+        let branch_ref = |branch: &str| format!("refs/heads/{}", branch);
+
+        let has_channel = config.get_channel(&environment).is_some();
+
+        let create_tag = match (phases.create, environment) {
+            (true, _) if tag_only || has_channel => tag.clone(),
+            (true, Environment::Production) => tag.clone(),
+            _ => None,
+        };
+
+        let push_refs = match (phases.push, environment) {
+            (false, _) => vec![],
+            (true, _) if tag_only => tag_ref.into_iter().collect(),
+            (true, Environment::Production) => {
+                get_production_branches(master, develop, branches.push_develop)
+                    .into_iter()
+                    .map(branch_ref)
+                    .chain(tag_ref)
+                    .collect()
+            }
+            (true, Environment::Staging) if has_channel => [Some(branch_ref(develop)), tag_ref]
+                .into_iter()
+                .flatten()
+                .collect(),
+            (true, Environment::Staging) => vec![branch_ref(develop)],
+        };
+
+        let (deploy_jobs, pipeline_ref) = match (phases.deploy, trigger) {
+            (false, _) => (vec![], None),
+            (true, Some(trigger)) => (vec![], Some(trigger.r#ref.clone())),
+            (true, None) => {
+                let pipeline_ref = match environment {
+                    Environment::Production => master,
+                    Environment::Staging => develop,
+                };
+
+                (
+                    environment.get_deploy_job_names(config)?,
+                    Some(pipeline_ref.to_string()),
+                )
+            }
+        };
+
+        Ok(Plan {
+            environment: environment.to_string(),
+            version: tag,
+            create_tag,
+            push_refs,
+            deploy_jobs,
+            pipeline_ref,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use semver::Version;
+
+    use crate::config::Config;
+    use crate::environment::Environment;
+    use crate::phases::Phases;
+    use crate::plan::{Branches, Plan};
+    use crate::trigger::Trigger;
+
+    fn config() -> Config {
+        Config::parse(
+            r#"
+[environments.production]
+deploy_jobs = ["deploy_eu", "deploy_us"]
+"#,
+        )
+        .unwrap()
+    }
+
+    fn branches(push_develop: bool) -> Branches<'static> {
+        Branches {
+            master: "main",
+            develop: "develop",
+            push_develop,
+        }
+    }
+
+    #[test]
+    fn plans_a_production_release() {
+        let plan = Plan::build(
+            Phases::from_flags(true, false, false).unwrap(),
+            Environment::Production,
+            false,
+            Some(&Version::new(1, 2, 0)),
+            &config(),
+            None,
+            branches(true),
+        )
+        .unwrap();
+
+        assert_eq!(
+            Plan {
+                environment: "Production".to_string(),
+                version: Some("1.2.0".to_string()),
+                create_tag: Some("1.2.0".to_string()),
+                push_refs: vec![
+                    "refs/heads/main".to_string(),
+                    "refs/heads/develop".to_string(),
+                    "refs/tags/1.2.0".to_string(),
+                ],
+                deploy_jobs: vec!["deploy_eu".to_string(), "deploy_us".to_string()],
+                pipeline_ref: Some("main".to_string()),
+            },
+            plan
+        );
+        assert_eq!(
+            r#"{"environment":"Production","version":"1.2.0","create_tag":"1.2.0","push_refs":["refs/heads/main","refs/heads/develop","refs/tags/1.2.0"],"deploy_jobs":["deploy_eu","deploy_us"],"pipeline_ref":"main"}"#,
+            serde_json::to_string(&plan).unwrap()
+        );
+    }
+
+    #[test]
+    fn plans_a_production_release_without_pushing_develop() {
+        let plan = Plan::build(
+            Phases::from_flags(false, false, false).unwrap(),
+            Environment::Production,
+            false,
+            Some(&Version::new(1, 2, 0)),
+            &config(),
+            None,
+            branches(false),
+        )
+        .unwrap();
+
+        assert_eq!(vec!["refs/heads/main", "refs/tags/1.2.0"], plan.push_refs);
+    }
+
+    #[test]
+    fn plans_a_staging_release_without_deploy() {
+        let plan = Plan::build(
+            Phases::from_flags(false, false, false).unwrap(),
+            Environment::Staging,
+            false,
+            None,
+            &config(),
+            None,
+            branches(true),
+        )
+        .unwrap();
+
+        assert_eq!(None, plan.create_tag);
+        assert_eq!(vec!["refs/heads/develop"], plan.push_refs);
+        assert!(plan.deploy_jobs.is_empty());
+        assert_eq!(None, plan.pipeline_ref);
+    }
+
+    #[test]
+    fn plans_a_staging_prerelease_on_a_channel() {
+        let config = Config::parse(
+            r#"
+[environments.staging]
+channel = "beta"
+"#,
+        )
+        .unwrap();
+
+        let plan = Plan::build(
+            Phases::from_flags(false, false, false).unwrap(),
+            Environment::Staging,
+            false,
+            Some(&Version::parse("1.3.0-beta.1").unwrap()),
+            &config,
+            None,
+            branches(true),
+        )
+        .unwrap();
+
+        assert_eq!(Some("1.3.0-beta.1".to_string()), plan.create_tag);
+        assert_eq!(
+            vec!["refs/heads/develop", "refs/tags/1.3.0-beta.1"],
+            plan.push_refs
+        );
+    }
+
+    #[test]
+    fn plans_a_deploy_through_a_trigger() {
+        let trigger = Trigger {
+            token: "glptt-0123".to_string(),
+            r#ref: "production".to_string(),
+            variables: BTreeMap::new(),
+        };
+
+        let plan = Plan::build(
+            Phases::from_flags(false, false, true).unwrap(),
+            Environment::Production,
+            false,
+            Some(&Version::new(1, 1, 0)),
+            &config(),
+            Some(&trigger),
+            branches(true),
+        )
+        .unwrap();
+
+        assert_eq!(None, plan.create_tag);
+        assert!(plan.push_refs.is_empty());
+        assert!(plan.deploy_jobs.is_empty());
+        assert_eq!(Some("production".to_string()), plan.pipeline_ref);
+    }
+}
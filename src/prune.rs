@@ -0,0 +1,125 @@
+use anyhow::Error;
+use git2::{BranchType, Repository};
+use semver::Version;
+
+use crate::confirm::Confirmation;
+use crate::git;
+
+/// Keep the release branches of a version already released, given the release
+/// branch prefix and the latest tag
+fn get_stale_release_branches(
+    branch_names: &[String],
+    prefix: &str,
+    last_tag: &Version,
+) -> Vec<String> {
+    branch_names
+        .iter()
+        .filter(|name| {
+            name.strip_prefix(prefix)
+                .and_then(|version| Version::parse(version).ok())
+                .is_some_and(|version| version <= *last_tag)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Delete the release branches of the versions already released, locally and
+/// on the remote if asked
+pub fn prune(
+    repository: &Repository,
+    last_tag: Option<&Version>,
+    prune_remote: bool,
+    confirmation: &Confirmation,
+) -> Result<(), Error> {
+    let Some(last_tag) = last_tag else {
+        info!("[Prune] No tag found, no release branch is stale.");
+        return Ok(());
+    };
+
+    let prefix = git::get_release_prefix(repository)?;
+    let local_branches = get_stale_release_branches(
+        &git::get_branch_names(repository, BranchType::Local)?,
+        &prefix,
+        last_tag,
+    );
+
+    let remote_prefix = format!("{}/", git::get_remote_name());
+    let remote_branches: Vec<String> = match prune_remote {
+        true => {
+            let names: Vec<String> = git::get_branch_names(repository, BranchType::Remote)?
+                .iter()
+                .filter_map(|name| name.strip_prefix(&remote_prefix).map(str::to_string))
+                .collect();
+
+            get_stale_release_branches(&names, &prefix, last_tag)
+        }
+        false => vec![],
+    };
+
+    if local_branches.is_empty() && remote_branches.is_empty() {
+        info!("[Prune] No release branch is older than {}.", last_tag);
+        return Ok(());
+    }
+
+    for branch in &local_branches {
+        info!("[Prune] {} is stale.", branch);
+    }
+    for branch in &remote_branches {
+        info!("[Prune] {}{} is stale.", remote_prefix, branch);
+    }
+    confirmation.ask("Do you want to delete these branches?")?;
+
+    for branch in &local_branches {
+        repository
+            .find_branch(branch, BranchType::Local)?
+            .delete()?;
+        info!("[Prune] {} was deleted.", branch);
+    }
+
+    if !remote_branches.is_empty() {
+        let refs: Vec<String> = remote_branches
+            .iter()
+            .map(|branch| format!(":refs/heads/{}", branch))
+            .collect();
+        let mut remote = git::get_remote(repository)?;
+        let rejected = git::push_refs(&mut remote, &refs)?;
+
+        for (refname, reason) in &rejected {
+            warn!("[Prune] {} could not be deleted ({}).", refname, reason);
+        }
+        info!(
+            "[Prune] {} remote branches were deleted.",
+            refs.len() - rejected.len()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::Version;
+
+    use crate::prune::get_stale_release_branches;
+
+    #[test]
+    fn identifies_the_stale_release_branches() {
+        let branches: Vec<String> = [
+            "develop",
+            "release/1.0.0",
+            "release/1.2.0",
+            "release/1.3.0",
+            "release/next",
+            "feature/1.0.0",
+        ]
+        .iter()
+        .map(|branch| branch.to_string())
+        .collect();
+
+        assert_eq!(
+            vec!["release/1.0.0", "release/1.2.0"],
+            get_stale_release_branches(&branches, "release/", &Version::new(1, 2, 0))
+        );
+        assert!(get_stale_release_branches(&branches, "rel-", &Version::new(2, 0, 0)).is_empty());
+    }
+}
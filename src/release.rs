@@ -1,81 +1,336 @@
-use semver::Version;
-use std::thread::sleep;
+use semver::{Prerelease, Version};
+use std::borrow::Cow;
+use std::io::{self, IsTerminal};
+use std::sync::{Mutex, OnceLock};
+use std::thread::{self, sleep};
 use std::time::Duration;
 
 use crate::{
+    abort,
+    api::{dump_query, is_not_found},
+    changelog::{self, ChangelogSource, CommitInfo, CommitRange},
+    config::Config,
+    confirm::Confirmation,
+    deployed,
     environment::Environment,
-    git::{self, get_gitflow_branches_refs, get_remote},
-    job::Job,
+    environment_ref,
+    error::WrError,
+    event::{Event, EventEmitter},
+    git::{self, get_remote, TagDate},
+    health,
+    job::{Job, RunningJobs},
+    merge_request::{MergeRequest, MergeRequestParams},
+    notify,
+    phases::Releaser,
     pipeline::Pipeline,
     pipeline::StatusState,
+    releases::{self, ReleaseAsset},
     semver_type::SemverType,
+    signature,
+    stats::ReleaseStats,
+    tags,
+    trigger::{self, Trigger},
+    version_format, version_sync,
+    versioning::{self, Versioning},
 };
 use anyhow::{anyhow, Error};
-use git2::{PushOptions, Repository};
+use chrono::Local;
+use dialoguer::{theme::ColorfulTheme, Select};
+use git2::{BranchType, Commit, Oid, PushOptions, Repository};
 use gitlab::{
     api::{
+        self,
         common::SortOrder,
-        projects::{self, pipelines::PipelineOrderBy},
-        Query,
+        projects::{self, merge_requests::MergeRequestState, pipelines::PipelineOrderBy},
+        Pagination, Query,
     },
     Gitlab,
 };
+use regex::Regex;
 
-use dialoguer::{theme::ColorfulTheme, Confirm};
-use duct::cmd;
+use duct::{cmd, Expression};
 
-use crate::{DEVELOP_BRANCH, PROJECT_NAME};
+use crate::{DEVELOP_BRANCH, MASTER_BRANCH, PROJECT_NAME};
+
+/// How many times to look for the replacement of a vanished deploy job
+const MAX_JOB_REDISCOVERIES: usize = 3;
 
 pub struct Release<'a> {
     pub gitlab: Gitlab,
     pub repository: &'a Repository,
     pub environment: Environment,
     pub semver_type: SemverType,
+    pub tag_only: bool,
+    pub changelog_source: ChangelogSource,
+    pub config: &'a Config,
+    pub confirmation: Confirmation,
+    pub mr_on_protected: bool,
+    pub trigger: Option<Trigger>,
+    pub running_jobs: RunningJobs,
+    pub changelog: Option<String>,
+    pub sign_commits: bool,
+    pub exclude_prerelease: bool,
+    pub since: Option<String>,
+    pub tag_date: Option<TagDate>,
+    pub interactive: bool,
+    pub tag_pattern: Option<Regex>,
+    pub consider_gitlab_releases: bool,
+    pub gitlab_release: bool,
+    pub notes_from_tag_message: bool,
+    pub health_url: Option<String>,
+    pub health_timeout: Duration,
+    pub ignore_unreachable_tags: bool,
+    pub push_develop: bool,
+    pub verify_remote_tag: bool,
+    pub events: &'a EventEmitter,
+    pub include_contributors: bool,
+    pub deploy_job_id: Option<u64>,
+    pub wait_created: bool,
+    pub base_branch: Option<String>,
+    pub verify_environment_ref: bool,
+    pub force_tag: bool,
+    pub release_assets: Vec<ReleaseAsset>,
+    pub release_branch_check: Option<String>,
+    pub tag_commit: Option<String>,
+    /// The URL of the pipeline the release was deployed with, once known.
+    pub pipeline_url: OnceLock<String>,
+    /// The pipeline the deploy jobs are run from, preset when resuming a run.
+    pub pipeline_id: OnceLock<u64>,
+    pub simulate_from: Option<Version>,
+    pub protected_tag_via_api: bool,
+    /// The commits since the latest tag, walked once.
+    pub commit_range: OnceLock<CommitRange>,
+    pub versioning: Versioning,
+    pub reset_lower: bool,
+    pub prevent_downgrade: bool,
+    pub deploy_failure_notify: Option<String>,
+    /// The version being released or deployed, once known.
+    pub version: Option<Version>,
+    pub release_name: Option<String>,
 }
 
 impl Release<'_> {
     /// Fetch the latest tag from a git repository
-    fn get_last_tag(&self) -> Result<Version, Error> {
-        let tags = self.repository.tag_names(None).unwrap();
-
-        let latest_tag = tags
-            .iter()
-            .filter_map(|x| Version::parse(x.unwrap()).ok())
-            .max_by(|x, y| x.cmp(y));
+    pub fn get_last_tag(&self) -> Result<Version, Error> {
+        // A backfill follows the latest tag preceding its commit
+        let last_tag = match self.get_backfill_commit()? {
+            Some(commit) => {
+                find_last_tag_before(self.repository, commit.id(), self.exclude_prerelease)
+            }
+            None => find_base_tag(
+                self.repository,
+                self.exclude_prerelease,
+                self.ignore_unreachable_tags,
+            ),
+        };
 
-        match latest_tag {
+        match last_tag {
             Some(version) => Ok(version),
             None => Err(anyhow!("No tag found")),
         }
     }
 
-    /// Compute the next tag from the existing tag
-    fn get_next_tag(&self) -> Result<Version, Error> {
-        let last_tag = self.get_last_tag();
+    /// Warn when the latest tag is not reachable from the master or develop
+    /// branches, as bumping from it gives a misleading version
+    pub fn check_last_tag_reachability(&self) -> Result<(), Error> {
+        if self.ignore_unreachable_tags {
+            return Ok(());
+        }
 
-        let next_tag: Version = match last_tag {
-            Ok(last_tag) => {
-                let mut next_tag = last_tag;
+        let Some(last_tag) = find_last_tag(self.repository, self.exclude_prerelease) else {
+            return Ok(());
+        };
 
-                match self.semver_type {
-                    SemverType::Major => {
-                        next_tag.major += 1;
-                        next_tag.minor = 0;
-                        next_tag.patch = 0;
-                    }
-                    SemverType::Minor => {
-                        next_tag.minor += 1;
-                        next_tag.patch = 0;
-                    }
-                    SemverType::Patch => next_tag.patch += 1,
-                }
+        let branches = get_lineage_branches();
+        if !is_tag_reachable(self.repository, &last_tag.to_string(), &branches)? {
+            warn!(
+                "[Release] The latest tag {} is not reachable from \"{}\" or \"{}\". Use --ignore-unreachable-tags to bump from the latest reachable tag.",
+                last_tag, branches[0], branches[1]
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Get the latest version, among the local tags and the releases
+    /// published on GitLab
+    fn get_last_published_version(&self) -> Result<Option<Version>, Error> {
+        let release_tags = releases::get_release_tags(&self.gitlab, &PROJECT_NAME)?;
+
+        Ok(get_highest_version(
+            self.get_last_tag().ok(),
+            &release_tags,
+            self.exclude_prerelease,
+        ))
+    }
+
+    /// Compute the next tag from the existing tag, on the prerelease channel
+    /// of the environment if any
+    pub fn get_next_tag(&self) -> Result<Version, Error> {
+        // The releases published since do not matter to a backfill
+        let consider_gitlab_releases = self.consider_gitlab_releases && self.tag_commit.is_none();
+
+        let last_tag = match (&self.simulate_from, consider_gitlab_releases) {
+            (Some(_), _) => None,
+            (None, true) => self.get_last_published_version()?,
+            (None, false) => self.get_last_tag().ok(),
+        };
+
+        compute_next_tag(
+            self.repository,
+            last_tag.as_ref(),
+            self.simulate_from.as_ref(),
+            self.get_version_scheme(),
+        )
+    }
+
+    /// How the next version of the environment is computed
+    fn get_version_scheme(&self) -> VersionScheme<'_> {
+        VersionScheme {
+            semver_type: self.semver_type,
+            versioning: self.versioning,
+            channel: self.config.get_channel(&self.environment),
+            reset_lower: self.reset_lower,
+        }
+    }
+
+    /// Get the version of the release, if it creates a tag
+    pub fn get_release_version(&self) -> Result<Option<Version>, Error> {
+        match self.tag_only || self.is_tagging_environment() {
+            true => Ok(Some(self.get_next_tag()?)),
+            false => Ok(None),
+        }
+    }
+
+    /// Whether the environment is released with a tag: production, or an
+    /// environment with a prerelease channel
+    fn is_tagging_environment(&self) -> bool {
+        self.environment == Environment::Production
+            || self.config.get_channel(&self.environment).is_some()
+    }
+
+    /// Get the version released by this run, which may not be the latest tag
+    /// (e.g. a prerelease, when they are excluded)
+    fn get_version(&self) -> Result<&Version, Error> {
+        self.version
+            .as_ref()
+            .ok_or_else(|| anyhow!("[Release] No version is being released."))
+    }
+
+    /// Get the commit pointed by the latest tag, if any
+    fn get_last_tag_commit(&self) -> Result<Option<Commit<'_>>, Error> {
+        match self.get_last_tag() {
+            Ok(tag) => {
+                let commit = self
+                    .repository
+                    .revparse_single(&tag.to_string())?
+                    .peel_to_commit()?;
+                Ok(Some(commit))
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Get the range of the commits since the latest tag, resolved on first
+    /// use and shared afterwards
+    fn get_commit_range(&self) -> Result<&CommitRange, Error> {
+        if let Some(range) = self.commit_range.get() {
+            return Ok(range);
+        }
+
+        let from = self.get_last_tag_commit()?;
+        let range = self.resolve_range(from.as_ref())?;
+
+        Ok(self.commit_range.get_or_init(|| range))
+    }
+
+    /// Resolve the range of the release from a commit: up to HEAD, or up to
+    /// the backfilled commit
+    fn resolve_range(&self, start: Option<&Commit>) -> Result<CommitRange, Error> {
+        match self.get_backfill_commit()? {
+            Some(end) => CommitRange::until(self.repository, start, &end),
+            None => CommitRange::resolve(self.repository, start, self.base_branch.as_deref()),
+        }
+    }
+
+    /// Get the past commit to backfill a release on, if any
+    fn get_backfill_commit(&self) -> Result<Option<Commit<'_>>, Error> {
+        self.tag_commit
+            .as_deref()
+            .map(|revision| {
+                Ok(self
+                    .repository
+                    .revparse_single(revision)?
+                    .peel_to_commit()?)
+            })
+            .transpose()
+    }
+
+    /// Resolve the past commit to backfill a release on, checking that it was
+    /// merged into the master branch of the remote, the local one may be stale
+    fn resolve_backfill_commit(&self) -> Result<Option<Commit<'_>>, Error> {
+        let Some(revision) = self.tag_commit.as_deref() else {
+            return Ok(None);
+        };
+
+        debug!("[Release] Fetching the {} branch.", MASTER_BRANCH.as_str());
+        git::fetch_branch(&mut get_remote(self.repository)?, &MASTER_BRANCH)?;
+        let master = format!("{}/{}", git::get_remote_name(), MASTER_BRANCH.as_str());
+
+        git::resolve_branch_commit(self.repository, revision, &master).map(Some)
+    }
+
+    /// Verify that the commits since the latest tag are signed
+    pub fn verify_signatures(&self, force: bool) -> Result<(), Error> {
+        let range = self.get_commit_range()?;
+
+        signature::verify_signatures(self.repository, &range.commits, force)
+    }
+
+    /// Compute the stats of the changes since the latest tag
+    pub fn get_stats(&self) -> Result<ReleaseStats, Error> {
+        ReleaseStats::compute(self.repository, self.get_commit_range()?)
+    }
+
+    /// Infer how to increment the version number from the conventional commits
+    /// since the latest tag
+    pub fn infer_semver_type(&self) -> Result<SemverType, Error> {
+        Ok(infer_semver_type(&self.get_commit_range()?.commits))
+    }
+
+    /// Generate the changelog of the changes since the latest tag, or since
+    /// the `since` revision
+    pub fn get_changelog(&self) -> Result<String, Error> {
+        let last_tag = self.get_last_tag().ok();
+        let start =
+            changelog::get_range_start(self.repository, self.since.as_deref(), last_tag.as_ref())?;
+
+        let range = match self.since {
+            None => Cow::Borrowed(self.get_commit_range()?),
+            Some(_) => Cow::Owned(self.resolve_range(start.as_ref())?),
+        };
+
+        let changelog = match self.changelog_source {
+            ChangelogSource::Commits => changelog::format_commits(&range.commits),
+            ChangelogSource::Mrs => {
+                let merge_requests =
+                    get_merged_merge_requests(&self.gitlab, self.repository, &range)?;
+
+                changelog::format_merge_requests(&merge_requests)
+            }
+            ChangelogSource::MrDescriptions => {
+                let merge_requests =
+                    get_merged_merge_requests(&self.gitlab, self.repository, &range)?;
 
-                next_tag
+                changelog::format_merge_request_descriptions(&merge_requests)
             }
-            Err(_) => Version::new(1, 0, 0),
         };
 
-        Ok(next_tag)
+        if self.include_contributors {
+            return Ok(changelog::append_contributors(&changelog, &range.commits));
+        }
+
+        Ok(changelog)
     }
 
     ///
@@ -83,58 +338,321 @@ impl Release<'_> {
         let mut push_options = self.get_push_options();
         let mut remote = get_remote(self.repository)?;
 
-        remote.push(&[git::ref_by_branch(&branch_name)], Some(&mut push_options))?;
+        remote
+            .push(&[git::ref_by_branch(&branch_name)], Some(&mut push_options))
+            .map_err(git::map_remote_error)?;
 
         Ok(())
     }
 
+    /// Push the current branch when the version file was bumped on it, the
+    /// tag pointing to the bump commit
+    fn push_version_bump(&self) -> Result<(), Error> {
+        if self.config.version_file.is_none() || self.get_backfill_commit()?.is_some() {
+            return Ok(());
+        }
+
+        let branch = git::get_current_branch(self.repository).ok_or_else(|| {
+            anyhow!("The version bump cannot be pushed, HEAD is not on a branch.")
+        })?;
+        info!("[Release] Pushing the version bump to {}.", branch);
+
+        self.push_branch(branch)
+    }
+
+    /// Announce that the release is underway, once confirmed and before the
+    /// long git-flow and push operations
+    fn announce_start(&self, version: &Version) {
+        info!("[Release] Release {} started.", version);
+        self.events.emit(Event::ReleaseStarted {
+            environment: self.environment.to_string(),
+            version: version.to_string(),
+        });
+    }
+
     pub fn create_production_release(&self) -> Result<(), Error> {
         let next_tag = self.get_next_tag()?;
 
+        self.check_tag_policy(&next_tag)?;
+        self.check_downgrade(&next_tag)?;
+
         info!("[Release] This will create release tag {}.", next_tag);
+        self.announce_release_name(&next_tag)?;
+        self.confirmation.ask("Do you want to continue?")?;
+        self.announce_start(&next_tag);
 
-        match Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt("Do you want to continue?")
-            .interact_opt()
-            .unwrap()
-        {
-            Some(true) => {
+        clear_existing_tag(self.repository, &next_tag.to_string(), self.force_tag)?;
+
+        let git_flow_env = self.get_git_flow_env()?;
+        let version = next_tag.to_string();
+
+        run_release_branch(
+            || {
                 info!("[Release] Creating release {}.", next_tag);
-                cmd!("git", "flow", "release", "start", next_tag.to_string())
-                    .stdout_capture()
-                    .stderr_capture()
-                    .read()?;
-                cmd!(
-                    "git",
-                    "flow",
-                    "release",
-                    "finish",
-                    "-m",
-                    next_tag.to_string(),
-                    next_tag.to_string()
+                with_env(
+                    git::git_flow(&["release", "start", &version]),
+                    &git_flow_env,
+                )
+                .stdout_capture()
+                .stderr_capture()
+                .read()?;
+                Ok(())
+            },
+            || {
+                self.sync_version_file(&next_tag)?;
+                self.check_release_branch()
+            },
+            || {
+                with_env(
+                    git::git_flow(&["release", "finish", "-m", &version, &version]),
+                    &git_flow_env,
                 )
                 .stdout_capture()
                 .stderr_capture()
                 .read()?;
+                Ok(())
+            },
+            || {
+                warn!("[Release] Deleting the {} release branch.", version);
+                abort::delete_release(&version)
+            },
+        )?;
+
+        if let Some(changelog) = &self.changelog {
+            debug!("[Release] Annotating tag {} with the changelog.", next_tag);
+            git::annotate_tag(
+                self.repository,
+                &next_tag.to_string(),
+                &self.get_tag_message(&next_tag, changelog),
+            )?;
+        }
 
-                cmd!("git", "checkout", DEVELOP_BRANCH.to_string())
-                    .stdout_capture()
-                    .stderr_capture()
-                    .read()?;
+        self.redate_tag(&next_tag)?;
+        self.merge_forward_master()?;
 
-                Ok(())
+        cmd!("git", "checkout", DEVELOP_BRANCH.to_string())
+            .stdout_capture()
+            .stderr_capture()
+            .read()?;
+
+        Ok(())
+    }
+
+    /// Check that develop received the release merged into master, offering
+    /// to merge master into develop when it diverged during the release
+    fn merge_forward_master(&self) -> Result<(), Error> {
+        let (master, develop) = (MASTER_BRANCH.as_str(), DEVELOP_BRANCH.as_str());
+
+        if master == develop || git::is_branch_merged(self.repository, master, develop)? {
+            return Ok(());
+        }
+
+        warn!(
+            "[Release] The {} branch did not receive the release merged into {}.",
+            develop, master
+        );
+        self.confirmation.ask(&format!(
+            "Do you want to merge {} into {}?",
+            master, develop
+        ))?;
+
+        cmd!("git", "checkout", develop)
+            .stdout_capture()
+            .stderr_capture()
+            .read()?;
+        cmd!("git", "merge", "--no-edit", master)
+            .stdout_capture()
+            .stderr_capture()
+            .read()?;
+        info!("[Release] Merged {} into {}.", master, develop);
+
+        Ok(())
+    }
+
+    /// Run the release branch check command on the started release branch,
+    /// if one is given
+    fn check_release_branch(&self) -> Result<(), Error> {
+        let Some(command) = &self.release_branch_check else {
+            return Ok(());
+        };
+
+        info!(
+            "[Release] Checking the release branch with \"{}\".",
+            command
+        );
+        cmd!("sh", "-c", command)
+            .run()
+            .map_err(|e| anyhow!("[Release] The release branch check failed ({}).", e))?;
+
+        Ok(())
+    }
+
+    /// Get the environment of the git-flow commands, signing their commits
+    /// when asked to
+    fn get_git_flow_env(&self) -> Result<Vec<(String, String)>, Error> {
+        if !self.sign_commits {
+            return Ok(vec![]);
+        }
+
+        let signing_key = git::get_signing_key(self.repository)?;
+        debug!(
+            "[Release] Signing the release commits with key {}.",
+            signing_key
+        );
+
+        Ok(git::get_config_env(&[
+            ("commit.gpgsign", "true"),
+            ("user.signingkey", &signing_key),
+        ]))
+    }
+
+    /// Get the message of a tag carrying the changelog
+    fn get_tag_message(&self, version: &Version, changelog: &str) -> String {
+        format!("{}\n\n{}", version, changelog)
+    }
+
+    /// Bump the version file to the new version, if one is configured,
+    /// committing it on the current branch
+    fn sync_version_file(&self, version: &Version) -> Result<(), Error> {
+        let Some(version_file) = &self.config.version_file else {
+            return Ok(());
+        };
+
+        let workdir = self.repository.workdir().ok_or_else(|| {
+            anyhow!(
+                "The repository has no working directory, {} cannot be bumped.",
+                version_file
+            )
+        })?;
+        let path = workdir.join(version_file);
+        let file_version = version_sync::read_version(&path)?;
+        version_sync::verify_version(&file_version, self.get_last_tag().ok().as_ref());
+
+        info!(
+            "[Release] Bumping {} from {} to {}.",
+            version_file, file_version, version
+        );
+        version_sync::bump_version(self.repository, version_file, version)?;
+
+        Ok(())
+    }
+
+    /// Create an annotated tag on the current commit, without git-flow
+    pub fn create_tag_release(&self) -> Result<(), Error> {
+        let next_tag = self.get_next_tag()?;
+
+        self.check_tag_policy(&next_tag)?;
+        self.check_downgrade(&next_tag)?;
+
+        // A past commit of master is tagged as is, to backfill a release
+        let past_commit = self.resolve_backfill_commit()?;
+
+        match &past_commit {
+            Some(commit) => info!(
+                "[Release] This will create tag {} on commit {:.7} (\"{}\").",
+                next_tag,
+                commit.id(),
+                commit.summary().unwrap_or_default()
+            ),
+            None => info!("[Release] This will create tag {} on HEAD.", next_tag),
+        }
+        self.announce_release_name(&next_tag)?;
+        self.confirmation.ask("Do you want to continue?")?;
+        self.announce_start(&next_tag);
+
+        clear_existing_tag(self.repository, &next_tag.to_string(), self.force_tag)?;
+
+        if past_commit.is_none() {
+            self.sync_version_file(&next_tag)?;
+        }
+
+        info!("[Release] Creating tag {}.", next_tag);
+        let message = match &self.changelog {
+            Some(changelog) => self.get_tag_message(&next_tag, changelog),
+            None => next_tag.to_string(),
+        };
+        match &past_commit {
+            Some(commit) => {
+                git::create_tag_at(self.repository, &next_tag.to_string(), commit, &message)?
             }
-            Some(false) => Err(anyhow!("Cancelling.")),
-            None => Err(anyhow!("Aborting.")),
+            None => git::create_tag(self.repository, &next_tag.to_string(), &message)?,
+        };
+        self.redate_tag(&next_tag)?;
+
+        Ok(())
+    }
+
+    /// Get the title of the GitLab release of a version, the tag itself by
+    /// default
+    fn get_release_name(&self, version: &Version) -> Result<String, Error> {
+        let template = self.release_name.as_deref().unwrap_or("{version}");
+        let today = Local::now().date_naive();
+
+        version_format::format_release_name(template, version, today).map_err(Error::msg)
+    }
+
+    /// Announce the title of the GitLab release, when it differs from the tag
+    fn announce_release_name(&self, tag: &Version) -> Result<(), Error> {
+        if self.gitlab_release && self.release_name.is_some() {
+            info!(
+                "[Release] The GitLab release will be named \"{}\".",
+                self.get_release_name(tag)?
+            );
         }
+
+        Ok(())
     }
 
-    /// Create the new release
-    pub fn create(&self) -> Result<(), Error> {
-        match self.environment {
-            Environment::Production => self.create_production_release(),
-            Environment::Staging => Ok(()),
+    /// Check the release tag against the tag pattern, if any
+    fn check_tag_policy(&self, tag: &Version) -> Result<(), Error> {
+        if let Some(tag_pattern) = &self.tag_pattern {
+            check_tag_policy(&tag.to_string(), tag_pattern)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check that the release tag is above the latest local tag and the latest
+    /// GitLab release, when downgrades are prevented. A tag recreated with the
+    /// force-tag flag is only compared with the other versions, and a backfill
+    /// is not checked, its commit being older than the latest release.
+    fn check_downgrade(&self, tag: &Version) -> Result<(), Error> {
+        if !self.prevent_downgrade {
+            return Ok(());
+        }
+
+        if self.tag_commit.is_some() {
+            debug!("[Release] Backfilling a release, skipping the downgrade check.");
+            return Ok(());
+        }
+
+        let recreated = self.force_tag.then(|| tag.to_string());
+        let is_other = |other: &str| Some(other) != recreated.as_deref();
+
+        let local_tags = self.repository.tag_names(None)?;
+        let local = get_latest_version(local_tags.iter().flatten().filter(|t| is_other(t)), false);
+        let release_tags = releases::get_release_tags(&self.gitlab, &PROJECT_NAME)?;
+        let published = get_latest_version(
+            release_tags
+                .iter()
+                .map(String::as_str)
+                .filter(|t| is_other(t)),
+            false,
+        );
+
+        check_downgrade(tag, local.as_ref(), published.as_ref())?;
+
+        Ok(())
+    }
+
+    /// Date the tagger of the release tag, when a tag date is given
+    fn redate_tag(&self, tag: &Version) -> Result<(), Error> {
+        if let Some(tag_date) = self.tag_date {
+            debug!("[Release] Dating tag {} ({:?}).", tag, tag_date);
+            git::redate_tag(self.repository, &tag.to_string(), tag_date)?;
         }
+
+        Ok(())
     }
 
     pub fn get_push_options(&self) -> PushOptions<'static> {
@@ -151,54 +669,167 @@ impl Release<'_> {
 
     /// Deploy to the production environment
     pub fn push_production(&self) -> Result<(), Error> {
-        let mut push_options = self.get_push_options();
-
         // Push master and develop branches
-        let branches_refs: Vec<String> = get_gitflow_branches_refs();
+        let branches_refs: Vec<String> = git::get_production_branches(
+            MASTER_BRANCH.as_str(),
+            DEVELOP_BRANCH.as_str(),
+            self.push_develop,
+        )
+        .into_iter()
+        .map(git::ref_by_branch)
+        .collect();
         let mut remote = get_remote(self.repository)?;
-        remote.push(&branches_refs, Some(&mut push_options))?;
+        let rejected = git::push_refs(&mut remote, &branches_refs)?;
+        self.handle_rejected_branches(rejected)?;
 
-        // Push all tags
-        let tags = self.repository.tag_names(None).unwrap();
-        let tag_refs: Vec<String> = tags
-            .iter()
-            .map(|a| a.unwrap())
-            .map(git::ref_by_tag)
-            .collect();
-        remote.push(&tag_refs, Some(&mut push_options))?;
+        // Push the release tag only, the other local tags are not ours to publish
+        let tag = self.get_version()?.to_string();
+        let rejected = git::push_refs(&mut remote, &[git::ref_by_tag(&tag)])?;
+        self.handle_rejected_tags(rejected)?;
 
         Ok(())
     }
 
-    /// Push the release
-    pub fn push(&self) -> Result<(), Error> {
-        match self.environment {
-            Environment::Production => self.push_production()?,
-            Environment::Staging => self.push_staging()?,
+    /// Create the release tag through the GitLab API when it is protected and
+    /// allowed to, or fail on any rejected tag
+    fn handle_rejected_tags(&self, rejected: Vec<(String, String)>) -> Result<(), Error> {
+        for (refname, status) in rejected {
+            let tag = refname.trim_start_matches("refs/tags/");
+
+            if !git::is_protected_tag_rejection(&status) {
+                return Err(anyhow!("The remote rejected \"{}\" ({}).", refname, status));
+            }
+
+            if !self.protected_tag_via_api {
+                return Err(anyhow!(
+                    "The {} tag is protected ({}). Pass the protected-tag-via-api flag to create it through the GitLab API instead.",
+                    tag,
+                    status
+                ));
+            }
+
+            let commit = self.repository.revparse_single(tag)?.peel_to_commit()?;
+            let message = git::read_tag_message(self.repository, tag).unwrap_or_default();
+            tags::create_tag(
+                &self.gitlab,
+                &PROJECT_NAME,
+                tag,
+                &commit.id().to_string(),
+                &message,
+            )?;
+            info!(
+                "[Push] The {} tag is protected, it was created through the GitLab API instead of git.",
+                tag
+            );
         }
 
         Ok(())
     }
 
-    ///
-    pub fn get_job(&self, job_id: u64) -> Result<Job, Error> {
-        let job_endpoint = projects::jobs::Job::builder()
+    /// Open a merge request to the master branch when it is protected, or fail
+    /// on any other rejected branch
+    fn handle_rejected_branches(&self, rejected: Vec<(String, String)>) -> Result<(), Error> {
+        let master_ref = format!("refs/heads/{}", MASTER_BRANCH.as_str());
+
+        for (refname, status) in rejected {
+            if refname != master_ref || !git::is_protected_branch_rejection(&status) {
+                return Err(anyhow!("The remote rejected \"{}\" ({}).", refname, status));
+            }
+
+            if !self.mr_on_protected {
+                return Err(anyhow!(
+                    "The \"{}\" branch is protected ({}). Pass the mr-on-protected flag to open a merge request instead.",
+                    MASTER_BRANCH.as_str(),
+                    status
+                ));
+            }
+
+            let merge_request = self.create_release_merge_request()?;
+            info!(
+                "[Push] \"{}\" branch is protected, merge request opened: {}",
+                MASTER_BRANCH.as_str(),
+                merge_request.web_url
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Open a merge request from the develop branch to the master branch
+    fn create_release_merge_request(&self) -> Result<MergeRequest, Error> {
+        let params = MergeRequestParams::for_release(
+            self.get_version()?,
+            DEVELOP_BRANCH.as_str(),
+            MASTER_BRANCH.as_str(),
+        );
+
+        let endpoint = projects::merge_requests::CreateMergeRequest::builder()
             .project(PROJECT_NAME.to_string())
-            .job(job_id)
+            .source_branch(params.source_branch)
+            .target_branch(params.target_branch)
+            .title(params.title)
+            .remove_source_branch(false)
             .build()
             .unwrap();
-        let job: Job = job_endpoint.query(&self.gitlab)?;
-        Ok(job)
+
+        let merge_request: MergeRequest =
+            dump_query(endpoint, |endpoint| endpoint.query(&self.gitlab))?;
+
+        Ok(merge_request)
     }
 
-    ///
-    pub fn get_last_pipeline_id(&self) -> Result<u64, Error> {
-        let mut last_pipeline_id: u64 = 0;
+    /// Push the tag created on the current commit
+    pub fn push_tag(&self) -> Result<(), Error> {
+        let mut push_options = self.get_push_options();
+        let mut remote = get_remote(self.repository)?;
+        let tag = self.get_version()?;
+
+        git::push_tag(&mut remote, &tag.to_string(), &mut push_options)?;
+
+        Ok(())
+    }
+
+    /// Publish a GitLab release of the released tag, described by the tag
+    /// message or the changelog
+    fn publish_gitlab_release(&self) -> Result<(), Error> {
+        let version = self.get_version()?;
+        let tag = version.to_string();
+        let description = match self.notes_from_tag_message {
+            true => git::read_tag_message(self.repository, &tag)?,
+            false => self.changelog.clone().unwrap_or_default(),
+        };
+        let name = self.get_release_name(version)?;
+
+        info!("[Release] Publishing the {} release on GitLab.", tag);
+        releases::create_release(
+            &self.gitlab,
+            &PROJECT_NAME,
+            &tag,
+            &name,
+            &description,
+            &self.release_assets,
+        )
+    }
+
+    /// Get a pipeline of the project
+    fn get_pipeline(&self, pipeline_id: u64) -> Result<Pipeline, Error> {
+        let pipeline_endpoint = projects::pipelines::Pipeline::builder()
+            .project(PROJECT_NAME.to_string())
+            .pipeline(pipeline_id)
+            .build()
+            .unwrap();
+
+        Ok(dump_query(pipeline_endpoint, |endpoint| {
+            endpoint.query(&self.gitlab)
+        })?)
+    }
+
+    /// Wait for the latest pipeline of the environment's ref
+    pub fn get_last_pipeline(&self) -> Result<Pipeline, Error> {
         let pipeline_ref = self.environment.get_pipeline_ref()?;
         let timeout = 60;
-        let mut counter = 0;
 
-        while last_pipeline_id == 0 && counter < timeout {
+        for _ in 0..timeout {
             sleep(Duration::from_secs(1));
 
             let pipelines_endpoint = projects::pipelines::Pipelines::builder()
@@ -209,83 +840,1718 @@ impl Release<'_> {
                 .build()
                 .unwrap();
 
-            let pipelines: Vec<Pipeline> = pipelines_endpoint.query(&self.gitlab)?;
-            let filtered_pipelines = pipelines
+            let pipelines: Vec<Pipeline> =
+                dump_query(pipelines_endpoint, |endpoint| endpoint.query(&self.gitlab))?;
+            let last_pipeline = pipelines
                 .into_iter()
-                .filter(|pipeline| pipeline.status == "skipped" || pipeline.status == "running");
+                .find(|pipeline| pipeline.status == "skipped" || pipeline.status == "running");
 
-            if let Some(last_pipeline) = filtered_pipelines.into_iter().next() {
-                last_pipeline_id = last_pipeline.id;
+            if let Some(last_pipeline) = last_pipeline {
+                return Ok(last_pipeline);
             }
-
-            counter += 1;
         }
 
-        if last_pipeline_id == 0 {
-            return Err(anyhow!("[Deploy] Pipeline was not found, aborting."));
-        }
+        Err(anyhow!("[Deploy] Pipeline was not found, aborting."))
+    }
+
+    /// Show what is about to be deployed, and ask for a confirmation
+    fn confirm_deploy(&self, pipeline: &Pipeline, jobs: &[&Job]) -> Result<(), Error> {
+        let external_url = deployed::get_external_url(&self.gitlab, &self.environment)
+            .unwrap_or_else(|e| {
+                debug!("[Deploy] Unable to get the environment URL ({}).", e);
+                None
+            });
+        info!(
+            "[Deploy] {}",
+            get_deploy_summary(
+                self.environment,
+                self.version.as_ref(),
+                &pipeline.web_url,
+                jobs,
+                external_url.as_deref(),
+            )
+        );
 
-        Ok(last_pipeline_id)
+        self.confirmation.ask("Do you want to deploy?")
     }
 
-    ///
-    pub fn deploy(&self) -> Result<(), Error> {
-        info!("[Deploy] Fetching latest pipeline.");
-        if let Ok(last_pipeline_id) = self.get_last_pipeline_id() {
+    /// Run the deploy jobs, or the deploy pipeline trigger
+    fn run_deploy(&self) -> Result<(), Error> {
+        if let Some(trigger) = &self.trigger {
+            info!("[Deploy] Triggering a pipeline on \"{}\".", trigger.r#ref);
+            let status = trigger::run_trigger(&self.gitlab, &PROJECT_NAME, trigger)?;
+
+            return match status {
+                StatusState::Success => Ok(()),
+                status => Err(anyhow!(
+                    "[Deploy] The triggered pipeline did not succeed ({:?}).",
+                    status
+                )),
+            };
+        }
+
+        if let Some(job_id) = self.deploy_job_id {
+            let pipeline_ref = self.environment.get_pipeline_ref()?;
+            let result = play_job_by_id(
+                job_id,
+                |job_id| find_job(&self.gitlab, job_id),
+                |job| {
+                    run_deploy_job(
+                        &self.gitlab,
+                        &pipeline_ref,
+                        job,
+                        &self.running_jobs,
+                        self.wait_created,
+                    )
+                },
+            )?;
+
+            return check_deploy_results(&[result]);
+        }
+
+        let pipeline = match self.pipeline_id.get() {
+            Some(pipeline_id) => {
+                info!(
+                    "[Deploy] Fetching pipeline {} of the previous run.",
+                    pipeline_id
+                );
+                self.get_pipeline(*pipeline_id)
+            }
+            None => {
+                info!("[Deploy] Fetching latest pipeline.");
+                self.get_last_pipeline()
+            }
+        };
+        if let Ok(last_pipeline) = pipeline {
+            let _ = self.pipeline_id.set(last_pipeline.id);
+            let _ = self.pipeline_url.set(last_pipeline.web_url.clone());
             let jobs_endpoint = projects::pipelines::PipelineJobs::builder()
                 .project(PROJECT_NAME.to_string())
-                .pipeline(last_pipeline_id)
+                .pipeline(last_pipeline.id)
                 .build()
                 .unwrap();
 
-            let jobs: Vec<Job> = jobs_endpoint.query(&self.gitlab)?;
-
-            let deploy_job_name = self.environment.get_deploy_job_name()?;
+            let jobs: Vec<Job> =
+                dump_query(jobs_endpoint, |endpoint| endpoint.query(&self.gitlab))?;
 
-            let deploy_job = jobs.into_iter().find(|job| {
-                job.name.contains(&deploy_job_name)
-                    && job.status != StatusState::Failed
-                    && job.status != StatusState::Success
-            });
+            let deploy_job_names = self.environment.get_deploy_job_names(self.config)?;
+            let pipeline_ref = self.environment.get_pipeline_ref()?;
 
-            if let Some(job) = deploy_job {
-                // While the job has the "created" state, it means other jobs
-                // are pending before.
-                let mut job_status = job.status;
-                info!("[Deploy] Waiting for previous jobs to be over.");
+            let mut deploy_jobs: Vec<&Job> = vec![];
+            for deploy_job_name in &deploy_job_names {
+                let candidates = get_candidate_jobs(&jobs, deploy_job_name);
 
-                while job_status == StatusState::Created {
-                    sleep(Duration::from_secs(1));
-                    let job: Job = self.get_job(job.id)?;
-                    job_status = job.status;
+                if let Some(job) = select_job(deploy_job_name, candidates, self.interactive)? {
+                    deploy_jobs.push(job);
                 }
+            }
 
-                // Trigger the deploy job
-                let play_job_endpoint = projects::jobs::PlayJob::builder()
-                    .project(PROJECT_NAME.to_string())
-                    .job(job.id)
-                    .build()
-                    .unwrap();
+            if deploy_jobs.is_empty() {
+                warn!("[Deploy] No deploy job was found in the pipeline.");
+                return Ok(());
+            }
+
+            self.confirm_deploy(&last_pipeline, &deploy_jobs)?;
 
-                gitlab::api::ignore(play_job_endpoint).query(&self.gitlab)?;
+            // Run every deploy job in parallel, and wait for all of them
+            let results: Mutex<Vec<(String, StatusState)>> = Mutex::new(vec![]);
 
-                info!("[Deploy] Playing \"{}\" job.", job.name);
+            thread::scope(|scope| {
+                for job in &deploy_jobs {
+                    let gitlab = &self.gitlab;
+                    let results = &results;
+                    let running_jobs = &self.running_jobs;
+                    let pipeline_ref = &pipeline_ref;
+                    let wait_created = self.wait_created;
 
-                let mut job: Job = self.get_job(job.id)?;
+                    scope.spawn(move || {
+                        let status =
+                            run_deploy_job(gitlab, pipeline_ref, job, running_jobs, wait_created)
+                                .unwrap_or_else(|e| {
+                                    error!(
+                                        "[Deploy] \"{}\" job could not be run ({})",
+                                        job.name, e
+                                    );
+                                    StatusState::Failed
+                                });
 
-                while job.status != StatusState::Failed && job.status != StatusState::Success {
-                    sleep(Duration::from_secs(1));
-                    job = self.get_job(job.id)?;
+                        results.lock().unwrap().push((job.name.clone(), status));
+                    });
                 }
+            });
+
+            let results = results.into_inner().unwrap();
+            self.report_deploy_failures(&last_pipeline.web_url, &deploy_jobs, &results);
+            check_deploy_results(&results)?;
+        }
+
+        Ok(())
+    }
+
+    /// Emit an event for each failed deploy job, and send it to the failure
+    /// webhook if any
+    fn report_deploy_failures(
+        &self,
+        pipeline_url: &str,
+        jobs: &[&Job],
+        results: &[(String, StatusState)],
+    ) {
+        let failed_jobs = jobs.iter().filter(|job| {
+            results
+                .iter()
+                .any(|(name, status)| *name == job.name && *status != StatusState::Success)
+        });
+
+        for job in failed_jobs {
+            let event = notify::describe_deploy_failure(
+                &self.gitlab,
+                &PROJECT_NAME,
+                self.environment,
+                self.version.as_ref().map(ToString::to_string),
+                job,
+                pipeline_url,
+            );
+
+            if let Some(url) = &self.deploy_failure_notify {
+                notify::send(url, &event);
+            }
+            self.events.emit(event);
+        }
+    }
+}
+
+impl Releaser for Release<'_> {
+    /// Create the new release
+    fn create(&self) -> Result<(), Error> {
+        if self.tag_only {
+            return self.create_tag_release();
+        }
+
+        match self.environment {
+            Environment::Production => self.create_production_release(),
+            Environment::Staging if self.is_tagging_environment() => self.create_tag_release(),
+            Environment::Staging => Ok(()),
+        }
+    }
 
-                if job.status == StatusState::Failed {
-                    error!("[Deploy] \"{}\" job failed", job.name);
-                } else if job.status == StatusState::Success {
-                    info!("[Deploy] \"{}\" job succeeded", job.name)
+    /// Push the release
+    fn push(&self) -> Result<(), Error> {
+        if self.tag_only {
+            self.push_version_bump()?;
+            self.push_tag()?;
+        } else {
+            match self.environment {
+                Environment::Production => self.push_production()?,
+                Environment::Staging => {
+                    self.push_staging()?;
+
+                    if self.is_tagging_environment() {
+                        self.push_tag()?;
+                    }
                 }
             }
         }
 
+        if self.verify_remote_tag && (self.tag_only || self.is_tagging_environment()) {
+            let tag = self.get_version()?.to_string();
+            info!("[Release] Verifying that the {} tag is on the remote.", tag);
+            git::verify_remote_tag(&mut get_remote(self.repository)?, &tag)?;
+        }
+
+        if self.gitlab_release && (self.tag_only || self.is_tagging_environment()) {
+            self.publish_gitlab_release()?;
+        }
+
         Ok(())
     }
+
+    /// Deploy the release, then check the health of the application
+    fn deploy(&self) -> Result<(), Error> {
+        if self.verify_environment_ref {
+            let deploy_ref = match &self.trigger {
+                Some(trigger) => trigger.r#ref.clone(),
+                None => self.environment.get_pipeline_ref()?,
+            };
+
+            environment_ref::verify(&self.gitlab, &PROJECT_NAME, self.environment, &deploy_ref)?;
+        }
+
+        self.run_deploy()?;
+
+        if let Some(health_url) = &self.health_url {
+            health::check(health_url, self.health_timeout)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Make a release in three steps: start its branch, prepare it (bump its
+/// version file and check it), then finish the release, or abort it when the
+/// preparation fails. A branch which failed to start is not ours to delete.
+fn run_release_branch<S, P, F, A>(start: S, prepare: P, finish: F, abort: A) -> Result<(), Error>
+where
+    S: FnOnce() -> Result<(), Error>,
+    P: FnOnce() -> Result<(), Error>,
+    F: FnOnce() -> Result<(), Error>,
+    A: FnOnce() -> Result<(), Error>,
+{
+    start()?;
+
+    if let Err(e) = prepare() {
+        if let Err(abort_error) = abort() {
+            error!(
+                "[Release] The release branch could not be deleted ({}).",
+                abort_error
+            );
+        }
+
+        return Err(e);
+    }
+
+    finish()
+}
+
+/// Make way for the tag of the new version: an existing tag is deleted to be
+/// recreated when forced to, and is an error otherwise
+fn clear_existing_tag(repository: &Repository, tag: &str, force_tag: bool) -> Result<(), Error> {
+    if repository
+        .find_reference(&format!("refs/tags/{}", tag))
+        .is_err()
+    {
+        return Ok(());
+    }
+
+    if !force_tag {
+        return Err(WrError::TagAlreadyExists {
+            tag: tag.to_string(),
+        }
+        .into());
+    }
+
+    warn!("[Release] The {} tag already exists, recreating it.", tag);
+    repository.tag_delete(tag)?;
+
+    Ok(())
+}
+
+/// Check that a tag follows the tag naming policy
+fn check_tag_policy(tag: &str, tag_pattern: &Regex) -> Result<(), WrError> {
+    if tag_pattern.is_match(tag) {
+        return Ok(());
+    }
+
+    Err(WrError::TagPolicyViolation {
+        tag: tag.to_string(),
+        pattern: tag_pattern.to_string(),
+    })
+}
+
+/// Check that a version is greater than the latest local tag and the latest
+/// published release
+fn check_downgrade(
+    version: &Version,
+    local: Option<&Version>,
+    published: Option<&Version>,
+) -> Result<(), WrError> {
+    let latest = [(local, "local tag"), (published, "GitLab release")]
+        .into_iter()
+        .filter_map(|(latest, origin)| Some((latest?, origin)))
+        .find(|(latest, _)| version <= latest);
+
+    match latest {
+        Some((latest, origin)) => Err(WrError::VersionDowngrade {
+            version: version.to_string(),
+            latest: latest.to_string(),
+            origin: origin.to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Summarize a deploy about to happen
+fn get_deploy_summary(
+    environment: Environment,
+    version: Option<&Version>,
+    pipeline_url: &str,
+    jobs: &[&Job],
+    external_url: Option<&str>,
+) -> String {
+    let job_names: Vec<&str> = jobs.iter().map(|job| job.name.as_str()).collect();
+
+    let mut lines = vec![format!("Deploying to {}:", environment)];
+    if let Some(version) = version {
+        lines.push(format!("  Version: {}", version));
+    }
+    lines.push(format!("  Pipeline: {}", pipeline_url));
+    lines.push(format!("  Jobs: {}", job_names.join(", ")));
+    if let Some(external_url) = external_url {
+        lines.push(format!("  URL: {}", external_url));
+    }
+
+    lines.join("\n")
+}
+
+/// Whether a job is a variant of a deploy job, run with the parallel keyword
+/// (e.g. "deploy_prod 1/2") or a matrix (e.g. "deploy_prod: [eu]")
+fn is_job_variant(job_name: &str, deploy_job_name: &str) -> bool {
+    job_name
+        .strip_prefix(deploy_job_name)
+        .is_some_and(|rest| rest.starts_with(' ') || rest.starts_with(':'))
+}
+
+/// List the jobs of a pipeline which could deploy, given a deploy job name:
+/// the jobs with this exact name, or else its parallel or matrix variants,
+/// so that "deploy_prod" never picks "deploy_prod_dryrun"
+fn get_candidate_jobs<'j>(jobs: &'j [Job], deploy_job_name: &str) -> Vec<&'j Job> {
+    let playable: Vec<&Job> = jobs
+        .iter()
+        .filter(|job| job.status != StatusState::Failed && job.status != StatusState::Success)
+        .collect();
+
+    let exact: Vec<&Job> = playable
+        .iter()
+        .copied()
+        .filter(|job| job.name == deploy_job_name)
+        .collect();
+
+    match exact.is_empty() {
+        false => exact,
+        true => playable
+            .into_iter()
+            .filter(|job| is_job_variant(&job.name, deploy_job_name))
+            .collect(),
+    }
+}
+
+/// Choose the job to play among the candidates, asking the user when several
+/// match in interactive mode, and picking the first one otherwise
+fn select_job<'j>(
+    deploy_job_name: &str,
+    candidates: Vec<&'j Job>,
+    interactive: bool,
+) -> Result<Option<&'j Job>, Error> {
+    if candidates.len() < 2 || !interactive || !io::stdin().is_terminal() {
+        return Ok(candidates.first().copied());
+    }
+
+    let items: Vec<String> = candidates
+        .iter()
+        .map(|job| format!("{} ({}, {:?})", job.name, job.stage, job.status))
+        .collect();
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "Several jobs match \"{}\", which one should be played?",
+            deploy_job_name
+        ))
+        .items(&items)
+        .default(0)
+        .interact_opt()?;
+
+    Ok(selection.map(|index| candidates[index]))
+}
+
+/// Get the merge requests of a range of commits: merged into develop, or
+/// into master for the hotfixes, between the dates of the range
+pub fn get_merged_merge_requests(
+    gitlab: &Gitlab,
+    repository: &Repository,
+    range: &CommitRange,
+) -> Result<Vec<MergeRequest>, Error> {
+    let (since, until) = changelog::get_range_dates(repository, range)?;
+
+    let mut builder = projects::merge_requests::MergeRequests::builder();
+    builder
+        .project(PROJECT_NAME.to_string())
+        .state(MergeRequestState::Merged);
+
+    if let Some(since) = since {
+        builder.updated_after(since);
+    }
+
+    let merge_requests_endpoint = builder.build().unwrap();
+    let merge_requests: Vec<MergeRequest> = dump_query(merge_requests_endpoint, |endpoint| {
+        api::paged(endpoint, Pagination::All).query(gitlab)
+    })?;
+
+    Ok(changelog::filter_merge_requests(
+        merge_requests,
+        &get_lineage_branches(),
+        since,
+        until,
+    ))
+}
+
+/// The branches the released tags are expected to be reachable from
+fn get_lineage_branches() -> [&'static str; 2] {
+    [MASTER_BRANCH.as_str(), DEVELOP_BRANCH.as_str()]
+}
+
+/// Find the latest tag to bump from, ignoring the tags unreachable from the
+/// master and develop branches when told to
+pub fn find_base_tag(
+    repository: &Repository,
+    exclude_prerelease: bool,
+    ignore_unreachable_tags: bool,
+) -> Option<Version> {
+    match ignore_unreachable_tags {
+        true => find_last_reachable_tag(repository, exclude_prerelease, &get_lineage_branches()),
+        false => find_last_tag(repository, exclude_prerelease),
+    }
+}
+
+/// Infer how to increment the version number from conventional commits,
+/// falling back to a patch
+pub fn infer_semver_type(commits: &[CommitInfo]) -> SemverType {
+    match SemverType::infer_from_commits(commits) {
+        Some((semver_type, commit)) => {
+            info!(
+                "[Setup] {} semver type was inferred from commit {:.7} (\"{}\").",
+                semver_type, commit.sha, commit.subject
+            );
+            semver_type
+        }
+        None => {
+            info!("[Setup] No conventional commit was found, falling back to Patch semver type.");
+            SemverType::Patch
+        }
+    }
+}
+
+/// Find the latest version among the tags of a repository
+pub fn find_last_tag(repository: &Repository, exclude_prerelease: bool) -> Option<Version> {
+    let tags = repository.tag_names(None).ok()?;
+
+    get_latest_version(tags.iter().flatten(), exclude_prerelease)
+}
+
+/// Whether a tag points to a commit of one of the branches. Branches missing
+/// from the repository are ignored, and a tag is considered reachable when
+/// none of them exists
+fn is_tag_reachable(repository: &Repository, tag: &str, branches: &[&str]) -> Result<bool, Error> {
+    let tag_commit = repository.revparse_single(tag)?.peel_to_commit()?.id();
+    let heads: Vec<Oid> = branches
+        .iter()
+        .filter_map(|branch| repository.find_branch(branch, BranchType::Local).ok())
+        .filter_map(|branch| branch.get().target())
+        .collect();
+
+    if heads.is_empty() {
+        return Ok(true);
+    }
+
+    for head in heads {
+        if head == tag_commit || repository.graph_descendant_of(head, tag_commit)? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Find the latest tag on the history of a commit, the commit included
+fn find_last_tag_before(
+    repository: &Repository,
+    commit: Oid,
+    exclude_prerelease: bool,
+) -> Option<Version> {
+    let tags = repository.tag_names(None).ok()?;
+    let preceding = tags.iter().flatten().filter(|tag| {
+        repository
+            .revparse_single(tag)
+            .and_then(|object| object.peel_to_commit())
+            .and_then(|tag_commit| {
+                Ok(tag_commit.id() == commit
+                    || repository.graph_descendant_of(commit, tag_commit.id())?)
+            })
+            .unwrap_or(false)
+    });
+
+    get_latest_version(preceding, exclude_prerelease)
+}
+
+/// Fetch the latest tag reachable from one of the branches
+pub fn find_last_reachable_tag(
+    repository: &Repository,
+    exclude_prerelease: bool,
+    branches: &[&str],
+) -> Option<Version> {
+    let tags = repository.tag_names(None).ok()?;
+    let reachable = tags
+        .iter()
+        .flatten()
+        .filter(|tag| is_tag_reachable(repository, tag, branches).unwrap_or(false));
+
+    get_latest_version(reachable, exclude_prerelease)
+}
+
+/// List the versions of the tags of the repository
+fn get_tag_versions(repository: &Repository) -> Result<Vec<Version>, Error> {
+    let tags = repository.tag_names(None)?;
+
+    Ok(tags
+        .iter()
+        .flatten()
+        .filter_map(|tag| Version::parse(tag).ok())
+        .collect())
+}
+
+/// Increment a version number, resetting the lower components unless told
+/// not to (e.g. "1.3.2" after "1.2.2" on a minor bump, which is not standard)
+fn bump_version(version: &Version, semver_type: SemverType, reset_lower: bool) -> Version {
+    let (minor, patch) = match reset_lower {
+        true => (0, 0),
+        false => (version.minor, version.patch),
+    };
+
+    match semver_type {
+        SemverType::Major => Version::new(version.major + 1, minor, patch),
+        SemverType::Minor => Version::new(version.major, version.minor + 1, patch),
+        SemverType::Patch => Version::new(version.major, version.minor, version.patch + 1),
+    }
+}
+
+/// Compute the version following the latest one. On a prerelease channel, the
+/// highest counter of the channel among the existing versions of the same
+/// core version is incremented (e.g. "1.3.0-beta.3" after "1.3.0-beta.1" and
+/// "1.3.0-beta.2"), and a stable release finalizes the latest prerelease
+pub fn get_next_version(
+    last_tag: Option<&Version>,
+    existing: &[Version],
+    semver_type: SemverType,
+    channel: Option<&str>,
+    reset_lower: bool,
+) -> Result<Version, Error> {
+    let core = match last_tag {
+        None => Version::new(1, 0, 0),
+        Some(last_tag) if last_tag.pre.is_empty() => {
+            bump_version(last_tag, semver_type, reset_lower)
+        }
+        Some(last_tag) => Version::new(last_tag.major, last_tag.minor, last_tag.patch),
+    };
+
+    match channel {
+        Some(channel) => {
+            let counter = get_channel_counter(existing.iter().chain(last_tag), &core, channel);
+            with_prerelease(core, channel, counter.map_or(1, |counter| counter + 1))
+        }
+        None => Ok(core),
+    }
+}
+
+/// How the next version is computed from the latest one
+#[derive(Debug, Clone, Copy)]
+pub struct VersionScheme<'a> {
+    pub semver_type: SemverType,
+    pub versioning: Versioning,
+    /// The prerelease channel of the environment, if any
+    pub channel: Option<&'a str>,
+    pub reset_lower: bool,
+}
+
+/// Compute the next tag from the latest one, or from a simulated latest tag
+/// which stands for the tags of the repository
+pub fn compute_next_tag(
+    repository: &Repository,
+    last_tag: Option<&Version>,
+    simulate_from: Option<&Version>,
+    scheme: VersionScheme,
+) -> Result<Version, Error> {
+    let last_tag = match simulate_from {
+        Some(simulated) => {
+            debug!("[Setup] Simulating the next version from {}.", simulated);
+            Some(simulated)
+        }
+        None => last_tag,
+    };
+
+    if scheme.versioning == Versioning::Calver {
+        let today = Local::now().date_naive();
+        return Ok(versioning::get_next_calver(last_tag, today));
+    }
+
+    let existing = match simulate_from {
+        Some(_) => vec![],
+        None => get_tag_versions(repository)?,
+    };
+
+    get_next_version(
+        last_tag,
+        &existing,
+        scheme.semver_type,
+        scheme.channel,
+        scheme.reset_lower,
+    )
+}
+
+/// Get the highest counter of a prerelease channel among the versions of a
+/// core version, ignoring the prereleases of other channels and the
+/// non-numeric counters
+fn get_channel_counter<'a>(
+    versions: impl Iterator<Item = &'a Version>,
+    core: &Version,
+    channel: &str,
+) -> Option<u64> {
+    versions
+        .filter(|version| {
+            (version.major, version.minor, version.patch) == (core.major, core.minor, core.patch)
+        })
+        .filter_map(|version| {
+            version
+                .pre
+                .as_str()
+                .strip_prefix(channel)
+                .and_then(|rest| rest.strip_prefix('.'))
+                .and_then(|counter| counter.parse::<u64>().ok())
+        })
+        .max()
+}
+
+/// Set the prerelease identifier of a version to "{channel}.{counter}"
+fn with_prerelease(mut version: Version, channel: &str, counter: u64) -> Result<Version, Error> {
+    version.pre = Prerelease::new(&format!("{}.{}", channel, counter))
+        .map_err(|e| anyhow!("Invalid prerelease channel \"{}\" ({}).", channel, e))?;
+
+    Ok(version)
+}
+
+/// Get the highest version among some tags, ignoring the prereleases if asked
+fn get_latest_version<'a>(
+    tags: impl Iterator<Item = &'a str>,
+    exclude_prerelease: bool,
+) -> Option<Version> {
+    tags.filter_map(|tag| Version::parse(tag).ok())
+        .filter(|version| !exclude_prerelease || version.pre.is_empty())
+        .max()
+}
+
+/// Get the highest version among the local one and the tags of the GitLab
+/// releases, ignoring the tags which are not versions
+pub fn get_highest_version(
+    local: Option<Version>,
+    release_tags: &[String],
+    exclude_prerelease: bool,
+) -> Option<Version> {
+    let published = get_latest_version(release_tags.iter().map(String::as_str), exclude_prerelease);
+
+    local.into_iter().chain(published).max()
+}
+
+/// Set some environment variables on a command
+fn with_env(expression: Expression, env: &[(String, String)]) -> Expression {
+    env.iter().fold(expression, |expression, (key, value)| {
+        expression.env(key, value)
+    })
+}
+
+/// Cancel jobs, when the run is aborted while they are running
+pub fn cancel_jobs(gitlab: &Gitlab, job_ids: &[u64]) {
+    for job_id in job_ids {
+        let cancel_job_endpoint = projects::jobs::CancelJob::builder()
+            .project(PROJECT_NAME.to_string())
+            .job(*job_id)
+            .build()
+            .unwrap();
+
+        match dump_query(cancel_job_endpoint, |endpoint| {
+            gitlab::api::ignore(endpoint).query(gitlab)
+        }) {
+            Ok(_) => warn!("[Deploy] Job {} has been canceled.", job_id),
+            Err(e) => error!("[Deploy] Job {} could not be canceled ({}).", job_id, e),
+        }
+    }
+}
+
+/// Fetch a job from the Gitlab API, if it still exists
+fn find_job(gitlab: &Gitlab, job_id: u64) -> Result<Option<Job>, Error> {
+    let job_endpoint = projects::jobs::Job::builder()
+        .project(PROJECT_NAME.to_string())
+        .job(job_id)
+        .build()
+        .unwrap();
+
+    match dump_query(job_endpoint, |endpoint| endpoint.query(gitlab)) {
+        Ok(job) => Ok(Some(job)),
+        Err(e) if is_not_found(&e) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Play a job known by its id, without looking for the pipeline and its jobs,
+/// once checked that it can be played
+fn play_job_by_id<F, P>(job_id: u64, find: F, play: P) -> Result<(String, StatusState), Error>
+where
+    F: FnOnce(u64) -> Result<Option<Job>, Error>,
+    P: FnOnce(&Job) -> Result<StatusState, Error>,
+{
+    let job = find(job_id)?
+        .ok_or_else(|| anyhow!("[Deploy] Job {} was not found in the project.", job_id))?;
+
+    if !matches!(job.status, StatusState::Manual | StatusState::Created) {
+        return Err(anyhow!(
+            "[Deploy] \"{}\" job ({}) can't be played, as it is {:?}.",
+            job.name,
+            job.id,
+            job.status
+        ));
+    }
+
+    info!("[Deploy] Deploying with \"{}\" job ({}).", job.name, job.id);
+    let status = play(&job)?;
+
+    Ok((job.name, status))
+}
+
+/// Find the latest job with the given name, in the latest pipeline of a ref
+fn rediscover_job(gitlab: &Gitlab, pipeline_ref: &str, name: &str) -> Result<Option<Job>, Error> {
+    let pipelines_endpoint = projects::pipelines::Pipelines::builder()
+        .project(PROJECT_NAME.to_string())
+        .ref_(pipeline_ref)
+        .order_by(PipelineOrderBy::Id)
+        .sort(SortOrder::Descending)
+        .build()
+        .unwrap();
+    let pipelines: Vec<Pipeline> =
+        dump_query(pipelines_endpoint, |endpoint| endpoint.query(gitlab))?;
+
+    let Some(pipeline) = pipelines.first() else {
+        return Ok(None);
+    };
+
+    let jobs_endpoint = projects::pipelines::PipelineJobs::builder()
+        .project(PROJECT_NAME.to_string())
+        .pipeline(pipeline.id)
+        .build()
+        .unwrap();
+    let jobs: Vec<Job> = dump_query(jobs_endpoint, |endpoint| endpoint.query(gitlab))?;
+
+    Ok(jobs
+        .into_iter()
+        .filter(|job| job.name == name)
+        .max_by_key(|job| job.id))
+}
+
+/// Fetch a job, looking for its replacement when it vanished (because it was
+/// retried, or its pipeline was recreated)
+fn get_job_or_replacement<F, R>(
+    job: &Job,
+    mut fetch: F,
+    mut rediscover: R,
+    delay: Duration,
+) -> Result<Job, Error>
+where
+    F: FnMut(u64) -> Result<Option<Job>, Error>,
+    R: FnMut() -> Result<Option<Job>, Error>,
+{
+    if let Some(job) = fetch(job.id)? {
+        return Ok(job);
+    }
+
+    for attempt in 1..=MAX_JOB_REDISCOVERIES {
+        warn!(
+            "[Deploy] \"{}\" job {} was not found, looking for its replacement ({}/{}).",
+            job.name, job.id, attempt, MAX_JOB_REDISCOVERIES
+        );
+
+        if let Some(replacement) = rediscover()? {
+            info!(
+                "[Deploy] Tracking \"{}\" job {} instead.",
+                replacement.name, replacement.id
+            );
+            return Ok(replacement);
+        }
+
+        sleep(delay);
+    }
+
+    Err(WrError::DeployJobNotFound {
+        name: job.name.clone(),
+    }
+    .into())
+}
+
+/// Play a deploy job, and wait for it to be over
+fn run_deploy_job(
+    gitlab: &Gitlab,
+    pipeline_ref: &str,
+    job: &Job,
+    running_jobs: &RunningJobs,
+    wait_created: bool,
+) -> Result<StatusState, Error> {
+    let refresh = |job: &Job| {
+        get_job_or_replacement(
+            job,
+            |job_id| find_job(gitlab, job_id),
+            || rediscover_job(gitlab, pipeline_ref, &job.name),
+            Duration::from_secs(1),
+        )
+    };
+
+    // While the job has the "created" state, it means other jobs
+    // are pending before. A manual job is played right away.
+    let mut job = job.clone();
+    if must_wait_for_previous_jobs(job.status, wait_created) {
+        info!(
+            "[Deploy] Waiting for previous jobs of \"{}\" to be over.",
+            job.name
+        );
+    }
+
+    while must_wait_for_previous_jobs(job.status, wait_created) {
+        sleep(Duration::from_secs(1));
+        job = refresh(&job)?;
+    }
+
+    // Trigger the deploy job
+    play_job(gitlab, &job)?;
+    running_jobs.add(job.id);
+
+    let job = wait_for_job(
+        job,
+        refresh,
+        |job| play_job(gitlab, job),
+        running_jobs,
+        Duration::from_secs(1),
+    )?;
+
+    if job.status == StatusState::Failed {
+        error!("[Deploy] \"{}\" job failed", job.name);
+    } else if job.status == StatusState::Success {
+        info!("[Deploy] \"{}\" job succeeded", job.name)
+    }
+
+    Ok(job.status)
+}
+
+/// Play a manual or created job
+fn play_job(gitlab: &Gitlab, job: &Job) -> Result<(), Error> {
+    let play_job_endpoint = projects::jobs::PlayJob::builder()
+        .project(PROJECT_NAME.to_string())
+        .job(job.id)
+        .build()
+        .unwrap();
+
+    dump_query(play_job_endpoint, |endpoint| {
+        gitlab::api::ignore(endpoint).query(gitlab)
+    })?;
+    info!("[Deploy] Playing \"{}\" job.", job.name);
+
+    Ok(())
+}
+
+/// Wait for a played job to be over. When the job is replaced (e.g. retried
+/// from the GitLab interface), its replacement is tracked instead, and played
+/// when it is manual.
+fn wait_for_job<F, P>(
+    mut job: Job,
+    mut refresh: F,
+    mut play: P,
+    running_jobs: &RunningJobs,
+    delay: Duration,
+) -> Result<Job, Error>
+where
+    F: FnMut(&Job) -> Result<Job, Error>,
+    P: FnMut(&Job) -> Result<(), Error>,
+{
+    let mut tracked_job_id = job.id;
+
+    loop {
+        job = refresh(&job)?;
+
+        if job.id != tracked_job_id {
+            running_jobs.remove(tracked_job_id);
+            if job.status == StatusState::Manual {
+                play(&job)?;
+            }
+            running_jobs.add(job.id);
+            tracked_job_id = job.id;
+        }
+
+        if is_job_over(job.status) {
+            running_jobs.remove(tracked_job_id);
+            return Ok(job);
+        }
+
+        sleep(delay);
+    }
+}
+
+/// Whether a job is over, and will not run anymore
+fn is_job_over(status: StatusState) -> bool {
+    matches!(
+        status,
+        StatusState::Success | StatusState::Failed | StatusState::Canceled | StatusState::Skipped
+    )
+}
+
+/// Whether a deploy job must wait for the previous jobs of its pipeline
+/// before being played, which only a created job does
+fn must_wait_for_previous_jobs(status: StatusState, wait_created: bool) -> bool {
+    status == StatusState::Created && wait_created
+}
+
+/// Check that every deploy job succeeded
+fn check_deploy_results(results: &[(String, StatusState)]) -> Result<(), Error> {
+    let failed_jobs: Vec<&str> = results
+        .iter()
+        .filter(|(_, status)| *status != StatusState::Success)
+        .map(|(job_name, _)| job_name.as_str())
+        .collect();
+
+    match failed_jobs.is_empty() {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "[Deploy] {} of {} deploy jobs did not succeed: {}.",
+            failed_jobs.len(),
+            results.len(),
+            failed_jobs.join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{anyhow, Error};
+    use chrono::{Datelike, Local};
+    use regex::Regex;
+    use semver::Version;
+
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    use crate::environment::Environment;
+    use crate::error::WrError;
+    use crate::git::{fetch_tags, ref_by_branch};
+    use crate::job::{Job, RunningJobs};
+    use crate::pipeline::StatusState;
+    use crate::release::{
+        check_deploy_results, check_downgrade, check_tag_policy, clear_existing_tag,
+        compute_next_tag, find_last_reachable_tag, find_last_tag, find_last_tag_before,
+        get_candidate_jobs, get_deploy_summary, get_highest_version, get_job_or_replacement,
+        get_latest_version, get_next_version, is_tag_reachable, must_wait_for_previous_jobs,
+        play_job_by_id, run_release_branch, select_job, wait_for_job, VersionScheme,
+        MAX_JOB_REDISCOVERIES,
+    };
+    use crate::semver_type::SemverType;
+    use crate::test_utils::{commit, init_bare_remote, init_repository};
+    use crate::versioning::Versioning;
+
+    #[test]
+    fn includes_prereleases_in_the_latest_version_by_default() {
+        let tags = ["1.9.0", "2.0.0-rc.1", "not-a-version", "1.10.0"];
+
+        assert_eq!(
+            Some(Version::parse("2.0.0-rc.1").unwrap()),
+            get_latest_version(tags.into_iter(), false)
+        );
+    }
+
+    #[test]
+    fn excludes_prereleases_from_the_latest_version() {
+        let tags = ["1.9.0", "2.0.0-rc.1", "1.10.0"];
+
+        assert_eq!(
+            Some(Version::new(1, 10, 0)),
+            get_latest_version(tags.into_iter(), true)
+        );
+        assert_eq!(None, get_latest_version(["2.0.0-rc.1"].into_iter(), true));
+    }
+
+    fn next_version(
+        last_tag: Option<&str>,
+        semver_type: SemverType,
+        channel: Option<&str>,
+    ) -> String {
+        let last_tag = last_tag.map(|tag| Version::parse(tag).unwrap());
+
+        get_next_version(last_tag.as_ref(), &[], semver_type, channel, true)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn simulates_the_next_version_for_each_semver_type() {
+        let (_dir, repository) = init_repository("main");
+        let head = repository.head().unwrap().peel_to_commit().unwrap();
+        // The tags of the repository are ignored by a simulation
+        repository
+            .tag_lightweight("3.0.0", head.as_object(), false)
+            .unwrap();
+
+        let simulate = |simulated: &str, semver_type: SemverType, channel: Option<&str>| {
+            let scheme = VersionScheme {
+                semver_type,
+                versioning: Versioning::Semver,
+                channel,
+                reset_lower: true,
+            };
+
+            compute_next_tag(
+                &repository,
+                None,
+                Some(&Version::parse(simulated).unwrap()),
+                scheme,
+            )
+            .unwrap()
+            .to_string()
+        };
+
+        assert_eq!("2.0.0", simulate("1.4.2", SemverType::Major, None));
+        assert_eq!("1.5.0", simulate("1.4.2", SemverType::Minor, None));
+        assert_eq!("1.4.3", simulate("1.4.2", SemverType::Patch, None));
+        assert_eq!(
+            "1.5.0-beta.1",
+            simulate("1.4.2", SemverType::Minor, Some("beta"))
+        );
+        assert_eq!(
+            "2.0.0-rc.2",
+            simulate("2.0.0-rc.1", SemverType::Major, Some("rc"))
+        );
+        assert_eq!("2.0.0", simulate("2.0.0-rc.1", SemverType::Patch, None));
+    }
+
+    #[test]
+    fn simulates_the_next_calendar_version() {
+        let (_dir, repository) = init_repository("main");
+        let today = Local::now().date_naive();
+        let scheme = VersionScheme {
+            semver_type: SemverType::Major,
+            versioning: Versioning::Calver,
+            channel: None,
+            reset_lower: true,
+        };
+        let simulated = Version::new(today.year() as u64, today.month() as u64, 4);
+
+        assert_eq!(
+            Version::new(today.year() as u64, today.month() as u64, 5),
+            compute_next_tag(&repository, None, Some(&simulated), scheme).unwrap()
+        );
+    }
+
+    fn next_prerelease(last_tag: &str, existing: &[&str], channel: &str) -> String {
+        let last_tag = Version::parse(last_tag).unwrap();
+        let existing: Vec<Version> = existing
+            .iter()
+            .map(|tag| Version::parse(tag).unwrap())
+            .collect();
+
+        get_next_version(
+            Some(&last_tag),
+            &existing,
+            SemverType::Minor,
+            Some(channel),
+            true,
+        )
+        .unwrap()
+        .to_string()
+    }
+
+    #[test]
+    fn increments_the_highest_prerelease_of_the_core_version() {
+        let existing = [
+            "1.2.0",
+            "1.3.0-rc.1",
+            "1.3.0-rc.2",
+            "1.3.0-rc.5",
+            "1.3.0-rc.final",
+            "1.3.0-beta.7",
+            "1.4.0-beta.9",
+        ];
+
+        assert_eq!("1.3.0-rc.6", next_prerelease("1.3.0-rc.5", &existing, "rc"));
+        assert_eq!(
+            "1.3.0-beta.8",
+            next_prerelease("1.3.0-rc.5", &existing, "beta")
+        );
+        assert_eq!(
+            "1.3.0-alpha.1",
+            next_prerelease("1.3.0-rc.5", &existing, "alpha")
+        );
+        // The prereleases are excluded from the latest tag
+        assert_eq!("1.3.0-rc.6", next_prerelease("1.2.0", &existing, "rc"));
+    }
+
+    #[test]
+    fn increments_the_channel_prerelease() {
+        let beta = Some("beta");
+
+        assert_eq!("1.0.0-beta.1", next_version(None, SemverType::Patch, beta));
+        assert_eq!(
+            "1.3.0-beta.1",
+            next_version(Some("1.2.0"), SemverType::Minor, beta)
+        );
+        assert_eq!(
+            "1.3.0-beta.3",
+            next_version(Some("1.3.0-beta.2"), SemverType::Minor, beta)
+        );
+        assert_eq!(
+            "1.3.0-canary.1",
+            next_version(Some("1.3.0-beta.2"), SemverType::Minor, Some("canary"))
+        );
+        assert!(get_next_version(None, &[], SemverType::Patch, Some("not valid"), true).is_err());
+    }
+
+    #[test]
+    fn resets_the_lower_components_unless_told_not_to() {
+        let last_tag = Version::new(1, 2, 3);
+        let bump = |semver_type: SemverType, reset_lower: bool| {
+            get_next_version(Some(&last_tag), &[], semver_type, None, reset_lower)
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!("2.0.0", bump(SemverType::Major, true));
+        assert_eq!("1.3.0", bump(SemverType::Minor, true));
+        assert_eq!("1.2.4", bump(SemverType::Patch, true));
+
+        assert_eq!("2.2.3", bump(SemverType::Major, false));
+        assert_eq!("1.3.3", bump(SemverType::Minor, false));
+        assert_eq!("1.2.4", bump(SemverType::Patch, false));
+    }
+
+    #[test]
+    fn finalizes_a_prerelease_to_stable() {
+        assert_eq!(
+            "1.3.0",
+            next_version(Some("1.3.0-beta.2"), SemverType::Minor, None)
+        );
+        assert_eq!(
+            "1.2.1",
+            next_version(Some("1.2.0"), SemverType::Patch, None)
+        );
+        assert_eq!(
+            "2.0.0",
+            next_version(Some("1.2.3"), SemverType::Major, None)
+        );
+        assert_eq!("1.0.0", next_version(None, SemverType::Patch, None));
+    }
+
+    #[test]
+    fn accounts_for_the_remote_tags_once_fetched() {
+        let (_dir, repository) = init_repository("main");
+        let (_remote_dir, remote_repository) = init_bare_remote(&repository);
+        let head = commit(&repository, "feat: add a feature");
+        repository
+            .tag_lightweight("2.0.0", &repository.find_object(head, None).unwrap(), false)
+            .unwrap();
+
+        // A teammate released 2.1.0 on the remote
+        let mut remote = repository.find_remote("origin").unwrap();
+        remote.push(&[ref_by_branch("main")], None).unwrap();
+        let pushed = remote_repository.find_object(head, None).unwrap();
+        remote_repository
+            .tag_lightweight("2.1.0", &pushed, false)
+            .unwrap();
+
+        assert_eq!(
+            Some(Version::new(2, 0, 0)),
+            find_last_tag(&repository, false)
+        );
+
+        fetch_tags(&mut remote).unwrap();
+
+        assert_eq!(
+            Some(Version::new(2, 1, 0)),
+            find_last_tag(&repository, false)
+        );
+    }
+
+    #[test]
+    fn finds_the_latest_tag_preceding_a_past_commit() {
+        let (_dir, repository) = init_repository("master");
+        let tag = |name: &str, oid: git2::Oid| {
+            let object = repository.find_object(oid, None).unwrap();
+            repository.tag_lightweight(name, &object, false).unwrap();
+        };
+
+        let released = commit(&repository, "feat: add a feature");
+        tag("1.0.0", released);
+        let past = commit(&repository, "fix: fix a bug");
+        let latest = commit(&repository, "feat: add another feature");
+        tag("1.1.0", latest);
+
+        assert_eq!(
+            Some(Version::new(1, 0, 0)),
+            find_last_tag_before(&repository, past, false)
+        );
+        assert_eq!(
+            Some(Version::new(1, 0, 0)),
+            find_last_tag_before(&repository, released, false)
+        );
+        assert_eq!(
+            Some(Version::new(1, 1, 0)),
+            find_last_tag_before(&repository, latest, false)
+        );
+    }
+
+    #[test]
+    fn ignores_the_tags_unreachable_from_the_branches() {
+        let (_dir, repository) = init_repository("main");
+        let tag = |name: &str, oid: git2::Oid| {
+            let object = repository.find_object(oid, None).unwrap();
+            repository.tag_lightweight(name, &object, false).unwrap();
+        };
+
+        let released = commit(&repository, "feat: add a feature");
+        tag("1.0.0", released);
+        let developed = commit(&repository, "feat: add another feature");
+        tag("1.1.0", developed);
+        repository
+            .branch(
+                "develop",
+                &repository.find_commit(developed).unwrap(),
+                false,
+            )
+            .unwrap();
+
+        // A tag left on an abandoned commit, which no branch points to anymore
+        let abandoned = commit(&repository, "feat: try something");
+        tag("2.0.0", abandoned);
+        repository
+            .reference("refs/heads/main", released, true, "Abandon")
+            .unwrap();
+
+        let branches = ["main", "develop"];
+        assert!(is_tag_reachable(&repository, "1.0.0", &branches).unwrap());
+        assert!(is_tag_reachable(&repository, "1.1.0", &branches).unwrap());
+        assert!(!is_tag_reachable(&repository, "2.0.0", &branches).unwrap());
+        assert!(!is_tag_reachable(&repository, "1.1.0", &["main"]).unwrap());
+        assert!(is_tag_reachable(&repository, "2.0.0", &["unknown"]).unwrap());
+
+        assert_eq!(
+            Some(Version::new(2, 0, 0)),
+            find_last_tag(&repository, false)
+        );
+        assert_eq!(
+            Some(Version::new(1, 1, 0)),
+            find_last_reachable_tag(&repository, false, &branches)
+        );
+        assert_eq!(
+            Some(Version::new(1, 0, 0)),
+            find_last_reachable_tag(&repository, false, &["main"])
+        );
+    }
+
+    fn job(id: u64, status: StatusState) -> Job {
+        Job {
+            id,
+            status,
+            name: "deploy_prod".to_string(),
+            stage: "deploy".to_string(),
+        }
+    }
+
+    #[test]
+    fn takes_the_highest_version_across_the_local_tags_and_gitlab_releases() {
+        let release_tags: Vec<String> = ["2.1.0", "not-a-version", "v3.0.0", "2.2.0-rc.1"]
+            .iter()
+            .map(|tag| tag.to_string())
+            .collect();
+
+        assert_eq!(
+            Some(Version::parse("2.2.0-rc.1").unwrap()),
+            get_highest_version(Some(Version::new(2, 0, 0)), &release_tags, false)
+        );
+        assert_eq!(
+            Some(Version::new(2, 1, 0)),
+            get_highest_version(Some(Version::new(2, 0, 0)), &release_tags, true)
+        );
+        assert_eq!(
+            Some(Version::new(3, 1, 0)),
+            get_highest_version(Some(Version::new(3, 1, 0)), &release_tags, false)
+        );
+        assert_eq!(None, get_highest_version(None, &[], false));
+    }
+
+    #[test]
+    fn checks_tags_against_the_tag_pattern() {
+        let pattern = Regex::new(r"^\d+\.\d+\.\d+$").unwrap();
+
+        assert!(check_tag_policy("1.2.0", &pattern).is_ok());
+        assert!(matches!(
+            check_tag_policy("1.3.0-beta.1", &pattern),
+            Err(WrError::TagPolicyViolation { tag, .. }) if tag == "1.3.0-beta.1"
+        ));
+    }
+
+    #[test]
+    fn detects_a_downgrade_from_the_local_or_published_versions() {
+        let version = |version: &str| Version::parse(version).unwrap();
+
+        assert!(check_downgrade(&version("1.3.0"), Some(&version("1.2.0")), None).is_ok());
+        assert!(check_downgrade(
+            &version("1.3.0"),
+            Some(&version("1.2.0")),
+            Some(&version("1.2.5"))
+        )
+        .is_ok());
+        assert!(check_downgrade(&version("1.0.0"), None, None).is_ok());
+
+        // The local tags are behind the GitLab releases
+        assert!(matches!(
+            check_downgrade(&version("1.3.0"), Some(&version("1.2.0")), Some(&version("1.4.0"))),
+            Err(WrError::VersionDowngrade { latest, origin, .. })
+                if latest == "1.4.0" && origin == "GitLab release"
+        ));
+        assert!(matches!(
+            check_downgrade(&version("1.3.0"), Some(&version("1.3.0")), None),
+            Err(WrError::VersionDowngrade { latest, origin, .. })
+                if latest == "1.3.0" && origin == "local tag"
+        ));
+        assert!(matches!(
+            check_downgrade(&version("1.3.0-beta.1"), None, Some(&version("1.3.0"))),
+            Err(WrError::VersionDowngrade { .. })
+        ));
+    }
+
+    #[test]
+    fn recreates_an_existing_tag_only_when_forced() {
+        let (_dir, repository) = init_repository("main");
+        let head = repository.head().unwrap().peel_to_commit().unwrap();
+        repository
+            .tag_lightweight("1.2.0", head.as_object(), false)
+            .unwrap();
+
+        assert!(clear_existing_tag(&repository, "1.3.0", false).is_ok());
+
+        let error = clear_existing_tag(&repository, "1.2.0", false).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<WrError>(),
+            Some(WrError::TagAlreadyExists { tag }) if tag == "1.2.0"
+        ));
+        assert!(repository.find_reference("refs/tags/1.2.0").is_ok());
+
+        clear_existing_tag(&repository, "1.2.0", true).unwrap();
+        assert!(repository.find_reference("refs/tags/1.2.0").is_err());
+    }
+
+    /// A step of a release branch, recording that it ran
+    fn step<'a>(
+        steps: &'a RefCell<Vec<&'static str>>,
+        name: &'static str,
+    ) -> impl FnOnce() -> Result<(), Error> + 'a {
+        move || {
+            steps.borrow_mut().push(name);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn finishes_a_prepared_release_branch() {
+        let steps = RefCell::new(vec![]);
+
+        run_release_branch(
+            step(&steps, "start"),
+            step(&steps, "prepare"),
+            step(&steps, "finish"),
+            step(&steps, "abort"),
+        )
+        .unwrap();
+
+        assert_eq!(vec!["start", "prepare", "finish"], steps.into_inner());
+    }
+
+    #[test]
+    fn aborts_a_release_branch_failing_its_preparation() {
+        let steps = RefCell::new(vec![]);
+        let failing_preparation = || {
+            steps.borrow_mut().push("prepare");
+            Err(anyhow!("tests failed"))
+        };
+
+        let result = run_release_branch(
+            step(&steps, "start"),
+            failing_preparation,
+            step(&steps, "finish"),
+            step(&steps, "abort"),
+        );
+
+        assert_eq!("tests failed", result.unwrap_err().to_string());
+        assert_eq!(vec!["start", "prepare", "abort"], steps.into_inner());
+    }
+
+    #[test]
+    fn stops_when_the_release_branch_cannot_start() {
+        let steps = RefCell::new(vec![]);
+
+        let result = run_release_branch(
+            || Err(anyhow!("branch exists")),
+            step(&steps, "prepare"),
+            step(&steps, "finish"),
+            step(&steps, "abort"),
+        );
+
+        assert!(result.is_err());
+        assert!(steps.into_inner().is_empty());
+    }
+
+    #[test]
+    fn summarizes_a_deploy() {
+        let eu = Job {
+            name: "deploy_eu".to_string(),
+            ..job(1, StatusState::Manual)
+        };
+        let us = Job {
+            name: "deploy_us".to_string(),
+            ..job(2, StatusState::Manual)
+        };
+
+        assert_eq!(
+            "Deploying to Production:\n  Version: 1.2.0\n  Pipeline: https://gitlab.com/aeyoll/wr/-/pipelines/42\n  Jobs: deploy_eu, deploy_us\n  URL: https://wr.example.com",
+            get_deploy_summary(
+                Environment::Production,
+                Some(&Version::new(1, 2, 0)),
+                "https://gitlab.com/aeyoll/wr/-/pipelines/42",
+                &[&eu, &us],
+                Some("https://wr.example.com"),
+            )
+        );
+        assert_eq!(
+            "Deploying to Staging:\n  Pipeline: https://gitlab.com/aeyoll/wr/-/pipelines/43\n  Jobs: deploy_eu",
+            get_deploy_summary(
+                Environment::Staging,
+                None,
+                "https://gitlab.com/aeyoll/wr/-/pipelines/43",
+                &[&eu],
+                None,
+            )
+        );
+    }
+
+    fn named_job(id: u64, name: &str) -> Job {
+        Job {
+            name: name.to_string(),
+            ..job(id, StatusState::Manual)
+        }
+    }
+
+    fn candidate_ids(jobs: &[Job], deploy_job_name: &str) -> Vec<u64> {
+        get_candidate_jobs(jobs, deploy_job_name)
+            .iter()
+            .map(|job| job.id)
+            .collect()
+    }
+
+    #[test]
+    fn collects_the_candidate_deploy_jobs() {
+        let jobs = vec![
+            job(1, StatusState::Manual),
+            named_job(2, "build"),
+            job(3, StatusState::Success),
+            Job {
+                name: "deploy_prod_eu".to_string(),
+                ..job(4, StatusState::Created)
+            },
+        ];
+
+        assert_eq!(vec![1], candidate_ids(&jobs, "deploy_prod"));
+
+        let selected = select_job(
+            "deploy_prod",
+            get_candidate_jobs(&jobs, "deploy_prod"),
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(1, selected.id);
+        assert!(select_job("deploy", vec![], true).unwrap().is_none());
+    }
+
+    #[test]
+    fn matches_the_deploy_job_of_the_environment_only() {
+        let jobs = vec![
+            named_job(1, "deploy_prod"),
+            named_job(2, "deploy_prod_dryrun"),
+            named_job(3, "deploy_staging"),
+        ];
+
+        assert_eq!(vec![1], candidate_ids(&jobs, "deploy_prod"));
+        assert_eq!(vec![2], candidate_ids(&jobs, "deploy_prod_dryrun"));
+        assert_eq!(vec![3], candidate_ids(&jobs, "deploy_staging"));
+        assert!(candidate_ids(&jobs, "deploy").is_empty());
+    }
+
+    #[test]
+    fn matches_the_parallel_and_matrix_variants_of_a_deploy_job() {
+        let jobs = vec![
+            named_job(1, "deploy_prod 1/2"),
+            named_job(2, "deploy_prod 2/2"),
+            named_job(3, "deploy_prod: [eu]"),
+            named_job(4, "deploy_prod_dryrun"),
+        ];
+
+        assert_eq!(vec![1, 2, 3], candidate_ids(&jobs, "deploy_prod"));
+    }
+
+    #[test]
+    fn waits_for_previous_jobs_only_when_created() {
+        assert!(must_wait_for_previous_jobs(StatusState::Created, true));
+        assert!(!must_wait_for_previous_jobs(StatusState::Created, false));
+        assert!(!must_wait_for_previous_jobs(StatusState::Manual, true));
+        assert!(!must_wait_for_previous_jobs(StatusState::Manual, false));
+    }
+
+    #[test]
+    fn plays_a_job_by_id_directly() {
+        let mut played = vec![];
+
+        let result = play_job_by_id(
+            42,
+            |id| Ok(Some(job(id, StatusState::Manual))),
+            |job| {
+                played.push(job.id);
+                Ok(StatusState::Success)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(("deploy_prod".to_string(), StatusState::Success), result);
+        assert_eq!(vec![42], played);
+    }
+
+    #[test]
+    fn refuses_to_play_a_missing_or_finished_job_by_id() {
+        let play = |_: &Job| -> Result<StatusState, anyhow::Error> {
+            panic!("The job must not be played")
+        };
+
+        assert!(play_job_by_id(42, |_| Ok(None), play).is_err());
+        assert!(play_job_by_id(42, |id| Ok(Some(job(id, StatusState::Success))), play).is_err());
+        assert!(play_job_by_id(42, |id| Ok(Some(job(id, StatusState::Running))), play).is_err());
+    }
+
+    #[test]
+    fn keeps_tracking_a_job_while_it_exists() {
+        let mut rediscoveries = 0;
+
+        let found = get_job_or_replacement(
+            &job(1, StatusState::Manual),
+            |id| Ok(Some(job(id, StatusState::Running))),
+            || {
+                rediscoveries += 1;
+                Ok(None)
+            },
+            Duration::ZERO,
+        )
+        .unwrap();
+
+        assert_eq!(1, found.id);
+        assert_eq!(StatusState::Running, found.status);
+        assert_eq!(0, rediscoveries);
+    }
+
+    #[test]
+    fn tracks_the_replacement_of_a_vanished_job() {
+        let mut attempts = vec![None, Some(job(2, StatusState::Pending))].into_iter();
+
+        let found = get_job_or_replacement(
+            &job(1, StatusState::Running),
+            |_| Ok(None),
+            || Ok(attempts.next().unwrap()),
+            Duration::ZERO,
+        )
+        .unwrap();
+
+        assert_eq!(2, found.id);
+    }
+
+    #[test]
+    fn plays_and_tracks_the_replacement_of_a_played_job() {
+        let running_jobs = RunningJobs::default();
+        running_jobs.add(1);
+        let mut refreshes = vec![
+            job(1, StatusState::Running),
+            job(2, StatusState::Manual),
+            job(2, StatusState::Running),
+            job(2, StatusState::Success),
+        ]
+        .into_iter();
+        let played = RefCell::new(vec![]);
+
+        let job = wait_for_job(
+            job(1, StatusState::Pending),
+            |_| Ok(refreshes.next().unwrap()),
+            |job| {
+                played.borrow_mut().push(job.id);
+                assert!(!running_jobs.ids().contains(&1));
+                Ok(())
+            },
+            &running_jobs,
+            Duration::ZERO,
+        )
+        .unwrap();
+
+        assert_eq!((2, StatusState::Success), (job.id, job.status));
+        assert_eq!(vec![2], played.into_inner());
+        assert!(running_jobs.ids().is_empty());
+    }
+
+    #[test]
+    fn stops_waiting_for_a_canceled_job() {
+        let running_jobs = RunningJobs::default();
+
+        let job = wait_for_job(
+            job(1, StatusState::Running),
+            |job| {
+                Ok(Job {
+                    status: StatusState::Canceled,
+                    ..job.clone()
+                })
+            },
+            |_| panic!("The job must not be played"),
+            &running_jobs,
+            Duration::ZERO,
+        )
+        .unwrap();
+
+        assert_eq!(StatusState::Canceled, job.status);
+    }
+
+    #[test]
+    fn fails_when_no_replacement_is_found() {
+        let mut rediscoveries = 0;
+
+        let error = get_job_or_replacement(
+            &job(1, StatusState::Running),
+            |_| Ok(None),
+            || {
+                rediscoveries += 1;
+                Ok(None)
+            },
+            Duration::ZERO,
+        )
+        .unwrap_err();
+
+        assert_eq!(MAX_JOB_REDISCOVERIES, rediscoveries);
+        assert!(matches!(
+            error.downcast_ref::<WrError>(),
+            Some(WrError::DeployJobNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn passes_when_every_deploy_job_succeeded() {
+        let results = vec![
+            ("deploy_eu".to_string(), StatusState::Success),
+            ("deploy_us".to_string(), StatusState::Success),
+        ];
+
+        assert!(check_deploy_results(&results).is_ok());
+    }
+
+    #[test]
+    fn fails_when_any_deploy_job_failed() {
+        let results = vec![
+            ("deploy_eu".to_string(), StatusState::Success),
+            ("deploy_us".to_string(), StatusState::Failed),
+        ];
+
+        let error = check_deploy_results(&results).unwrap_err();
+
+        assert_eq!(
+            "[Deploy] 1 of 2 deploy jobs did not succeed: deploy_us.",
+            error.to_string()
+        );
+    }
 }
@@ -0,0 +1,29 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum, Default)]
+pub enum ReleaseModel {
+    /// Release only when develop is ahead of its upstream
+    #[default]
+    GitFlow,
+    /// Release whatever is on develop, as long as it is not behind its upstream
+    TagDevelop,
+}
+
+impl FromStr for ReleaseModel {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "git-flow" => Ok(ReleaseModel::GitFlow),
+            "tag-develop" => Ok(ReleaseModel::TagDevelop),
+            _ => Err("Unknown release model"),
+        }
+    }
+}
+
+impl fmt::Display for ReleaseModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
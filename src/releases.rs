@@ -0,0 +1,238 @@
+use std::borrow::Cow;
+
+use anyhow::Error;
+use gitlab::{
+    api::{self, BodyError, Endpoint, FormParams, Pageable, Pagination, Query},
+    Gitlab,
+};
+use http::Method;
+use serde::Deserialize;
+
+use crate::api::dump_query;
+
+/// The endpoint listing the releases published on a GitLab project
+pub struct ProjectReleases<'a> {
+    pub project: &'a str,
+}
+
+impl Endpoint for ProjectReleases<'_> {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/releases", self.project.replace('/', "%2F")).into()
+    }
+}
+
+impl Pageable for ProjectReleases<'_> {}
+
+/// A link to a build artifact, attached to a release
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub url: String,
+}
+
+/// Parse a release asset link, given as "NAME=URL"
+pub fn parse_release_asset(asset: &str) -> Result<ReleaseAsset, String> {
+    match asset.split_once('=') {
+        Some((name, url))
+            if !name.is_empty() && (url.starts_with("https://") || url.starts_with("http://")) =>
+        {
+            Ok(ReleaseAsset {
+                name: name.to_string(),
+                url: url.to_string(),
+            })
+        }
+        _ => Err(format!("\"{}\" is not a NAME=URL asset link", asset)),
+    }
+}
+
+/// The endpoint publishing a release of a tag on a GitLab project
+pub struct CreateRelease<'a> {
+    pub project: &'a str,
+    pub tag_name: &'a str,
+    /// The title of the release.
+    pub name: &'a str,
+    pub description: &'a str,
+    pub assets: &'a [ReleaseAsset],
+}
+
+impl CreateRelease<'_> {
+    /// The form parameters of the request
+    fn get_params(&self) -> Vec<(String, String)> {
+        let mut params = vec![
+            ("tag_name".to_string(), self.tag_name.to_string()),
+            ("name".to_string(), self.name.to_string()),
+            ("description".to_string(), self.description.to_string()),
+        ];
+
+        for asset in self.assets {
+            params.push(("assets[links][][name]".to_string(), asset.name.clone()));
+            params.push(("assets[links][][url]".to_string(), asset.url.clone()));
+        }
+
+        params
+    }
+}
+
+impl Endpoint for CreateRelease<'_> {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/releases", self.project.replace('/', "%2F")).into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+
+        for (key, value) in self.get_params() {
+            params.push(key, value);
+        }
+
+        params.into_body()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GitlabRelease {
+    /// The tag the release was published for.
+    tag_name: String,
+}
+
+/// List the tags of the releases published on GitLab
+pub fn get_release_tags(gitlab: &Gitlab, project: &str) -> Result<Vec<String>, Error> {
+    let endpoint = ProjectReleases { project };
+    let releases: Vec<GitlabRelease> = dump_query(endpoint, |endpoint| {
+        api::paged(endpoint, Pagination::All).query(gitlab)
+    })?;
+
+    Ok(releases
+        .into_iter()
+        .map(|release| release.tag_name)
+        .collect())
+}
+
+/// Publish a release of a tag on GitLab
+pub fn create_release(
+    gitlab: &Gitlab,
+    project: &str,
+    tag_name: &str,
+    name: &str,
+    description: &str,
+    assets: &[ReleaseAsset],
+) -> Result<(), Error> {
+    let endpoint = CreateRelease {
+        project,
+        tag_name,
+        name,
+        description,
+        assets,
+    };
+    dump_query(endpoint, |endpoint| api::ignore(endpoint).query(gitlab))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use gitlab::api::Endpoint;
+    use http::Method;
+
+    use crate::releases::{parse_release_asset, CreateRelease, ProjectReleases, ReleaseAsset};
+
+    #[test]
+    fn lists_the_releases_of_the_project() {
+        let endpoint = ProjectReleases {
+            project: "aeyoll/wr",
+        };
+
+        assert_eq!(Method::GET, endpoint.method());
+        assert_eq!("projects/aeyoll%2Fwr/releases", endpoint.endpoint());
+    }
+
+    #[test]
+    fn publishes_a_release_of_a_tag() {
+        let endpoint = CreateRelease {
+            project: "aeyoll/wr",
+            tag_name: "1.2.0",
+            name: "1.2.0",
+            description: "Notes",
+            assets: &[],
+        };
+
+        assert_eq!(Method::POST, endpoint.method());
+        assert_eq!("projects/aeyoll%2Fwr/releases", endpoint.endpoint());
+    }
+
+    #[test]
+    fn names_a_release_apart_from_its_tag() {
+        let endpoint = CreateRelease {
+            project: "aeyoll/wr",
+            tag_name: "1.2.0",
+            name: "Release 1.2.0 (2024-05-07)",
+            description: "Notes",
+            assets: &[],
+        };
+
+        assert_eq!(
+            vec![
+                ("tag_name".to_string(), "1.2.0".to_string()),
+                ("name".to_string(), "Release 1.2.0 (2024-05-07)".to_string()),
+                ("description".to_string(), "Notes".to_string()),
+            ],
+            endpoint.get_params()
+        );
+    }
+
+    #[test]
+    fn parses_a_release_asset() {
+        assert_eq!(
+            Ok(ReleaseAsset {
+                name: "wr-linux".to_string(),
+                url: "https://example.com/wr?arch=x86_64".to_string(),
+            }),
+            parse_release_asset("wr-linux=https://example.com/wr?arch=x86_64")
+        );
+        assert!(parse_release_asset("wr-linux").is_err());
+        assert!(parse_release_asset("=https://example.com/wr").is_err());
+        assert!(parse_release_asset("wr-linux=example.com/wr").is_err());
+    }
+
+    #[test]
+    fn links_the_assets_of_a_release() {
+        let assets = vec![
+            parse_release_asset("wr-linux=https://example.com/wr-linux").unwrap(),
+            parse_release_asset("wr-macos=https://example.com/wr-macos").unwrap(),
+        ];
+        let endpoint = CreateRelease {
+            project: "aeyoll/wr",
+            tag_name: "1.2.0",
+            name: "1.2.0",
+            description: "Notes",
+            assets: &assets,
+        };
+
+        assert_eq!(
+            vec![
+                ("tag_name".to_string(), "1.2.0".to_string()),
+                ("name".to_string(), "1.2.0".to_string()),
+                ("description".to_string(), "Notes".to_string()),
+                ("assets[links][][name]".to_string(), "wr-linux".to_string()),
+                (
+                    "assets[links][][url]".to_string(),
+                    "https://example.com/wr-linux".to_string()
+                ),
+                ("assets[links][][name]".to_string(), "wr-macos".to_string()),
+                (
+                    "assets[links][][url]".to_string(),
+                    "https://example.com/wr-macos".to_string()
+                ),
+            ],
+            endpoint.get_params()
+        );
+    }
+}
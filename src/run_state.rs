@@ -0,0 +1,235 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use git2::Repository;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::phases::{PhaseStatus, Phases, RunReport};
+
+/// The path of the run state, relative to the working directory
+const STATE_PATH: &str = ".wr/state.json";
+
+/// The phases completed by a previous run, to resume it with the continue
+/// flag
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunState {
+    /// The deploy environment of the run.
+    pub environment: String,
+    /// The version of the release, if it creates a tag.
+    pub version: Option<String>,
+    /// The pipeline the deploy jobs were run from, if any.
+    pub pipeline_id: Option<u64>,
+    pub created: bool,
+    pub pushed: bool,
+    pub deployed: bool,
+}
+
+impl RunState {
+    /// Record the phases completed by a run, on top of the ones completed
+    /// by the run it resumed
+    pub fn record(&mut self, report: &RunReport) {
+        for (phase, status) in &report.phases {
+            let completed = *status == PhaseStatus::Succeeded;
+
+            match *phase {
+                "create" => self.created |= completed,
+                "push" => self.pushed |= completed,
+                "deploy" => self.deployed |= completed,
+                _ => {}
+            }
+        }
+    }
+
+    /// Get the version released by the run, to release the same one when
+    /// resumed
+    pub fn get_version(&self) -> Result<Option<Version>, Error> {
+        self.version
+            .as_deref()
+            .map(Version::parse)
+            .transpose()
+            .map_err(|e| anyhow!("The version of the previous run is invalid ({}).", e))
+    }
+
+    /// Select the phases left to run, among the selected ones
+    pub fn resume(&self, phases: Phases) -> Phases {
+        Phases {
+            create: phases.create && !self.created,
+            push: phases.push && !self.pushed,
+            deploy: phases.deploy && !self.deployed,
+        }
+    }
+}
+
+/// Get the path of the run state of a repository, unless it is bare
+pub fn get_state_path(repository: &Repository) -> Option<PathBuf> {
+    repository.workdir().map(|workdir| workdir.join(STATE_PATH))
+}
+
+/// Read the state of the previous run, if any
+pub fn load(path: &Path) -> Result<Option<RunState>, Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path)?;
+    let state = serde_json::from_str(&content)
+        .map_err(|e| anyhow!("\"{}\" is not a valid run state ({}).", path.display(), e))?;
+
+    Ok(Some(state))
+}
+
+/// Write the state of the run
+pub fn save(path: &Path, state: &RunState) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+
+        // Keep the state out of the repository status, which must be clean
+        // to continue the run
+        fs::write(parent.join(".gitignore"), "*\n")?;
+    }
+
+    fs::write(path, serde_json::to_string_pretty(state)?)?;
+
+    Ok(())
+}
+
+/// Remove the state of the run, once every phase succeeded
+pub fn clear(path: &Path) -> Result<(), Error> {
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use semver::Version;
+
+    use crate::phases::{PhaseStatus, Phases, RunReport};
+    use crate::run_state::{clear, load, save, RunState};
+
+    fn all_phases() -> Phases {
+        Phases {
+            create: true,
+            push: true,
+            deploy: true,
+        }
+    }
+
+    #[test]
+    fn resumes_from_the_first_incomplete_phase() {
+        let state = RunState {
+            environment: "Production".to_string(),
+            version: Some("1.2.0".to_string()),
+            pipeline_id: None,
+            created: true,
+            pushed: false,
+            deployed: false,
+        };
+
+        assert_eq!(
+            Phases {
+                create: false,
+                push: true,
+                deploy: true,
+            },
+            state.resume(all_phases())
+        );
+        assert_eq!(
+            Phases {
+                create: false,
+                push: true,
+                deploy: false,
+            },
+            state.resume(Phases {
+                deploy: false,
+                ..all_phases()
+            })
+        );
+        assert_eq!(all_phases(), RunState::default().resume(all_phases()));
+    }
+
+    #[test]
+    fn records_the_completed_phases_across_runs() {
+        let mut state = RunState::default();
+
+        state.record(&RunReport {
+            phases: vec![
+                ("create", PhaseStatus::Succeeded),
+                ("push", PhaseStatus::Failed),
+                ("deploy", PhaseStatus::Skipped),
+            ],
+            error: Some(anyhow!("The remote rejected the push.")),
+        });
+        assert!(state.created && !state.pushed && !state.deployed);
+
+        state.record(&RunReport {
+            phases: vec![
+                ("push", PhaseStatus::Succeeded),
+                ("deploy", PhaseStatus::Failed),
+            ],
+            error: Some(anyhow!("The deploy job failed.")),
+        });
+        assert!(state.created && state.pushed && !state.deployed);
+    }
+
+    #[test]
+    fn saves_loads_and_clears_the_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".wr/state.json");
+        let state = RunState {
+            environment: "Staging".to_string(),
+            version: Some("1.2.0-rc.1".to_string()),
+            pipeline_id: Some(42),
+            created: true,
+            pushed: true,
+            deployed: false,
+        };
+
+        assert_eq!(None, load(&path).unwrap());
+
+        save(&path, &state).unwrap();
+        assert_eq!(Some(state), load(&path).unwrap());
+        assert!(dir.path().join(".wr/.gitignore").exists());
+
+        clear(&path).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn reads_the_version_of_the_previous_run() {
+        let state = RunState {
+            version: Some("1.2.0-rc.1".to_string()),
+            ..RunState::default()
+        };
+        assert_eq!(
+            Some(Version::parse("1.2.0-rc.1").unwrap()),
+            state.get_version().unwrap()
+        );
+
+        assert_eq!(None, RunState::default().get_version().unwrap());
+        assert!(RunState {
+            version: Some("latest".to_string()),
+            ..RunState::default()
+        }
+        .get_version()
+        .is_err());
+    }
+
+    #[test]
+    fn loads_a_state_saved_without_a_pipeline() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        std::fs::write(
+            &path,
+            r#"{"environment":"Production","version":"1.2.0","created":true,"pushed":true,"deployed":false}"#,
+        )
+        .unwrap();
+
+        assert_eq!(None, load(&path).unwrap().unwrap().pipeline_id);
+    }
+}
@@ -0,0 +1,63 @@
+use schemars::schema_for;
+
+use crate::config::Config;
+use crate::event::Event;
+
+/// The types whose JSON schema can be printed
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum SchemaKind {
+    /// The events printed with the json flag
+    Events,
+    /// The wr.toml configuration file
+    Config,
+}
+
+/// Get the JSON schema of a type, pretty-printed
+pub fn get_json_schema(kind: SchemaKind) -> String {
+    let schema = match kind {
+        SchemaKind::Events => schema_for!(Event),
+        SchemaKind::Config => schema_for!(Config),
+    };
+
+    serde_json::to_string_pretty(&schema).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::schema::{get_json_schema, SchemaKind};
+
+    #[test]
+    fn generates_the_config_schema() {
+        let schema: Value = serde_json::from_str(&get_json_schema(SchemaKind::Config)).unwrap();
+
+        assert_eq!("Config", schema["title"]);
+        assert!(schema["properties"]["environments"].is_object());
+        assert!(schema["properties"]["steps"].is_object());
+    }
+
+    #[test]
+    fn generates_the_events_schema() {
+        let schema: Value = serde_json::from_str(&get_json_schema(SchemaKind::Events)).unwrap();
+
+        let events: Vec<&Value> = schema["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| &variant["properties"]["event"]["enum"][0])
+            .collect();
+
+        assert_eq!(
+            vec![
+                "release_started",
+                "created",
+                "pushed",
+                "deployed",
+                "deploy_failed",
+                "summary"
+            ],
+            events
+        );
+    }
+}
@@ -1,6 +1,9 @@
+use regex::Regex;
 use std::fmt;
 use std::str::FromStr;
 
+use crate::changelog::CommitInfo;
+
 #[derive(Debug, Copy, Clone, clap::ValueEnum, Default)]
 pub enum SemverType {
     Major,
@@ -9,6 +12,59 @@ pub enum SemverType {
     Patch,
 }
 
+impl SemverType {
+    /// Rank the semver types, from the smallest to the biggest increment
+    fn rank(&self) -> u8 {
+        match self {
+            SemverType::Major => 3,
+            SemverType::Minor => 2,
+            SemverType::Patch => 1,
+        }
+    }
+
+    /// Infer how to increment the version number from a conventional commit
+    pub fn from_conventional_commit(commit: &CommitInfo) -> Option<SemverType> {
+        lazy_static! {
+            static ref CONVENTIONAL_COMMIT_REGEX: Regex =
+                Regex::new(r"^(?P<type>\w+)(\([^)]*\))?(?P<breaking>!)?: ").unwrap();
+        }
+
+        let captures = CONVENTIONAL_COMMIT_REGEX.captures(&commit.subject)?;
+
+        if captures.name("breaking").is_some()
+            || commit.body.contains("BREAKING CHANGE")
+            || commit.body.contains("BREAKING-CHANGE")
+        {
+            return Some(SemverType::Major);
+        }
+
+        match &captures["type"] {
+            "feat" => Some(SemverType::Minor),
+            "fix" | "perf" => Some(SemverType::Patch),
+            _ => None,
+        }
+    }
+
+    /// Infer how to increment the version number from a list of conventional
+    /// commits, the biggest increment winning, along with the triggering commit
+    pub fn infer_from_commits(commits: &[CommitInfo]) -> Option<(SemverType, &CommitInfo)> {
+        commits
+            .iter()
+            .filter_map(|commit| {
+                SemverType::from_conventional_commit(commit)
+                    .map(|semver_type| (semver_type, commit))
+            })
+            .fold(None, |inferred, (semver_type, commit)| match inferred {
+                Some((inferred_type, _))
+                    if SemverType::rank(&inferred_type) >= semver_type.rank() =>
+                {
+                    inferred
+                }
+                _ => Some((semver_type, commit)),
+            })
+    }
+}
+
 impl FromStr for SemverType {
     type Err = &'static str;
 
@@ -27,3 +83,89 @@ impl fmt::Display for SemverType {
         write!(f, "{:?}", self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::changelog::CommitInfo;
+    use crate::semver_type::SemverType;
+
+    fn commit(subject: &str, body: &str) -> CommitInfo {
+        CommitInfo {
+            sha: subject.to_string(),
+            author: "wr".to_string(),
+            date: "2024-06-01T12:00:00+00:00".to_string(),
+            subject: subject.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    fn infer(commits: &[CommitInfo]) -> Option<(String, String)> {
+        SemverType::infer_from_commits(commits)
+            .map(|(semver_type, commit)| (semver_type.to_string(), commit.subject.clone()))
+    }
+
+    #[test]
+    fn infers_a_patch_from_fixes() {
+        let commits = vec![
+            commit("chore: bump dependencies", ""),
+            commit("perf(api): cache the results", ""),
+            commit("fix: fix a bug", ""),
+        ];
+
+        assert_eq!(
+            Some((
+                "Patch".to_string(),
+                "perf(api): cache the results".to_string()
+            )),
+            infer(&commits)
+        );
+    }
+
+    #[test]
+    fn infers_a_minor_from_features() {
+        let commits = vec![
+            commit("fix: fix a bug", ""),
+            commit("feat(ui): add a button", ""),
+            commit("docs: update the README", ""),
+        ];
+
+        assert_eq!(
+            Some(("Minor".to_string(), "feat(ui): add a button".to_string())),
+            infer(&commits)
+        );
+    }
+
+    #[test]
+    fn infers_a_major_from_breaking_changes() {
+        let commits = vec![
+            commit("feat: add a button", ""),
+            commit("refactor!: drop the old api", ""),
+        ];
+        assert_eq!(
+            Some((
+                "Major".to_string(),
+                "refactor!: drop the old api".to_string()
+            )),
+            infer(&commits)
+        );
+
+        let commits = vec![
+            commit("fix: fix a bug", ""),
+            commit(
+                "feat: new config",
+                "BREAKING CHANGE: the config format changed",
+            ),
+        ];
+        assert_eq!(
+            Some(("Major".to_string(), "feat: new config".to_string())),
+            infer(&commits)
+        );
+    }
+
+    #[test]
+    fn infers_nothing_without_conventional_commits() {
+        let commits = vec![commit("Update the README", ""), commit("wip", "")];
+
+        assert_eq!(None, infer(&commits));
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::Error;
+use duct::cmd;
+use git2::{ErrorCode, Oid, Repository};
+
+use crate::changelog::CommitInfo;
+use crate::error::WrError;
+
+/// The signature state of a commit
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SignatureStatus {
+    Valid,
+    Unsigned,
+    Invalid,
+}
+
+/// Get the signature state of a commit, checking its signature with `verify`
+fn get_signature_status(
+    repository: &Repository,
+    oid: Oid,
+    verify: impl Fn(Oid) -> bool,
+) -> Result<SignatureStatus, Error> {
+    match repository.extract_signature(&oid, None) {
+        Ok(_) if verify(oid) => Ok(SignatureStatus::Valid),
+        Ok(_) => Ok(SignatureStatus::Invalid),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(SignatureStatus::Unsigned),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Check the signature of a commit with git, which knows about the GPG and
+/// SSH signatures
+fn verify_commit(oid: Oid) -> bool {
+    cmd!("git", "verify-commit", oid.to_string())
+        .stdout_null()
+        .stderr_null()
+        .unchecked()
+        .run()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// List the commits which are not signed, or whose signature is invalid
+fn get_unsigned_commits<'c>(
+    repository: &Repository,
+    commits: &'c [CommitInfo],
+    verify: impl Fn(Oid) -> bool,
+) -> Result<Vec<(&'c CommitInfo, SignatureStatus)>, Error> {
+    let mut unsigned = vec![];
+
+    for commit in commits {
+        let status = get_signature_status(repository, Oid::from_str(&commit.sha)?, &verify)?;
+
+        if status != SignatureStatus::Valid {
+            unsigned.push((commit, status));
+        }
+    }
+
+    Ok(unsigned)
+}
+
+/// Verify that the commits of the release are all validly signed, reporting
+/// the others, and failing unless forced
+pub fn verify_signatures(
+    repository: &Repository,
+    commits: &[CommitInfo],
+    force: bool,
+) -> Result<(), Error> {
+    let unsigned = get_unsigned_commits(repository, commits, verify_commit)?;
+
+    if unsigned.is_empty() {
+        info!("[Setup] The {} commits are signed.", commits.len());
+        return Ok(());
+    }
+
+    for (commit, status) in &unsigned {
+        warn!(
+            "[Setup] {:.7} \"{}\" is {:?}.",
+            commit.sha, commit.subject, status
+        );
+    }
+
+    let error = WrError::UnsignedCommits {
+        count: unsigned.len(),
+    };
+
+    if force {
+        warn!("[Setup] {} But force flag has been passed.", error);
+        return Ok(());
+    }
+
+    Err(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use git2::Repository;
+
+    use crate::changelog::CommitInfo;
+    use crate::signature::{get_signature_status, get_unsigned_commits, SignatureStatus};
+    use crate::test_utils::{commit, commit_signed, init_repository};
+
+    const SIGNATURE: &str = "-----BEGIN PGP SIGNATURE-----\n\niQEz\n-----END PGP SIGNATURE-----";
+
+    fn commit_info(repository: &Repository, oid: git2::Oid) -> CommitInfo {
+        CommitInfo::from(&repository.find_commit(oid).unwrap())
+    }
+
+    #[test]
+    fn gets_the_signature_status_of_a_commit() {
+        let (_dir, repository) = init_repository("main");
+        let unsigned = commit(&repository, "Unsigned");
+        let signed = commit_signed(&repository, "Signed", SIGNATURE);
+
+        assert_eq!(
+            SignatureStatus::Unsigned,
+            get_signature_status(&repository, unsigned, |_| true).unwrap()
+        );
+        assert_eq!(
+            SignatureStatus::Valid,
+            get_signature_status(&repository, signed, |_| true).unwrap()
+        );
+        assert_eq!(
+            SignatureStatus::Invalid,
+            get_signature_status(&repository, signed, |_| false).unwrap()
+        );
+    }
+
+    #[test]
+    fn lists_the_commits_not_validly_signed() {
+        let (_dir, repository) = init_repository("main");
+        let unsigned = commit(&repository, "Unsigned");
+        let signed = commit_signed(&repository, "Signed", SIGNATURE);
+        let forged = commit_signed(&repository, "Forged", SIGNATURE);
+        let commits: Vec<CommitInfo> = [forged, signed, unsigned]
+            .into_iter()
+            .map(|oid| commit_info(&repository, oid))
+            .collect();
+
+        let found: Vec<(String, SignatureStatus)> =
+            get_unsigned_commits(&repository, &commits, |oid| oid == signed)
+                .unwrap()
+                .into_iter()
+                .map(|(commit, status)| (commit.subject.clone(), status))
+                .collect();
+
+        assert_eq!(
+            vec![
+                ("Forged".to_string(), SignatureStatus::Invalid),
+                ("Unsigned".to_string(), SignatureStatus::Unsigned),
+            ],
+            found
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use anyhow::Error;
+use git2::Repository;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::changelog::CommitRange;
+
+/// Some figures about the changes of a release
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct ReleaseStats {
+    /// The number of commits.
+    pub commits: usize,
+    /// The number of distinct commit authors.
+    pub authors: usize,
+    /// The number of files changed.
+    pub files_changed: usize,
+}
+
+impl ReleaseStats {
+    /// Compute the stats of the changes of a commit range, the files being
+    /// compared between its start and HEAD
+    pub fn compute(repository: &Repository, range: &CommitRange) -> Result<Self, Error> {
+        let authors: HashSet<&str> = range
+            .commits
+            .iter()
+            .map(|commit| commit.author.as_str())
+            .collect();
+
+        let old_tree = match range.from {
+            Some(from) => Some(repository.find_commit(from)?.tree()?),
+            None => None,
+        };
+        let new_tree = match range.to.first() {
+            Some(to) => Some(repository.find_commit(*to)?.tree()?),
+            None => None,
+        };
+        let diff = repository.diff_tree_to_tree(old_tree.as_ref(), new_tree.as_ref(), None)?;
+
+        Ok(ReleaseStats {
+            commits: range.commits.len(),
+            authors: authors.len(),
+            files_changed: diff.stats()?.files_changed(),
+        })
+    }
+}
+
+impl fmt::Display for ReleaseStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} commits by {} authors, {} files changed",
+            self.commits, self.authors, self.files_changed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::changelog::{format_commits, get_contributors, CommitRange};
+    use crate::semver_type::SemverType;
+    use crate::stats::ReleaseStats;
+    use crate::test_utils::{commit_file, init_repository};
+
+    #[test]
+    fn computes_the_stats_between_two_commits() {
+        let (_dir, repository) = init_repository("main");
+        let from = commit_file(&repository, "alice", "README.md", "Add a readme");
+        commit_file(&repository, "bob", "src/main.rs", "Add the sources");
+        commit_file(&repository, "alice", "README.md", "Update the readme");
+        commit_file(&repository, "carol", "src/main.rs", "Fix the sources");
+        let start = repository.find_commit(from).unwrap();
+        let range = CommitRange::resolve(&repository, Some(&start), None).unwrap();
+
+        assert_eq!(
+            ReleaseStats {
+                commits: 3,
+                authors: 3,
+                files_changed: 2,
+            },
+            ReleaseStats::compute(&repository, &range).unwrap()
+        );
+    }
+
+    #[test]
+    fn derives_everything_from_a_single_range() {
+        let (_dir, repository) = init_repository("main");
+        let from = commit_file(&repository, "alice", "README.md", "docs: add a readme");
+        commit_file(&repository, "bob", "src/main.rs", "feat: add the sources");
+        let head = commit_file(&repository, "carol", "src/lib.rs", "fix: fix the sources");
+        let start = repository.find_commit(from).unwrap();
+
+        let range = CommitRange::resolve(&repository, Some(&start), None).unwrap();
+
+        assert_eq!(Some(from), range.from);
+        assert_eq!(vec![head], range.to);
+        assert_eq!(
+            format!("* fix: fix the sources ({:.7})", head),
+            format_commits(&range.commits).lines().next().unwrap()
+        );
+        assert_eq!(vec!["bob", "carol"], get_contributors(&range.commits));
+        assert!(matches!(
+            SemverType::infer_from_commits(&range.commits),
+            Some((SemverType::Minor, _))
+        ));
+        assert_eq!(
+            ReleaseStats {
+                commits: 2,
+                authors: 2,
+                files_changed: 2,
+            },
+            ReleaseStats::compute(&repository, &range).unwrap()
+        );
+    }
+
+    #[test]
+    fn computes_the_stats_of_the_whole_history() {
+        let (_dir, repository) = init_repository("main");
+        commit_file(&repository, "alice", "README.md", "Add a readme");
+
+        let range = CommitRange::resolve(&repository, None, None).unwrap();
+        let stats = ReleaseStats::compute(&repository, &range).unwrap();
+
+        assert_eq!(2, stats.commits);
+        assert_eq!(2, stats.authors);
+        assert_eq!(1, stats.files_changed);
+        assert_eq!("2 commits by 2 authors, 1 files changed", stats.to_string());
+    }
+}
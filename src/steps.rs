@@ -0,0 +1,245 @@
+use std::fmt;
+
+use anyhow::{anyhow, Error};
+use duct::cmd;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// When a step is run during the release
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    PreCheck,
+    PreRelease,
+    PostRelease,
+    PostDeploy,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let phase = match self {
+            Phase::PreCheck => "pre-check",
+            Phase::PreRelease => "pre-release",
+            Phase::PostRelease => "post-release",
+            Phase::PostDeploy => "post-deploy",
+        };
+
+        write!(f, "{}", phase)
+    }
+}
+
+/// What to do when a step fails
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnFailure {
+    #[default]
+    Abort,
+    Continue,
+}
+
+/// A custom command, run at some phase of the release
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Step {
+    /// The name of the step.
+    pub name: String,
+    /// The phase when the step is run.
+    pub phase: Phase,
+    /// The shell command to run.
+    pub command: String,
+    /// What to do when the command fails.
+    #[serde(default)]
+    pub on_failure: OnFailure,
+}
+
+pub struct StepRunner<'a> {
+    pub steps: &'a [Step],
+    pub dry_run: bool,
+}
+
+impl StepRunner<'_> {
+    /// Run the steps of a phase, in order
+    pub fn run(&self, phase: Phase, vars: &[(&str, String)]) -> Result<(), Error> {
+        self.run_with(phase, vars, run_command)
+    }
+
+    fn run_with<F>(&self, phase: Phase, vars: &[(&str, String)], mut run: F) -> Result<(), Error>
+    where
+        F: FnMut(&Step, &[(&str, String)]) -> Result<(), Error>,
+    {
+        for step in self.steps.iter().filter(|step| step.phase == phase) {
+            if self.dry_run {
+                info!(
+                    "[Steps] Dry run, skipping \"{}\" ({}).",
+                    step.name, step.command
+                );
+                continue;
+            }
+
+            info!("[Steps] Running \"{}\" ({} phase).", step.name, phase);
+
+            let mut step_vars = vars.to_vec();
+            step_vars.push(("WR_PHASE", phase.to_string()));
+            step_vars.push(("WR_STEP", step.name.clone()));
+
+            if let Err(e) = run(step, &step_vars) {
+                match step.on_failure {
+                    OnFailure::Abort => {
+                        return Err(anyhow!("[Steps] \"{}\" step failed ({}).", step.name, e))
+                    }
+                    OnFailure::Continue => {
+                        warn!("[Steps] \"{}\" step failed, continuing ({}).", step.name, e)
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Run the command of a step in a shell, streaming its output
+fn run_command(step: &Step, vars: &[(&str, String)]) -> Result<(), Error> {
+    let mut expression = cmd!("sh", "-c", &step.command);
+
+    for (key, value) in vars {
+        expression = expression.env(key, value);
+    }
+
+    expression.run()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use crate::config::Config;
+    use crate::steps::{OnFailure, Phase, Step, StepRunner};
+
+    fn step(name: &str, phase: Phase, on_failure: OnFailure) -> Step {
+        Step {
+            name: name.to_string(),
+            phase,
+            command: format!("echo {}", name),
+            on_failure,
+        }
+    }
+
+    #[test]
+    fn parses_steps() {
+        let config = Config::parse(
+            r#"
+[[steps]]
+name = "build"
+phase = "pre-release"
+command = "npm run build"
+
+[[steps]]
+name = "notify"
+phase = "post-deploy"
+command = "./notify.sh"
+on_failure = "continue"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(2, config.steps.len());
+        assert_eq!(Phase::PreRelease, config.steps[0].phase);
+        assert_eq!(OnFailure::Abort, config.steps[0].on_failure);
+        assert_eq!(OnFailure::Continue, config.steps[1].on_failure);
+    }
+
+    #[test]
+    fn runs_the_steps_of_a_phase_in_order() {
+        let steps = vec![
+            step("first", Phase::PreRelease, OnFailure::Abort),
+            step("other", Phase::PostRelease, OnFailure::Abort),
+            step("second", Phase::PreRelease, OnFailure::Abort),
+        ];
+        let runner = StepRunner {
+            steps: &steps,
+            dry_run: false,
+        };
+        let mut ran = vec![];
+
+        runner
+            .run_with(Phase::PreRelease, &[], |step, _| {
+                ran.push(step.name.clone());
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(vec!["first", "second"], ran);
+    }
+
+    #[test]
+    fn stops_at_the_first_failing_step() {
+        let steps = vec![
+            step("ignored", Phase::PreRelease, OnFailure::Continue),
+            step("failing", Phase::PreRelease, OnFailure::Abort),
+            step("never", Phase::PreRelease, OnFailure::Abort),
+        ];
+        let runner = StepRunner {
+            steps: &steps,
+            dry_run: false,
+        };
+        let mut ran = vec![];
+
+        let result = runner.run_with(Phase::PreRelease, &[], |step, _| {
+            ran.push(step.name.clone());
+            match step.name.as_str() {
+                "never" => Ok(()),
+                _ => Err(anyhow!("exit status 1")),
+            }
+        });
+
+        assert_eq!(
+            "[Steps] \"failing\" step failed (exit status 1).",
+            result.unwrap_err().to_string()
+        );
+        assert_eq!(vec!["ignored", "failing"], ran);
+    }
+
+    #[test]
+    fn does_not_run_steps_in_dry_run() {
+        let steps = vec![step("build", Phase::PreRelease, OnFailure::Abort)];
+        let runner = StepRunner {
+            steps: &steps,
+            dry_run: true,
+        };
+
+        runner
+            .run_with(Phase::PreRelease, &[], |_, _| panic!("step was run"))
+            .unwrap();
+    }
+
+    #[test]
+    fn passes_variables_to_the_steps() {
+        let steps = vec![step("build", Phase::PostRelease, OnFailure::Abort)];
+        let runner = StepRunner {
+            steps: &steps,
+            dry_run: false,
+        };
+        let mut vars: Vec<String> = vec![];
+
+        runner
+            .run_with(
+                Phase::PostRelease,
+                &[("WR_VERSION", "1.2.3".to_string())],
+                |_, step_vars| {
+                    vars = step_vars
+                        .iter()
+                        .map(|(key, value)| format!("{}={}", key, value))
+                        .collect();
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            vec!["WR_VERSION=1.2.3", "WR_PHASE=post-release", "WR_STEP=build"],
+            vars
+        );
+    }
+}
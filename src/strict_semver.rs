@@ -0,0 +1,111 @@
+use anyhow::Error;
+use git2::Repository;
+use semver::Version;
+
+use crate::error::WrError;
+
+/// How a tag is read as a version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagKind {
+    /// The tag is a semver version.
+    Valid,
+    /// The tag looks like a version, but is not a semver one, for the given
+    /// reason.
+    NearMiss(String),
+    /// The tag is not meant to be a version.
+    Ignored,
+}
+
+/// Tell whether a tag is a version, or looks like one
+pub fn classify_tag(tag: &str) -> TagKind {
+    let Err(error) = Version::parse(tag) else {
+        return TagKind::Valid;
+    };
+
+    let unprefixed = tag.strip_prefix(['v', 'V']).unwrap_or(tag);
+
+    if !unprefixed.starts_with(|c: char| c.is_ascii_digit()) {
+        return TagKind::Ignored;
+    }
+
+    if unprefixed != tag && Version::parse(unprefixed).is_ok() {
+        return TagKind::NearMiss(format!("has a \"{}\" prefix", &tag[..1]));
+    }
+
+    let numbers = unprefixed.split(['-', '+']).next().unwrap_or_default();
+    if numbers.split('.').count() < 3 {
+        return TagKind::NearMiss("misses the minor or patch number".to_string());
+    }
+
+    TagKind::NearMiss(error.to_string())
+}
+
+/// Fail when some tags look like versions, but are not semver ones, instead
+/// of ignoring them
+pub fn check_tags<'a>(tags: impl Iterator<Item = &'a str>) -> Result<(), WrError> {
+    let near_misses: Vec<String> = tags
+        .filter_map(|tag| match classify_tag(tag) {
+            TagKind::NearMiss(reason) => Some(format!("\"{}\" {}", tag, reason)),
+            _ => None,
+        })
+        .collect();
+
+    match near_misses.is_empty() {
+        true => Ok(()),
+        false => Err(WrError::NonSemverTags {
+            report: near_misses.join(", "),
+        }),
+    }
+}
+
+/// Check the tags of a repository in strict semver mode
+pub fn check(repository: &Repository) -> Result<(), Error> {
+    let tags = repository.tag_names(None)?;
+    check_tags(tags.iter().flatten())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::WrError;
+    use crate::strict_semver::{check_tags, classify_tag, TagKind};
+
+    fn is_near_miss(tag: &str) -> bool {
+        matches!(classify_tag(tag), TagKind::NearMiss(_))
+    }
+
+    #[test]
+    fn classifies_the_tags() {
+        assert_eq!(TagKind::Valid, classify_tag("1.2.3"));
+        assert_eq!(TagKind::Valid, classify_tag("2.0.0-rc.1+build.5"));
+
+        assert_eq!(
+            TagKind::NearMiss("has a \"v\" prefix".to_string()),
+            classify_tag("v1.2.3")
+        );
+        assert_eq!(
+            TagKind::NearMiss("misses the minor or patch number".to_string()),
+            classify_tag("1.0")
+        );
+        assert!(is_near_miss("V2"));
+        assert!(is_near_miss("1.2.3.4"));
+        assert!(is_near_miss("1.02.3"));
+
+        assert_eq!(TagKind::Ignored, classify_tag("latest"));
+        assert_eq!(TagKind::Ignored, classify_tag("release-candidate"));
+        assert_eq!(TagKind::Ignored, classify_tag("v"));
+    }
+
+    #[test]
+    fn reports_the_near_miss_tags() {
+        assert!(check_tags(["1.0.0", "1.1.0", "latest"].into_iter()).is_ok());
+
+        let error = check_tags(["1.0.0", "v1.1.0", "1.2", "latest"].into_iter()).unwrap_err();
+        assert!(matches!(
+            &error,
+            WrError::NonSemverTags { report }
+                if report == "\"v1.1.0\" has a \"v\" prefix, \"1.2\" misses the minor or patch number"
+        ));
+    }
+}
@@ -1,33 +1,43 @@
 use anyhow::{anyhow, Error};
 use duct::cmd;
-use git2::{ErrorCode, FetchOptions, Repository, StatusOptions};
-use std::{env, path::Path};
+use git2::{AutotagOption, ErrorCode, FetchOptions, Oid, Repository, StatusOptions};
 
+use crate::environment::Environment;
+use crate::error::WrError;
+use crate::release_model::ReleaseModel;
 use crate::repository_status::RepositoryStatus;
 use crate::{
-    git::{self, get_gitflow_branches_refs, get_remote},
+    git::{self, get_remote},
     DEVELOP_BRANCH, MASTER_BRANCH,
 };
 
+/// The default path of the GitLab CI configuration file
+const DEFAULT_CI_CONFIG_PATH: &str = ".gitlab-ci.yml";
+
+/// The git configuration key overriding the GitLab CI configuration file path
+const CI_CONFIG_PATH_CONFIG: &str = "wr.ciConfigPath";
+
 pub struct System<'a> {
     pub repository: &'a Repository,
     pub force: bool,
+    pub tag_only: bool,
+    pub environment: Environment,
+    pub release_model: ReleaseModel,
+    pub ci_config_path: Option<String>,
+    /// Whether the tags are fetched along, for the next version to be
+    /// computed from them.
+    pub fetch_tags: bool,
 }
 
 impl System<'_> {
     /// Test if git is installed
     fn check_git(&self) -> Result<(), Error> {
-        let output = cmd!("which", "git").stdout_capture().run()?;
-
-        match output.status.code() {
-            Some(0) => Ok(()),
-            _ => Err(anyhow!("\"git\" not found. Please install git.")),
-        }
+        require_tool("git", is_tool_available("git", &["--version"]))
     }
 
     /// Test if git-flow is installed
     fn check_git_flow(&self) -> Result<(), Error> {
-        let output = cmd!("git", "flow", "version").stdout_capture().run()?;
+        let output = git::git_flow(&["version"]).stdout_capture().run()?;
 
         match output.status.code() {
             Some(0) => Ok(()),
@@ -37,7 +47,7 @@ impl System<'_> {
 
     /// Test if git-flow AVH is installed
     fn check_git_flow_version(&self) -> Result<(), Error> {
-        let output = cmd!("git", "flow", "version").read()?;
+        let output = git::git_flow(&["version"]).read()?;
 
         match output.contains("AVH").then_some(0) {
             Some(_) => Ok(()),
@@ -45,17 +55,17 @@ impl System<'_> {
         }
     }
 
-    /// Test if a file exists
-    fn file_exists(&self, file_name: String) -> bool {
-        let current_dir = env::current_dir().unwrap();
-        let path = format!("{}/{}", current_dir.display(), file_name);
-
-        Path::new(&path).exists()
+    /// Test if a file exists in the repository's working directory
+    fn file_exists(&self, file_name: &str) -> bool {
+        match self.repository.workdir() {
+            Some(workdir) => workdir.join(file_name).exists(),
+            None => false,
+        }
     }
 
     /// Test if the repository is initialized with git flow
     fn is_git_flow_initialized(&self) -> Result<(), Error> {
-        let output = cmd!("git", "flow", "config")
+        let output = git::git_flow(&["config"])
             .stdout_capture()
             .stderr_capture()
             .run();
@@ -79,12 +89,41 @@ impl System<'_> {
         };
         let head = head.as_ref().and_then(|h| h.shorthand());
 
-        match (head.unwrap() == branch_name).then_some(0) {
-            Some(_) => Ok(()),
+        match head {
+            Some(head) if head == branch_name => Ok(()),
             _ => Err(anyhow!("Please checkout the {} branch", branch_name)),
         }
     }
 
+    /// Test that a production release is not made from the master branch
+    /// itself, which happens when develop and master are the same branch
+    fn is_not_on_master_branch(
+        &self,
+        branch_name: &str,
+        master_branch_name: &str,
+    ) -> Result<(), Error> {
+        if self.environment != Environment::Production || self.tag_only {
+            return Ok(());
+        }
+
+        if branch_name != master_branch_name {
+            return Ok(());
+        }
+
+        if self.force {
+            warn!(
+                "[Setup] Releasing from the {} branch, but force flag has been passed.",
+                branch_name
+            );
+            return Ok(());
+        }
+
+        Err(WrError::ReleaseFromMasterBranch {
+            branch: branch_name.to_string(),
+        }
+        .into())
+    }
+
     /// Test if an upstream branch is correctly defined
     fn is_upstream_branch_defined(&self, branch_name: String) -> Result<(), Error> {
         let spec = format!("{branch_name}@{{u}}", branch_name = branch_name);
@@ -92,64 +131,126 @@ impl System<'_> {
 
         match revspec {
             Ok(_) => Ok(()),
-            Err(_) => Err(anyhow!("
-                Upstream branches are not correctly defined.
-                Please run 'git checkout {branch_name} && git branch --set-upstream-to=origin/{branch_name} {branch_name}'.",
-                branch_name=branch_name
-            )),
+            Err(_) => Err(upstream_not_defined(&branch_name)),
         }
     }
 
+    /// Compare a local branch with its upstream branch
+    fn get_branch_status(&self, branch_name: &str) -> Result<RepositoryStatus, Error> {
+        let spec = format!("{branch_name}@{{u}}", branch_name = branch_name);
+
+        let local = self
+            .repository
+            .revparse_single(branch_name)?
+            .peel_to_commit()?
+            .id();
+        let remote = match self.repository.revparse_single(&spec) {
+            Ok(object) => object.peel_to_commit()?.id(),
+            Err(_) => return Err(upstream_not_defined(branch_name)),
+        };
+
+        self.compare_commits(local, remote)
+    }
+
+    /// Compare a local branch with its remote-tracking branch, both resolved
+    /// by their full reference name, without relying on the "@{u}" shorthand
+    fn get_refs_status(
+        &self,
+        branch_name: &str,
+        local_ref: &str,
+        upstream_ref: &str,
+    ) -> Result<RepositoryStatus, Error> {
+        let local = self
+            .repository
+            .find_reference(local_ref)?
+            .peel_to_commit()?
+            .id();
+        let remote = match self.repository.find_reference(upstream_ref) {
+            Ok(reference) => reference.peel_to_commit()?.id(),
+            Err(_) => return Err(upstream_not_defined(branch_name)),
+        };
+
+        self.compare_commits(local, remote)
+    }
+
+    /// Compare a local commit with a remote one, through their merge base
+    fn compare_commits(&self, local: Oid, remote: Oid) -> Result<RepositoryStatus, Error> {
+        // Compare base, local and remote (https://stackoverflow.com/a/3278427)
+        let base = self.repository.merge_base(local, remote)?;
+
+        let status = if local == remote {
+            RepositoryStatus::UpToDate
+        } else if local == base {
+            RepositoryStatus::NeedToPull
+        } else if remote == base {
+            RepositoryStatus::NeedToPush
+        } else {
+            RepositoryStatus::Diverged
+        };
+
+        Ok(status)
+    }
+
     /// Get the repository status and go further only if we need to push
     /// something
     fn get_repository_status(&self) -> Result<(), Error> {
+        let head = self.repository.head()?;
+        let current_branch = head.shorthand().unwrap_or("HEAD");
+
         let mut fetch_options = FetchOptions::new();
         fetch_options.remote_callbacks(git::create_remote_callback().unwrap());
-        fetch_options.download_tags(git2::AutotagOption::All);
+        fetch_options.download_tags(get_autotag_option(self.fetch_tags));
 
         let mut remote = get_remote(self.repository)?;
 
-        // Fetch first
-        let branches_refs: Vec<String> = get_gitflow_branches_refs();
-        remote.download(&branches_refs, Some(&mut fetch_options))?;
-
-        // Then compare base, local and remote (https://stackoverflow.com/a/3278427)
-        let local = self.repository.revparse("@{0}")?.from().unwrap().id();
-        let remote = self.repository.revparse("@{u}")?.from().unwrap().id();
-        let base = self.repository.merge_base(local, remote).unwrap();
-
-        let status;
-
-        if local == remote {
-            status = RepositoryStatus::UpToDate;
-        } else if local == base {
-            status = RepositoryStatus::NeedToPull;
-        } else if remote == base {
-            status = RepositoryStatus::NeedToPush;
+        // Fetch first, only the compared branch, updating its remote-tracking
+        // branch
+        let refspecs = get_status_refspecs(self.tag_only, current_branch, &DEVELOP_BRANCH);
+        remote
+            .fetch(&refspecs, Some(&mut fetch_options), None)
+            .map_err(git::map_remote_error)?;
+
+        let status = if self.tag_only {
+            debug!(
+                "Comparing the {} branch with its upstream branch.",
+                current_branch
+            );
+            self.get_branch_status(current_branch)?
         } else {
-            status = RepositoryStatus::Diverged;
-        }
+            let develop_branch = DEVELOP_BRANCH.as_str();
+            let remote_name = git::get_remote_name();
+            debug!(
+                "Comparing the {} branch with {}/{}.",
+                develop_branch, remote_name, develop_branch
+            );
+            self.get_refs_status(
+                develop_branch,
+                &format!("refs/heads/{}", develop_branch),
+                &format!("refs/remotes/{}/{}", remote_name, develop_branch),
+            )?
+        };
 
-        match status {
-            RepositoryStatus::UpToDate => {
-                if self.force {
-                    info!("[Setup] Repository is up-to-date, but force flag has been passed.");
-                    Ok(())
-                } else {
-                    Err(anyhow!("Repository is up-to-date, nothing to do."))
-                }
-            }
-            RepositoryStatus::NeedToPull => Err(anyhow!("Repository need to be pulled first.")),
-            RepositoryStatus::Diverged => Err(anyhow!(
-                "Branch have diverged, please fix the conflict first."
-            )),
-            RepositoryStatus::NeedToPush => Ok(()),
-        }
+        check_repository_status(status, self.release_model, self.force)
+    }
+
+    /// Get the path of the GitLab CI configuration file, from the arguments,
+    /// the git configuration ("wr.ciConfigPath"), or the default one
+    pub fn get_ci_config_path(&self) -> String {
+        self.ci_config_path
+            .clone()
+            .or_else(|| {
+                self.repository
+                    .config()
+                    .ok()?
+                    .get_string(CI_CONFIG_PATH_CONFIG)
+                    .ok()
+            })
+            .unwrap_or_else(|| DEFAULT_CI_CONFIG_PATH.to_string())
     }
 
-    /// Test if the repository has a .gitlab-ci.yml
+    /// Test if the repository has a GitLab CI configuration file
     pub fn has_gitlab_ci(&self) -> bool {
-        self.file_exists(".gitlab-ci.yml".to_string())
+        self.file_exists(&self.get_ci_config_path())
     }
 
     /// Test if repository is clean
@@ -167,43 +268,522 @@ impl System<'_> {
         }
     }
 
-    /// Perform system checks
-    pub fn system_check(&self) -> Result<(), Error> {
-        debug!("Checking for git.");
-        self.check_git()?;
+    /// Test if the kind of repository is supported: a bare repository can
+    /// only be tagged, as git-flow needs a working tree
+    fn check_repository_kind(&self) -> Result<(), Error> {
+        if self.repository.is_worktree() {
+            debug!("Running in a linked worktree.");
+        }
 
-        debug!("Checking for git-flow.");
-        self.check_git_flow()?;
+        if self.repository.is_bare() && !self.tag_only {
+            return Err(WrError::UnsupportedRepositoryKind {
+                kind: "bare".to_string(),
+            }
+            .into());
+        }
 
-        debug!("Checking for git-flow version.");
-        self.check_git_flow_version()?;
+        Ok(())
+    }
 
-        debug!("Checking if the repository has git-flow initialized.");
-        self.is_git_flow_initialized()?;
+    /// Run every system check, given the master and develop branches
+    fn check_branches(&self, master_branch: &str, develop_branch: &str) -> CheckReport {
+        let mut report = CheckReport::default();
 
-        debug!(
-            "Checking if the repository is on the {} branch.",
-            DEVELOP_BRANCH.as_str()
-        );
-        self.is_on_branch(DEVELOP_BRANCH.to_string())?;
+        // The other checks would fail confusingly on an unsupported repository
+        report.run("repository kind", || self.check_repository_kind());
+        if !report.passed() {
+            return report;
+        }
+
+        report.run("git installed", || self.check_git());
+
+        if self.tag_only {
+            debug!("Tag only mode, skipping git-flow checks.");
+        } else {
+            report.run("git-flow installed", || self.check_git_flow());
+            report.run("git-flow version", || self.check_git_flow_version());
+            report.run("git-flow initialized", || self.is_git_flow_initialized());
+        }
+
+        report.run("on develop branch", || {
+            self.is_on_branch(develop_branch.to_string())
+        });
+        report.run("develop is not master", || {
+            self.is_not_on_master_branch(develop_branch, master_branch)
+        });
+        report.run("master upstream defined", || {
+            self.is_upstream_branch_defined(master_branch.to_string())
+        });
+        report.run("develop upstream defined", || {
+            self.is_upstream_branch_defined(develop_branch.to_string())
+        });
+        // The status fetches the remote, which is pointless once the branches
+        // it compares are known to be wrong
+        if report.passed() {
+            report.run("repository status", || self.get_repository_status());
+        } else {
+            debug!("A prerequisite failed, skipping the repository status check.");
+        }
+
+        if self.repository.is_bare() {
+            debug!("Bare repository, skipping the working tree checks.");
+        } else {
+            report.run("repository clean", || self.is_repository_clean());
+        }
+
+        report
+    }
 
-        debug!("Checking if upstreams are defined.");
-        self.is_upstream_branch_defined(MASTER_BRANCH.to_string())?;
-        self.is_upstream_branch_defined(DEVELOP_BRANCH.to_string())?;
+    /// Run every system check, and report their outcome
+    pub fn check(&self) -> CheckReport {
+        self.check_branches(&MASTER_BRANCH, &DEVELOP_BRANCH)
+    }
 
-        debug!("Checking if the repository is up-to-date with origin.");
-        self.get_repository_status()?;
+    /// Perform system checks
+    pub fn system_check(&self) -> Result<(), Error> {
+        let report = self.check();
 
-        debug!("Checking for .gitlab-ci.yml.");
+        let ci_config_path = self.get_ci_config_path();
+        debug!("Checking for {}.", ci_config_path);
         if self.has_gitlab_ci() {
-            debug!(".gitlab-ci.yml found");
+            debug!("{} found", ci_config_path);
         } else {
-            warn!(".gitlab-ci.yml not found");
+            warn!("{} not found", ci_config_path);
         }
 
-        debug!("Checking if repository is clean.");
-        self.is_repository_clean()?;
+        report.into_result()
+    }
+}
+
+/// The outcome of a system check
+#[derive(Debug)]
+pub struct CheckResult {
+    /// The name of the check.
+    pub name: &'static str,
+    /// Why the check failed, if it did.
+    pub error: Option<WrError>,
+}
 
-        Ok(())
+impl CheckResult {
+    /// Whether the check passed
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// The outcome of all the system checks
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl CheckReport {
+    /// Run a check, and record its outcome
+    fn run(&mut self, name: &'static str, check: impl FnOnce() -> Result<(), Error>) {
+        debug!("Checking {}.", name);
+
+        self.checks.push(CheckResult {
+            name,
+            error: check().err().map(WrError::from_error),
+        });
+    }
+
+    /// Whether every check passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(CheckResult::passed)
+    }
+
+    /// Fail with the error of the first failed check, logging the others
+    pub fn into_result(self) -> Result<(), Error> {
+        if self.passed() {
+            debug!("All the {} system checks passed.", self.checks.len());
+            return Ok(());
+        }
+
+        let mut failed = self
+            .checks
+            .into_iter()
+            .filter_map(|check| Some((check.name, check.error?)));
+        let (_, error) = failed.next().unwrap();
+
+        for (name, other) in failed {
+            error!("[Setup] The \"{}\" check failed too: {}", name, other);
+        }
+
+        Err(error.into_error())
+    }
+}
+
+/// Get the refspecs to fetch to compare the repository with its remote: the
+/// current branch when tagging it, and develop otherwise
+fn get_status_refspecs(tag_only: bool, current_branch: &str, develop_branch: &str) -> Vec<String> {
+    match tag_only {
+        true => vec![git::get_tracking_refspec(current_branch)],
+        false => vec![git::get_tracking_refspec(develop_branch)],
+    }
+}
+
+/// Download the tags along with the branch only when they are needed
+fn get_autotag_option(fetch_tags: bool) -> AutotagOption {
+    match fetch_tags {
+        true => AutotagOption::All,
+        false => AutotagOption::None,
+    }
+}
+
+/// Whether a tool is installed, by running it with some harmless arguments
+/// (portable, unlike looking it up with "which")
+fn is_tool_available(program: &str, args: &[&str]) -> bool {
+    cmd(program, args)
+        .stdout_null()
+        .stderr_null()
+        .unchecked()
+        .run()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Go further only if a tool is installed
+fn require_tool(name: &str, available: bool) -> Result<(), Error> {
+    match available {
+        true => Ok(()),
+        false => Err(anyhow!("\"{}\" not found. Please install {}.", name, name)),
+    }
+}
+
+/// Go further only if the repository status allows a release with the given
+/// release model
+fn check_repository_status(
+    status: RepositoryStatus,
+    release_model: ReleaseModel,
+    force: bool,
+) -> Result<(), Error> {
+    match status {
+        RepositoryStatus::UpToDate => {
+            if release_model == ReleaseModel::TagDevelop {
+                Ok(())
+            } else if force {
+                info!("[Setup] Repository is up-to-date, but force flag has been passed.");
+                Ok(())
+            } else {
+                Err(anyhow!("Repository is up-to-date, nothing to do."))
+            }
+        }
+        RepositoryStatus::NeedToPull => Err(anyhow!("Repository need to be pulled first.")),
+        RepositoryStatus::Diverged => Err(anyhow!(
+            "Branch have diverged, please fix the conflict first."
+        )),
+        RepositoryStatus::NeedToPush => Ok(()),
+    }
+}
+
+/// Build the error explaining how to define the upstream of a branch
+fn upstream_not_defined(branch_name: &str) -> Error {
+    anyhow!("
+        Upstream branches are not correctly defined.
+        Please run 'git checkout {branch_name} && git branch --set-upstream-to={remote_name}/{branch_name} {branch_name}'.",
+        branch_name=branch_name,
+        remote_name=git::get_remote_name()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::Environment;
+    use crate::error::WrError;
+    use crate::release_model::ReleaseModel;
+    use crate::repository_status::RepositoryStatus;
+    use crate::system::{
+        check_repository_status, get_autotag_option, get_status_refspecs, is_tool_available,
+        require_tool, CheckReport, System,
+    };
+    use crate::test_utils::{commit, init_repository};
+    use git2::{AutotagOption, Oid, Repository};
+    use std::fs;
+
+    fn system(repository: &Repository) -> System {
+        System {
+            repository,
+            force: false,
+            tag_only: false,
+            environment: Environment::Production,
+            release_model: ReleaseModel::GitFlow,
+            ci_config_path: None,
+            fetch_tags: true,
+        }
+    }
+
+    #[test]
+    fn reports_every_check_and_its_outcome() {
+        let (_dir, repository) = init_repository("develop");
+        commit(&repository, "Initial commit");
+        let mut system = system(&repository);
+        system.tag_only = true;
+
+        let report = system.check_branches("main", "develop");
+        let outcomes: Vec<(&str, bool)> = report
+            .checks
+            .iter()
+            .map(|check| (check.name, check.passed()))
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("repository kind", true),
+                ("git installed", true),
+                ("on develop branch", true),
+                ("develop is not master", true),
+                ("master upstream defined", false),
+                ("develop upstream defined", false),
+                ("repository clean", true),
+            ],
+            outcomes
+        );
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn refuses_a_git_flow_release_from_a_bare_repository() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repository = Repository::init_bare(dir.path()).unwrap();
+
+        let report = system(&repository).check_branches("main", "develop");
+
+        assert_eq!(1, report.checks.len());
+        assert!(matches!(
+            report.checks[0].error,
+            Some(WrError::UnsupportedRepositoryKind { .. })
+        ));
+    }
+
+    #[test]
+    fn skips_the_working_tree_checks_of_a_bare_repository_when_tagging() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repository = Repository::init_bare(dir.path()).unwrap();
+        let mut system = system(&repository);
+        system.tag_only = true;
+
+        let report = system.check_branches("main", "develop");
+        let names: Vec<&str> = report.checks.iter().map(|check| check.name).collect();
+
+        assert!(report.checks[0].passed());
+        assert!(!names.contains(&"repository clean"));
+    }
+
+    #[test]
+    fn fails_with_the_first_failed_check() {
+        let mut report = CheckReport::default();
+        report.run("first", || Ok(()));
+        report.run("second", || Err(anyhow::anyhow!("second failed")));
+        report.run("third", || Err(anyhow::anyhow!("third failed")));
+
+        assert_eq!(
+            "second failed",
+            report.into_result().unwrap_err().to_string()
+        );
+        assert!(CheckReport::default().into_result().is_ok());
+    }
+
+    #[test]
+    fn keeps_the_kind_of_the_failures() {
+        let mut report = CheckReport::default();
+        report.run("ssh agent", || Err(WrError::SshAgentNoKeys.into()));
+        report.run("other", || Err(anyhow::anyhow!("other failed")));
+
+        assert!(matches!(
+            report.checks[0].error,
+            Some(WrError::SshAgentNoKeys)
+        ));
+        assert!(
+            matches!(&report.checks[1].error, Some(WrError::Other(error)) if error.to_string() == "other failed")
+        );
+        assert!(matches!(
+            report.into_result().unwrap_err().downcast_ref::<WrError>(),
+            Some(WrError::SshAgentNoKeys)
+        ));
+    }
+
+    #[test]
+    fn explains_how_to_define_a_missing_upstream_branch() {
+        let (_dir, repository) = init_repository("feature");
+        let system = system(&repository);
+
+        let error = system.get_branch_status("feature").unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("git branch --set-upstream-to=origin/feature feature"));
+    }
+
+    /// Point the "origin/develop" remote-tracking branch to a commit
+    fn set_upstream(repository: &Repository, oid: Oid) {
+        repository
+            .reference("refs/remotes/origin/develop", oid, true, "test")
+            .unwrap();
+    }
+
+    fn develop_status(repository: &Repository) -> RepositoryStatus {
+        system(repository)
+            .get_refs_status(
+                "develop",
+                "refs/heads/develop",
+                "refs/remotes/origin/develop",
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn compares_develop_with_its_remote_tracking_branch() {
+        let (_dir, repository) = init_repository("develop");
+        let base = commit(&repository, "Base");
+
+        set_upstream(&repository, base);
+        assert_eq!(RepositoryStatus::UpToDate, develop_status(&repository));
+
+        let local = commit(&repository, "Local");
+        assert_eq!(RepositoryStatus::NeedToPush, develop_status(&repository));
+
+        repository
+            .reference("refs/heads/develop", base, true, "test")
+            .unwrap();
+        set_upstream(&repository, local);
+        assert_eq!(RepositoryStatus::NeedToPull, develop_status(&repository));
+
+        commit(&repository, "Diverged");
+        assert_eq!(RepositoryStatus::Diverged, develop_status(&repository));
+    }
+
+    #[test]
+    fn explains_how_to_define_a_missing_remote_tracking_branch() {
+        let (_dir, repository) = init_repository("develop");
+
+        let error = system(&repository)
+            .get_refs_status(
+                "develop",
+                "refs/heads/develop",
+                "refs/remotes/origin/develop",
+            )
+            .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("git branch --set-upstream-to=origin/develop develop"));
+    }
+
+    #[test]
+    fn refuses_a_production_release_when_develop_is_master() {
+        let (_dir, repository) = init_repository("main");
+        let system = system(&repository);
+
+        let error = system.is_not_on_master_branch("main", "main").unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<WrError>(),
+            Some(WrError::ReleaseFromMasterBranch { .. })
+        ));
+        assert!(system.is_not_on_master_branch("develop", "main").is_ok());
+    }
+
+    #[test]
+    fn allows_a_release_from_master_when_forced() {
+        let (_dir, repository) = init_repository("main");
+        let mut system = system(&repository);
+        system.force = true;
+
+        assert!(system.is_not_on_master_branch("main", "main").is_ok());
+    }
+
+    #[test]
+    fn accepts_only_repositories_to_push_with_the_git_flow_model() {
+        let model = ReleaseModel::GitFlow;
+
+        assert!(check_repository_status(RepositoryStatus::NeedToPush, model, false).is_ok());
+        assert!(check_repository_status(RepositoryStatus::UpToDate, model, false).is_err());
+        assert!(check_repository_status(RepositoryStatus::UpToDate, model, true).is_ok());
+        assert!(check_repository_status(RepositoryStatus::NeedToPull, model, true).is_err());
+        assert!(check_repository_status(RepositoryStatus::Diverged, model, true).is_err());
+    }
+
+    #[test]
+    fn accepts_up_to_date_repositories_with_the_tag_develop_model() {
+        let model = ReleaseModel::TagDevelop;
+
+        assert!(check_repository_status(RepositoryStatus::NeedToPush, model, false).is_ok());
+        assert!(check_repository_status(RepositoryStatus::UpToDate, model, false).is_ok());
+        assert!(check_repository_status(RepositoryStatus::NeedToPull, model, false).is_err());
+        assert!(check_repository_status(RepositoryStatus::Diverged, model, false).is_err());
+    }
+
+    #[test]
+    fn detects_the_gitlab_ci_file_at_the_default_path() {
+        let (dir, repository) = init_repository("main");
+        let system = system(&repository);
+        assert!(!system.has_gitlab_ci());
+
+        fs::write(dir.path().join(".gitlab-ci.yml"), "").unwrap();
+        assert!(system.has_gitlab_ci());
+    }
+
+    #[test]
+    fn detects_the_gitlab_ci_file_at_a_custom_path() {
+        let (dir, repository) = init_repository("main");
+        fs::create_dir(dir.path().join("ci")).unwrap();
+        fs::write(dir.path().join("ci/main.yml"), "").unwrap();
+
+        let mut system = system(&repository);
+        assert!(!system.has_gitlab_ci());
+
+        system.ci_config_path = Some("ci/main.yml".to_string());
+        assert!(system.has_gitlab_ci());
+    }
+
+    #[test]
+    fn reads_the_gitlab_ci_file_path_from_the_git_config() {
+        let (dir, repository) = init_repository("main");
+        fs::write(dir.path().join("gitlab.yml"), "").unwrap();
+        repository
+            .config()
+            .unwrap()
+            .set_str("wr.ciConfigPath", "gitlab.yml")
+            .unwrap();
+
+        let system = system(&repository);
+
+        assert_eq!("gitlab.yml", system.get_ci_config_path());
+        assert!(system.has_gitlab_ci());
+    }
+
+    #[test]
+    fn fetches_only_the_compared_branch() {
+        assert_eq!(
+            vec!["refs/heads/develop:refs/remotes/origin/develop"],
+            get_status_refspecs(false, "feature/x", "develop")
+        );
+        assert_eq!(
+            vec!["refs/heads/main:refs/remotes/origin/main"],
+            get_status_refspecs(true, "main", "develop")
+        );
+    }
+
+    #[test]
+    fn fetches_the_tags_only_when_needed() {
+        assert!(matches!(get_autotag_option(true), AutotagOption::All));
+        assert!(matches!(get_autotag_option(false), AutotagOption::None));
+    }
+
+    #[test]
+    fn detects_the_installed_tools() {
+        assert!(is_tool_available("git", &["--version"]));
+        assert!(!is_tool_available("wr-missing-tool", &["--version"]));
+        assert!(!is_tool_available("git", &["--no-such-option"]));
+    }
+
+    #[test]
+    fn requires_an_installed_tool() {
+        assert!(require_tool("git", true).is_ok());
+        assert_eq!(
+            "\"git\" not found. Please install git.",
+            require_tool("git", false).unwrap_err().to_string()
+        );
     }
 }
@@ -0,0 +1,122 @@
+use std::borrow::Cow;
+
+use anyhow::Error;
+use gitlab::{
+    api::{self, BodyError, Endpoint, FormParams, Query},
+    Gitlab,
+};
+use http::Method;
+
+use crate::api::dump_query;
+
+/// The endpoint creating a tag of a GitLab project, with the permissions of
+/// the API user instead of the ones of the git push
+pub struct CreateTag<'a> {
+    pub project: &'a str,
+    pub tag_name: &'a str,
+    /// The commit to tag.
+    pub r#ref: &'a str,
+    /// The message of an annotated tag, a lightweight tag being created if
+    /// empty.
+    pub message: &'a str,
+}
+
+impl CreateTag<'_> {
+    /// The form parameters of the request
+    fn get_params(&self) -> Vec<(String, String)> {
+        let mut params = vec![
+            ("tag_name".to_string(), self.tag_name.to_string()),
+            ("ref".to_string(), self.r#ref.to_string()),
+        ];
+
+        if !self.message.is_empty() {
+            params.push(("message".to_string(), self.message.to_string()));
+        }
+
+        params
+    }
+}
+
+impl Endpoint for CreateTag<'_> {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/repository/tags",
+            self.project.replace('/', "%2F")
+        )
+        .into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+
+        for (key, value) in self.get_params() {
+            params.push(key, value);
+        }
+
+        params.into_body()
+    }
+}
+
+/// Create a tag of a commit through the GitLab API
+pub fn create_tag(
+    gitlab: &Gitlab,
+    project: &str,
+    tag_name: &str,
+    r#ref: &str,
+    message: &str,
+) -> Result<(), Error> {
+    let endpoint = CreateTag {
+        project,
+        tag_name,
+        r#ref,
+        message,
+    };
+    dump_query(endpoint, |endpoint| api::ignore(endpoint).query(gitlab))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use gitlab::api::Endpoint;
+    use http::Method;
+
+    use crate::tags::CreateTag;
+
+    #[test]
+    fn assembles_the_tag_creation_request() {
+        let endpoint = CreateTag {
+            project: "aeyoll/wr",
+            tag_name: "1.2.0",
+            r#ref: "0123456789abcdef",
+            message: "1.2.0\n\nNotes",
+        };
+
+        assert_eq!(Method::POST, endpoint.method());
+        assert_eq!("projects/aeyoll%2Fwr/repository/tags", endpoint.endpoint());
+        assert_eq!(
+            vec![
+                ("tag_name".to_string(), "1.2.0".to_string()),
+                ("ref".to_string(), "0123456789abcdef".to_string()),
+                ("message".to_string(), "1.2.0\n\nNotes".to_string()),
+            ],
+            endpoint.get_params()
+        );
+    }
+
+    #[test]
+    fn creates_a_lightweight_tag_without_message() {
+        let endpoint = CreateTag {
+            project: "aeyoll/wr",
+            tag_name: "1.2.0",
+            r#ref: "0123456789abcdef",
+            message: "",
+        };
+
+        assert_eq!(2, endpoint.get_params().len());
+    }
+}
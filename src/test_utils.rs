@@ -0,0 +1,132 @@
+use std::env;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Mutex, PoisonError};
+use std::{fs, path::Path};
+
+use git2::{Oid, Repository, RepositoryInitOptions, Signature};
+use tempfile::TempDir;
+
+/// Serializes the tests setting environment variables, which are shared by
+/// the whole process
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// Run a test with some environment variables set, restoring them afterwards
+pub fn with_env(vars: &[(&str, &str)], test: impl FnOnce()) {
+    let _lock = ENV_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let previous: Vec<(&str, Option<String>)> = vars
+        .iter()
+        .map(|(key, _)| (*key, env::var(key).ok()))
+        .collect();
+    for (key, value) in vars {
+        env::set_var(key, value);
+    }
+
+    let result = panic::catch_unwind(AssertUnwindSafe(test));
+
+    for (key, value) in previous {
+        match value {
+            Some(value) => env::set_var(key, value),
+            None => env::remove_var(key),
+        }
+    }
+
+    if let Err(panic) = result {
+        panic::resume_unwind(panic);
+    }
+}
+
+/// Create a repository in a temporary directory, with an initial commit on
+/// the given branch
+pub fn init_repository(branch: &str) -> (TempDir, Repository) {
+    let dir = TempDir::new().unwrap();
+
+    let mut options = RepositoryInitOptions::new();
+    options.initial_head(branch);
+    let repository = Repository::init_opts(dir.path(), &options).unwrap();
+
+    let mut config = repository.config().unwrap();
+    config.set_str("user.name", "wr").unwrap();
+    config.set_str("user.email", "wr@example.com").unwrap();
+
+    commit(&repository, "Initial commit");
+
+    (dir, repository)
+}
+
+/// Create an empty commit on top of HEAD
+pub fn commit(repository: &Repository, message: &str) -> Oid {
+    commit_as(repository, "wr", message)
+}
+
+/// Write a file, and commit it on top of HEAD as the given author
+pub fn commit_file(repository: &Repository, author: &str, path: &str, message: &str) -> Oid {
+    let file = repository.workdir().unwrap().join(path);
+    fs::create_dir_all(file.parent().unwrap()).unwrap();
+    fs::write(&file, message).unwrap();
+
+    let mut index = repository.index().unwrap();
+    index.add_path(Path::new(path)).unwrap();
+    index.write().unwrap();
+
+    commit_as(repository, author, message)
+}
+
+fn commit_as(repository: &Repository, author: &str, message: &str) -> Oid {
+    let signature = Signature::now(author, &format!("{}@example.com", author)).unwrap();
+    let tree_id = repository.index().unwrap().write_tree().unwrap();
+    let tree = repository.find_tree(tree_id).unwrap();
+
+    let parent = repository
+        .head()
+        .ok()
+        .and_then(|head| head.peel_to_commit().ok());
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+
+    repository
+        .commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap()
+}
+
+/// Create a bare repository in a temporary directory, and register it as the
+/// "origin" remote of the given repository
+pub fn init_bare_remote(repository: &Repository) -> (TempDir, Repository) {
+    let dir = TempDir::new().unwrap();
+    let remote_repository = Repository::init_bare(dir.path()).unwrap();
+
+    repository
+        .remote("origin", dir.path().to_str().unwrap())
+        .unwrap();
+
+    (dir, remote_repository)
+}
+
+/// Create an empty commit on top of HEAD, carrying the given signature
+pub fn commit_signed(repository: &Repository, message: &str, gpg_signature: &str) -> Oid {
+    let signature = Signature::now("wr", "wr@example.com").unwrap();
+    let tree_id = repository.index().unwrap().write_tree().unwrap();
+    let tree = repository.find_tree(tree_id).unwrap();
+    let parent = repository.head().unwrap().peel_to_commit().unwrap();
+
+    let buffer = repository
+        .commit_create_buffer(&signature, &signature, message, &tree, &[&parent])
+        .unwrap();
+    let oid = repository
+        .commit_signed(buffer.as_str().unwrap(), gpg_signature, None)
+        .unwrap();
+
+    repository
+        .head()
+        .unwrap()
+        .set_target(oid, "commit signed")
+        .unwrap();
+
+    oid
+}
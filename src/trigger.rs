@@ -0,0 +1,236 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Error};
+use gitlab::{
+    api::{projects, BodyError, Endpoint, FormParams, Query},
+    Gitlab,
+};
+use http::Method;
+use serde::Deserialize;
+
+use crate::api::dump_query;
+use crate::pipeline::StatusState;
+
+/// How long a triggered pipeline may take before the deploy gives up on it
+const PIPELINE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// The settings to deploy by triggering a pipeline, instead of playing a job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trigger {
+    /// The pipeline trigger token.
+    pub token: String,
+    /// The branch or tag to run the pipeline on.
+    pub r#ref: String,
+    /// The variables passed to the pipeline.
+    pub variables: BTreeMap<String, String>,
+}
+
+/// Parse a pipeline variable, given as "KEY=VALUE"
+pub fn parse_variable(variable: &str) -> Result<(String, String), String> {
+    match variable.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("\"{}\" is not a KEY=VALUE variable", variable)),
+    }
+}
+
+/// The endpoint triggering a pipeline with a trigger token
+pub struct TriggerPipeline<'a> {
+    pub project: &'a str,
+    pub trigger: &'a Trigger,
+}
+
+impl TriggerPipeline<'_> {
+    /// The form parameters of the request
+    fn get_params(&self) -> Vec<(String, String)> {
+        let mut params = vec![
+            ("token".to_string(), self.trigger.token.clone()),
+            ("ref".to_string(), self.trigger.r#ref.clone()),
+        ];
+
+        params.extend(
+            self.trigger
+                .variables
+                .iter()
+                .map(|(key, value)| (format!("variables[{}]", key), value.clone())),
+        );
+
+        params
+    }
+}
+
+impl Endpoint for TriggerPipeline<'_> {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/trigger/pipeline",
+            self.project.replace('/', "%2F")
+        )
+        .into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+
+        for (key, value) in self.get_params() {
+            params.push(key, value);
+        }
+
+        params.into_body()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TriggeredPipeline {
+    id: u64,
+    status: StatusState,
+    web_url: String,
+}
+
+/// Trigger a pipeline, and wait for it to be over
+pub fn run_trigger(
+    gitlab: &Gitlab,
+    project: &str,
+    trigger: &Trigger,
+) -> Result<StatusState, Error> {
+    let endpoint = TriggerPipeline { project, trigger };
+    let mut pipeline: TriggeredPipeline = dump_query(endpoint, |endpoint| endpoint.query(gitlab))?;
+
+    info!(
+        "[Deploy] Pipeline {} was triggered on \"{}\" ({}).",
+        pipeline.id, trigger.r#ref, pipeline.web_url
+    );
+
+    let deadline = Instant::now() + PIPELINE_TIMEOUT;
+    while !is_pipeline_over(pipeline.status) {
+        if Instant::now() >= deadline {
+            return Err(anyhow!(
+                "[Deploy] Pipeline {} is still {:?} after {} seconds, giving up ({}).",
+                pipeline.id,
+                pipeline.status,
+                PIPELINE_TIMEOUT.as_secs(),
+                pipeline.web_url
+            ));
+        }
+
+        sleep(Duration::from_secs(1));
+
+        let pipeline_endpoint = projects::pipelines::Pipeline::builder()
+            .project(project.to_string())
+            .pipeline(pipeline.id)
+            .build()
+            .unwrap();
+        pipeline = dump_query(pipeline_endpoint, |endpoint| endpoint.query(gitlab))?;
+    }
+
+    if pipeline.status == StatusState::Success {
+        info!("[Deploy] Pipeline {} succeeded.", pipeline.id);
+    } else {
+        error!(
+            "[Deploy] Pipeline {} ended as {:?}.",
+            pipeline.id, pipeline.status
+        );
+    }
+
+    Ok(pipeline.status)
+}
+
+/// Whether a pipeline won't change its status anymore without someone
+/// stepping in: a pipeline blocked on a manual job, or scheduled, is over
+/// as far as the deploy is concerned
+fn is_pipeline_over(status: StatusState) -> bool {
+    matches!(
+        status,
+        StatusState::Success
+            | StatusState::Failed
+            | StatusState::Canceled
+            | StatusState::Skipped
+            | StatusState::Manual
+            | StatusState::Scheduled
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use gitlab::api::Endpoint;
+    use http::Method;
+
+    use crate::pipeline::StatusState;
+    use crate::trigger::{is_pipeline_over, parse_variable, Trigger, TriggerPipeline};
+
+    fn trigger() -> Trigger {
+        Trigger {
+            token: "glptt-0123".to_string(),
+            r#ref: "master".to_string(),
+            variables: BTreeMap::from([
+                ("ENVIRONMENT".to_string(), "production".to_string()),
+                ("DRY_RUN".to_string(), "0".to_string()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn parses_a_pipeline_variable() {
+        assert_eq!(
+            Ok(("DEPLOY_URL".to_string(), "https://a.b/?c=d".to_string())),
+            parse_variable("DEPLOY_URL=https://a.b/?c=d")
+        );
+        assert!(parse_variable("DEPLOY_URL").is_err());
+        assert!(parse_variable("=value").is_err());
+    }
+
+    #[test]
+    fn tells_when_a_pipeline_is_over() {
+        for status in [
+            StatusState::Success,
+            StatusState::Failed,
+            StatusState::Canceled,
+            StatusState::Skipped,
+            StatusState::Manual,
+            StatusState::Scheduled,
+        ] {
+            assert!(is_pipeline_over(status), "{:?} should be over", status);
+        }
+
+        for status in [
+            StatusState::Created,
+            StatusState::WaitingForResource,
+            StatusState::Preparing,
+            StatusState::Pending,
+            StatusState::Running,
+        ] {
+            assert!(!is_pipeline_over(status), "{:?} should not be over", status);
+        }
+    }
+
+    #[test]
+    fn assembles_the_trigger_request() {
+        let trigger = trigger();
+        let endpoint = TriggerPipeline {
+            project: "aeyoll/wr",
+            trigger: &trigger,
+        };
+
+        assert_eq!(Method::POST, endpoint.method());
+        assert_eq!("projects/aeyoll%2Fwr/trigger/pipeline", endpoint.endpoint());
+        assert_eq!(
+            vec![
+                ("token".to_string(), "glptt-0123".to_string()),
+                ("ref".to_string(), "master".to_string()),
+                ("variables[DRY_RUN]".to_string(), "0".to_string()),
+                (
+                    "variables[ENVIRONMENT]".to_string(),
+                    "production".to_string()
+                ),
+            ],
+            endpoint.get_params()
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::NaiveDate;
+use semver::Version;
+
+/// Expand the placeholders of a template with the components of a version:
+/// "{version}", "{major}", "{minor}", "{patch}", "{pre}" and "{build}"
+pub fn format_version(template: &str, version: &Version) -> Result<String, String> {
+    let mut output = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("\"{}\" has an unclosed placeholder", template))?;
+        let placeholder = &rest[start + 1..start + end];
+
+        let value = match placeholder {
+            "version" => version.to_string(),
+            "major" => version.major.to_string(),
+            "minor" => version.minor.to_string(),
+            "patch" => version.patch.to_string(),
+            "pre" => version.pre.to_string(),
+            "build" => version.build.to_string(),
+            _ => {
+                return Err(format!(
+                    "\"{{{}}}\" is not a version placeholder",
+                    placeholder
+                ))
+            }
+        };
+
+        output.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+/// Check that a version template only uses known placeholders
+pub fn parse_version_format(template: &str) -> Result<String, String> {
+    format_version(template, &Version::new(0, 0, 0))?;
+
+    Ok(template.to_string())
+}
+
+/// Expand a release name template: the "{date}" placeholder, as YYYY-MM-DD,
+/// along with the placeholders of the version
+pub fn format_release_name(
+    template: &str,
+    version: &Version,
+    date: NaiveDate,
+) -> Result<String, String> {
+    let template = template.replace("{date}", &date.format("%Y-%m-%d").to_string());
+
+    format_version(&template, version)
+}
+
+/// Check that a release name template only uses known placeholders
+pub fn parse_release_name(template: &str) -> Result<String, String> {
+    format_release_name(template, &Version::new(0, 0, 0), NaiveDate::MIN)?;
+
+    Ok(template.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use semver::Version;
+
+    use crate::version_format::{
+        format_release_name, format_version, parse_release_name, parse_version_format,
+    };
+
+    #[test]
+    fn formats_a_version_with_a_template() {
+        let version = Version::parse("1.2.3-rc.1+build.42").unwrap();
+        let format = |template: &str| format_version(template, &version).unwrap();
+
+        assert_eq!("1.2.3-rc.1+build.42", format("{version}"));
+        assert_eq!("v1.2.3", format("v{major}.{minor}.{patch}"));
+        assert_eq!("1.2", format("{major}.{minor}"));
+        assert_eq!("rc.1/build.42", format("{pre}/{build}"));
+        assert_eq!("release", format("release"));
+    }
+
+    #[test]
+    fn formats_a_stable_version_without_prerelease() {
+        let version = Version::new(2, 0, 0);
+
+        assert_eq!(
+            "v2.0.0-",
+            format_version("v{version}-{pre}", &version).unwrap()
+        );
+    }
+
+    #[test]
+    fn formats_a_release_name_with_the_date() {
+        let version = Version::new(1, 2, 0);
+        let date = NaiveDate::from_ymd_opt(2024, 5, 7).unwrap();
+        let format = |template: &str| format_release_name(template, &version, date).unwrap();
+
+        assert_eq!("1.2.0", format("{version}"));
+        assert_eq!(
+            "Release 1.2.0 (2024-05-07)",
+            format("Release {version} ({date})")
+        );
+        assert_eq!("Spring 1.2", format("Spring {major}.{minor}"));
+    }
+
+    #[test]
+    fn rejects_unknown_release_name_placeholders() {
+        assert!(parse_release_name("Release {version} ({date})").is_ok());
+        assert!(parse_release_name("Release {codename}").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_placeholders() {
+        assert!(parse_version_format("v{version}").is_ok());
+        assert!(parse_version_format("{revision}").is_err());
+        assert!(parse_version_format("v{major").is_err());
+    }
+}
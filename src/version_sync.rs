@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+use git2::{Oid, Repository};
+use semver::Version;
+
+/// Read the version from a file holding only the version number
+pub fn read_version(path: &Path) -> Result<Version, Error> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Unable to read \"{}\" ({}).", path.display(), e))?;
+
+    Version::parse(content.trim()).map_err(|e| {
+        anyhow!(
+            "\"{}\" does not hold a valid version ({}).",
+            path.display(),
+            e
+        )
+    })
+}
+
+/// Check that the version of the file matches the latest tag, warning
+/// otherwise
+pub fn verify_version(file_version: &Version, last_tag: Option<&Version>) -> bool {
+    match last_tag {
+        Some(last_tag) if last_tag != file_version => {
+            warn!(
+                "[Release] The version file is at {}, but the latest tag is {}.",
+                file_version, last_tag
+            );
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Write a version to a file
+pub fn write_version(path: &Path, version: &Version) -> Result<(), Error> {
+    fs::write(path, format!("{}\n", version))?;
+
+    Ok(())
+}
+
+/// Write the new version to the version file, and commit it on top of HEAD
+pub fn bump_version(repository: &Repository, file: &str, version: &Version) -> Result<Oid, Error> {
+    let workdir = repository
+        .workdir()
+        .ok_or_else(|| anyhow!("The repository has no working directory."))?;
+    let path = workdir.join(file);
+    let previous_version = read_version(&path)?;
+
+    write_version(&path, version)?;
+
+    let mut index = repository.index()?;
+    index.read(false)?;
+    index.add_path(Path::new(file))?;
+    index.write()?;
+
+    let tree = repository.find_tree(index.write_tree()?)?;
+    let parent = repository.head()?.peel_to_commit()?;
+    let signature = repository.signature()?;
+    let message = format!(
+        "Bump version to {}\n\n{} was at {}.",
+        version, file, previous_version
+    );
+
+    let oid = repository.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &message,
+        &tree,
+        &[&parent],
+    )?;
+
+    Ok(oid)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use semver::Version;
+
+    use crate::test_utils::{commit_file, init_repository};
+    use crate::version_sync::{bump_version, read_version, verify_version, write_version};
+
+    #[test]
+    fn reads_a_version_file() {
+        let (dir, _repository) = init_repository("main");
+        let path = dir.path().join(".version");
+
+        fs::write(&path, "1.2.3\n").unwrap();
+        assert_eq!(Version::new(1, 2, 3), read_version(&path).unwrap());
+
+        fs::write(&path, "latest").unwrap();
+        assert!(read_version(&path).is_err());
+    }
+
+    #[test]
+    fn verifies_the_version_against_the_latest_tag() {
+        let version = Version::new(1, 2, 3);
+
+        assert!(verify_version(&version, Some(&Version::new(1, 2, 3))));
+        assert!(!verify_version(&version, Some(&Version::new(1, 2, 2))));
+        assert!(verify_version(&version, None));
+    }
+
+    #[test]
+    fn writes_and_reads_back_a_version() {
+        let (dir, _repository) = init_repository("main");
+        let path = dir.path().join(".version");
+        let version = Version::parse("2.0.0-rc.1").unwrap();
+
+        write_version(&path, &version).unwrap();
+
+        assert_eq!("2.0.0-rc.1\n", fs::read_to_string(&path).unwrap());
+        assert_eq!(version, read_version(&path).unwrap());
+    }
+
+    #[test]
+    fn commits_the_bumped_version() {
+        let (_dir, repository) = init_repository("main");
+        commit_file(&repository, "wr", ".version", "1.0.0\n");
+
+        let oid = bump_version(&repository, ".version", &Version::new(1, 1, 0)).unwrap();
+
+        let commit = repository.find_commit(oid).unwrap();
+        assert_eq!(oid, repository.head().unwrap().target().unwrap());
+        assert_eq!(
+            "Bump version to 1.1.0\n\n.version was at 1.0.0.",
+            commit.message().unwrap()
+        );
+        let blob = commit
+            .tree()
+            .unwrap()
+            .get_path(Path::new(".version"))
+            .unwrap()
+            .to_object(&repository)
+            .unwrap()
+            .peel_to_blob()
+            .unwrap();
+        assert_eq!(b"1.1.0\n", blob.content());
+    }
+}
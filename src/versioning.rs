@@ -0,0 +1,94 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate};
+use semver::Version;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum, Default)]
+pub enum Versioning {
+    /// Bump the major, minor or patch number of the latest tag
+    #[default]
+    Semver,
+    /// Tag "YYYY.MM.PATCH", the patch number being reset every month
+    Calver,
+}
+
+impl FromStr for Versioning {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "semver" => Ok(Versioning::Semver),
+            "calver" => Ok(Versioning::Calver),
+            _ => Err("Unknown versioning scheme"),
+        }
+    }
+}
+
+impl fmt::Display for Versioning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Compute the next calendar version: the patch number of the latest tag is
+/// incremented within the same month, and reset on a new month
+pub fn get_next_calver(last_tag: Option<&Version>, today: NaiveDate) -> Version {
+    let year = today.year() as u64;
+    let month = today.month() as u64;
+
+    match last_tag {
+        Some(last_tag) if (last_tag.major, last_tag.minor) == (year, month) => {
+            Version::new(year, month, last_tag.patch + 1)
+        }
+        _ => Version::new(year, month, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use semver::Version;
+
+    use crate::versioning::get_next_calver;
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn increments_the_patch_within_the_same_month() {
+        let last_tag = Version::new(2024, 5, 2);
+
+        assert_eq!(
+            Version::new(2024, 5, 3),
+            get_next_calver(Some(&last_tag), day(2024, 5, 31))
+        );
+    }
+
+    #[test]
+    fn resets_the_patch_on_a_new_month() {
+        let last_tag = Version::new(2024, 5, 2);
+
+        assert_eq!(
+            Version::new(2024, 6, 0),
+            get_next_calver(Some(&last_tag), day(2024, 6, 1))
+        );
+        assert_eq!(
+            Version::new(2025, 5, 0),
+            get_next_calver(Some(&last_tag), day(2025, 5, 10))
+        );
+    }
+
+    #[test]
+    fn starts_from_the_current_month() {
+        assert_eq!(
+            Version::new(2024, 5, 0),
+            get_next_calver(None, day(2024, 5, 10))
+        );
+        assert_eq!(
+            Version::new(2024, 5, 0),
+            get_next_calver(Some(&Version::new(1, 4, 0)), day(2024, 5, 10))
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// Call a function when the run exceeds a time budget, unless the watchdog
+/// is dropped before
+pub struct Watchdog {
+    _stop: Sender<()>,
+}
+
+impl Watchdog {
+    pub fn start<F>(budget: Duration, on_timeout: F) -> Watchdog
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        thread::spawn(move || {
+            // Dropping the watchdog disconnects the channel, and stops the wait
+            if let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(budget) {
+                on_timeout();
+            }
+        });
+
+        Watchdog { _stop: stop }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use crate::watchdog::Watchdog;
+
+    #[test]
+    fn fires_when_the_budget_is_exceeded() {
+        let (sender, receiver) = mpsc::channel();
+
+        let _watchdog = Watchdog::start(Duration::from_millis(10), move || {
+            sender.send(()).unwrap();
+        });
+
+        assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn does_not_fire_once_dropped() {
+        let (sender, receiver) = mpsc::channel();
+
+        let watchdog = Watchdog::start(Duration::from_millis(100), move || {
+            sender.send(()).unwrap();
+        });
+        drop(watchdog);
+
+        assert!(receiver.recv_timeout(Duration::from_millis(300)).is_err());
+    }
+}